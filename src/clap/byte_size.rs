@@ -0,0 +1,209 @@
+//! Parsing byte sizes like `8GiB` with a [`clap::builder::Arg::value_parser`].
+
+use std::fmt::Display;
+use std::ops::Range;
+
+use clap::builder::StringValueParser;
+use clap::builder::TypedValueParser;
+use clap::builder::ValueParserFactory;
+use miette::LabeledSpan;
+use miette::MietteDiagnostic;
+use miette::Report;
+
+use super::value_validation_error;
+
+/// A size in bytes, like `8GiB`.
+///
+/// Sizes are written as a number, optionally with a decimal point, followed by an optional unit.
+/// `KiB`, `MiB`, `GiB`, and `TiB` (or `K`, `M`, `G`, and `T`) are powers of 1024, and `KB`, `MB`,
+/// `GB`, and `TB` are powers of 1000. Units are case-insensitive.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct ByteSize(pub u64);
+
+impl ByteSize {
+    /// Get the size in bytes.
+    pub fn bytes(self) -> u64 {
+        self.0
+    }
+}
+
+impl Display for ByteSize {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+        if self.0 < 1024 {
+            return write!(f, "{} B", self.0);
+        }
+        let mut size = self.0 as f64;
+        let mut unit = UNITS[0];
+        for next_unit in UNITS {
+            size /= 1024.0;
+            unit = next_unit;
+            if size < 1024.0 {
+                break;
+            }
+        }
+        write!(f, "{size:.1} {unit}")
+    }
+}
+
+/// An error from parsing a [`ByteSize`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ByteSizeError {
+    message: &'static str,
+    help: Option<&'static str>,
+    span: Option<Range<usize>>,
+}
+
+impl ByteSizeError {
+    fn new(message: &'static str) -> Self {
+        Self {
+            message,
+            help: None,
+            span: None,
+        }
+    }
+
+    fn help(mut self, help: &'static str) -> Self {
+        self.help = Some(help);
+        self
+    }
+
+    fn span(mut self, span: Range<usize>) -> Self {
+        self.span = Some(span);
+        self
+    }
+}
+
+/// Parse a [`ByteSize`].
+fn parse_byte_size(input: &str) -> Result<ByteSize, ByteSizeError> {
+    let trimmed = input.trim_end();
+    if trimmed.trim_start().is_empty() {
+        return Err(ByteSizeError::new("No size given"));
+    }
+    let start = input.len() - input.trim_start().len();
+
+    let number_end = trimmed[start..]
+        .find(|c: char| !c.is_ascii_digit() && c != '.')
+        .map(|offset| start + offset)
+        .unwrap_or(trimmed.len());
+    let number = &trimmed[start..number_end];
+    if number.is_empty() {
+        return Err(ByteSizeError::new("Expected number").span(start..start + 1));
+    }
+    let number: f64 = number
+        .parse()
+        .map_err(|_| ByteSizeError::new("Invalid number").span(start..number_end))?;
+
+    let unit = trimmed[number_end..].trim_start();
+    let unit_start = trimmed.len() - unit.len();
+    let multiplier: u64 = match unit.to_ascii_lowercase().as_str() {
+        "" | "b" => 1,
+        "k" | "kib" => 1 << 10,
+        "m" | "mib" => 1 << 20,
+        "g" | "gib" => 1 << 30,
+        "t" | "tib" => 1 << 40,
+        "kb" => 1_000,
+        "mb" => 1_000_000,
+        "gb" => 1_000_000_000,
+        "tb" => 1_000_000_000_000,
+        _ => {
+            return Err(ByteSizeError::new("Unknown unit")
+                .help("Valid units include `MiB`, `GiB`, `MB`, and `GB`")
+                .span(unit_start..trimmed.len()));
+        }
+    };
+
+    let bytes = number * multiplier as f64;
+    if bytes >= u64::MAX as f64 {
+        return Err(ByteSizeError::new("Size is too large"));
+    }
+    Ok(ByteSize(bytes.round() as u64))
+}
+
+/// Adapter for parsing [`ByteSize`] with a [`clap::builder::Arg::value_parser`].
+#[derive(Default, Clone)]
+pub struct ByteSizeValueParser {
+    inner: StringValueParser,
+}
+
+impl TypedValueParser for ByteSizeValueParser {
+    type Value = ByteSize;
+
+    fn parse_ref(
+        &self,
+        cmd: &clap::Command,
+        arg: Option<&clap::Arg>,
+        value: &std::ffi::OsStr,
+    ) -> Result<Self::Value, clap::Error> {
+        self.inner.parse_ref(cmd, arg, value).and_then(|str_value| {
+            parse_byte_size(&str_value).map_err(|err| {
+                let diagnostic = Report::new(MietteDiagnostic {
+                    message: err.message.to_owned(),
+                    code: None,
+                    severity: None,
+                    help: err.help.map(ToOwned::to_owned),
+                    url: None,
+                    labels: err
+                        .span
+                        .map(|span| vec![LabeledSpan::at(span, err.message)]),
+                })
+                .with_source_code(str_value.clone());
+                value_validation_error(arg, &str_value, format!("{diagnostic:?}"))
+            })
+        })
+    }
+}
+
+impl ValueParserFactory for ByteSize {
+    type Parser = ByteSizeValueParser;
+
+    fn value_parser() -> Self::Parser {
+        Self::Parser::default()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_byte_size() {
+        assert_eq!(parse_byte_size("1234"), Ok(ByteSize(1234)));
+        assert_eq!(parse_byte_size("8GiB"), Ok(ByteSize(8 << 30)));
+        assert_eq!(parse_byte_size("8g"), Ok(ByteSize(8 << 30)));
+        assert_eq!(parse_byte_size("512 MiB"), Ok(ByteSize(512 << 20)));
+        assert_eq!(parse_byte_size("1.5GB"), Ok(ByteSize(1_500_000_000)));
+        assert_eq!(parse_byte_size("2kb"), Ok(ByteSize(2_000)));
+    }
+
+    #[test]
+    fn test_parse_byte_size_errors() {
+        assert_eq!(
+            parse_byte_size(""),
+            Err(ByteSizeError::new("No size given"))
+        );
+        assert_eq!(
+            parse_byte_size("GiB"),
+            Err(ByteSizeError::new("Expected number").span(0..1))
+        );
+        assert_eq!(
+            parse_byte_size("1.2.3G"),
+            Err(ByteSizeError::new("Invalid number").span(0..5))
+        );
+        assert_eq!(
+            parse_byte_size("8 gigs").map_err(|err| err.span),
+            Err(Some(2..6))
+        );
+    }
+
+    #[test]
+    fn test_display_byte_size() {
+        assert_eq!(ByteSize(512).to_string(), "512 B");
+        assert_eq!(ByteSize(1536).to_string(), "1.5 KiB");
+        assert_eq!(ByteSize(8 << 30).to_string(), "8.0 GiB");
+        assert_eq!(ByteSize(3 << 40).to_string(), "3.0 TiB");
+    }
+}
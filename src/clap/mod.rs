@@ -1,5 +1,6 @@
 //! Adapters for parsing [`clap`] arguments to various types.
 
+mod byte_size;
 mod camino;
 mod error_message;
 mod fmt_span;
@@ -7,6 +8,7 @@ mod humantime;
 mod rust_backtrace;
 
 pub use self::humantime::DurationValueParser;
+pub use byte_size::ByteSize;
 pub use error_message::value_validation_error;
 pub use fmt_span::FmtSpanParserFactory;
 pub use rust_backtrace::RustBacktrace;
@@ -7,9 +7,15 @@ use clap::Parser;
 use clap_complete::Shell;
 use tracing_subscriber::fmt::format::FmtSpan;
 
+use crate::clap::ByteSize;
 use crate::clap::FmtSpanParserFactory;
 use crate::clap::RustBacktrace;
 use crate::clonable_command::ClonableCommand;
+use crate::ghci::DisplayMode;
+use crate::ghci::ErrorLogFormat;
+use crate::ghci::GhciCommand;
+use crate::ghci::ModuleGraphFormat;
+use crate::ghci::TimingsFormat;
 use crate::ignore::GlobMatcher;
 use crate::normal_path::NormalPath;
 
@@ -18,7 +24,7 @@ use crate::normal_path::NormalPath;
 ///
 /// ## Examples
 ///
-/// Load `cabal v2-repl` and watch for changes in `src`:
+/// Detect the right `ghci` command for the project and watch for changes in `src`:
 ///
 ///     ghciwatch
 ///
@@ -60,6 +66,11 @@ pub struct Opts {
     /// This is used to launch the underlying `ghci` session that `ghciwatch` controls.
     ///
     /// May contain quoted arguments which will be parsed in a `sh`-like manner.
+    ///
+    /// If not given, the command is detected like `ghcid` does: `stack ghci` if `stack.yaml`
+    /// exists, `ghci` if `.ghci` exists, `cabal repl` if a `.cabal` file exists (with a target if
+    /// there are multiple components), `hpack` and then `cabal repl` if only `package.yaml`
+    /// exists, and `cabal repl` otherwise.
     #[arg(long, value_name = "SHELL_COMMAND")]
     pub command: Option<ClonableCommand>,
 
@@ -71,10 +82,68 @@ pub struct Opts {
 
     /// A file to write compilation errors to.
     ///
-    /// The output format is compatible with `ghcid`'s `--outputfile` option.
+    /// By default, the output format is compatible with `ghcid`'s `--outputfile` option. Use
+    /// `--error-file-format` to write JSON or SARIF instead.
     #[arg(long, alias = "outputfile", alias = "errors")]
     pub error_file: Option<Utf8PathBuf>,
 
+    /// The format to write the `--error-file` in.
+    #[arg(long, value_name = "FORMAT", default_value = "ghcid")]
+    pub error_file_format: ErrorLogFormat,
+
+    /// Hide diagnostics matching this rule from the terminal summary and the `--error-file`.
+    ///
+    /// A rule can be a warning flag like `-Wmissing-export-lists`, an error code like
+    /// `GHC-83865`, or a path glob like `src/Generated/**`. Path globs have the same semantics as
+    /// `--reload-glob`. Path globs also hide test failures in matching files.
+    ///
+    /// Hidden diagnostics are still counted. `ghci`'s own output isn't filtered.
+    ///
+    /// Can be given multiple times.
+    #[arg(
+        long = "hide-diagnostics",
+        value_name = "RULE",
+        allow_hyphen_values = true
+    )]
+    pub hide_diagnostics: Vec<String>,
+
+    /// A file to write the import graph of the loaded modules to.
+    ///
+    /// The graph is built from `:show modules` and the `import` declarations in each module, and
+    /// is rewritten after every reload. Use `--module-graph-format` to choose between Graphviz DOT
+    /// and JSON.
+    #[arg(long, value_name = "PATH")]
+    pub module_graph: Option<Utf8PathBuf>,
+
+    /// The format to write the `--module-graph` in.
+    #[arg(long, value_name = "FORMAT", default_value = "dot")]
+    pub module_graph_format: ModuleGraphFormat,
+
+    /// A file to write how long each module took to compile to.
+    ///
+    /// The file lists the most recent compilation time of every module compiled in the session,
+    /// slowest first. Use `--timings-file-format` to choose between CSV and JSON.
+    #[arg(long, value_name = "PATH")]
+    pub timings_file: Option<Utf8PathBuf>,
+
+    /// The format to write the `--timings-file` in.
+    #[arg(long, value_name = "FORMAT", default_value = "csv")]
+    pub timings_file_format: TimingsFormat,
+
+    /// A file to remember the modules `ghciwatch` has `:add`ed to `ghci` in.
+    ///
+    /// Modules which aren't loaded when `ghci` starts, like new modules or every module with
+    /// `cabal repl --repl-no-load`, are `:add`ed when they change. They're always added again when
+    /// `ghci` restarts; with a state file, they're also added again when `ghciwatch` restarts.
+    #[arg(long, value_name = "PATH")]
+    pub state_file: Option<Utf8PathBuf>,
+
+    /// How many of the slowest modules to log after each reload.
+    ///
+    /// Set to 0 to disable the report. Nothing is reported if only one module was compiled.
+    #[arg(long, value_name = "N", default_value = "5")]
+    pub slowest_modules: usize,
+
     /// Evaluate Haskell code in comments.
     ///
     /// This parses line commands starting with `-- $>` or multiline commands delimited by `{- $>`
@@ -86,12 +155,101 @@ pub struct Opts {
     #[arg(long)]
     pub clear: bool,
 
+    /// How to display compilation results.
+    ///
+    /// `pretty` hides `ghci`'s compiler output and shows each diagnostic after compilation
+    /// finishes, errors first, with a snippet of the current source. File names and error codes
+    /// are hyperlinked in terminals which support it.
+    ///
+    /// `ghcid` hides `ghci`'s compiler output and, after compilation finishes, clears the screen
+    /// and shows a summary which fits in the terminal: `All good (N modules)` if compilation
+    /// succeeded, then errors and warnings, starting with the most recently modified files.
+    #[arg(long, value_name = "MODE", default_value = "ghci")]
+    pub display: DisplayMode,
+
     /// Don't interrupt reloads when files change.
     ///
     /// Depending on your workflow, `ghciwatch` may feel more responsive with this set.
     #[arg(long)]
     pub no_interrupt_reloads: bool,
 
+    /// Restart `ghci` when it uses more than this much memory.
+    ///
+    /// Memory usage is measured between reloads, as the total resident set size of the `ghci`
+    /// process and its children, and `ghci` is restarted before the next reload. Sizes may have a
+    /// unit, like `8GiB` or `512MB`. Only supported on Linux.
+    #[arg(long, value_name = "SIZE")]
+    pub max_memory: Option<ByteSize>,
+
+    /// Restart `ghci` after this many reloads.
+    ///
+    /// Long-running `ghci` sessions can slow down or leak memory; this restarts them
+    /// periodically, between reloads.
+    #[arg(long, value_name = "RELOADS", value_parser = clap::value_parser!(u64).range(1..))]
+    pub restart_every: Option<u64>,
+
+    /// Pass lines typed in the terminal through to `ghci` as commands.
+    ///
+    /// This lets you run commands like `:type foo` in the session `ghciwatch` has already loaded.
+    /// Commands are queued while `ghci` is busy and run between reloads.
+    #[arg(long, conflicts_with_all = ["tui", "lsp"])]
+    pub repl: bool,
+
+    /// Run a long-running `ghci` command, like `Main.main`, after each successful compilation.
+    ///
+    /// This is useful for servers and other apps which run until they're stopped. When files
+    /// change, the command is interrupted with `Ctrl-C` (`SIGINT`) before reloading, and it's run
+    /// again once compilation succeeds. Commands typed with `--repl` also interrupt it, and it's
+    /// run again once they finish.
+    #[arg(long, value_name = "GHCI_CMD")]
+    pub run: Option<GhciCommand>,
+
+    /// Run tests in a separate `ghci` session, so that reloads never wait for tests to finish.
+    ///
+    /// The test session is started once the main session first compiles successfully, and it's
+    /// reloaded to run the `--test-ghci` and `--test-shell` commands after each successful
+    /// compilation after that. If the main session compiles successfully again while tests are
+    /// running, they're interrupted and run again. Test results are reported with the main session's
+    /// compilation results, once it's idle.
+    #[arg(long)]
+    pub test_session: bool,
+
+    /// Keep a standby `ghci` session loaded in the background, to switch to when `ghci` restarts.
+    ///
+    /// This makes restarts nearly instant: the standby session only reloads the files which
+    /// changed since it started, and then a new standby session is started in the background. The
+    /// standby session isn't used if `.cabal`, `.ghci`, or `--restart-glob` files changed or
+    /// modules were removed since it started. This uses twice as much memory.
+    #[arg(long)]
+    pub standby: bool,
+
+    /// Listen for requests on a Unix domain socket at the given path.
+    ///
+    /// Requests are newline-delimited and can be any of `reload`, `restart`, `test`, `status`, or
+    /// `shutdown`. Each request gets a single-line JSON response.
+    ///
+    /// If no path is given, `.ghciwatch.sock` in the current directory is used.
+    #[arg(
+        long,
+        value_name = "PATH",
+        num_args = 0..=1,
+        default_missing_value = ".ghciwatch.sock"
+    )]
+    pub control_socket: Option<Utf8PathBuf>,
+
+    /// Run as a language server, publishing diagnostics over `stdin` and `stdout`.
+    ///
+    /// In this mode, `ghci` output is written to `stderr` instead of `stdout`.
+    #[arg(long, conflicts_with_all = ["tui", "clear"])]
+    pub lsp: bool,
+
+    /// Write lifecycle events to the given path as newline-delimited JSON.
+    ///
+    /// Events include file changes, reloads and restarts, compiled modules, diagnostics, and
+    /// hooks. See the documentation for the schema.
+    #[arg(long, value_name = "PATH")]
+    pub events_file: Option<Utf8PathBuf>,
+
     /// Enable TUI mode (experimental).
     #[arg(long, hide = true)]
     pub tui: bool,
@@ -118,6 +276,10 @@ pub struct Opts {
     #[command(flatten)]
     pub watch: WatchOpts,
 
+    /// Options for timing out commands which never finish.
+    #[command(flatten)]
+    pub timeouts: TimeoutOpts,
+
     /// Options to modify logging and error-handling behavior.
     #[command(flatten)]
     pub logging: LoggingOpts,
@@ -171,11 +333,10 @@ pub struct WatchOpts {
 
     /// Restart the `ghci` session when paths matching this glob change.
     ///
-    /// By default, only changes to `.cabal` or `.ghci` files or Haskell source files being
-    /// moved/removed will trigger restarts.
+    /// By default, only changes to `.cabal` or `.ghci` files will trigger restarts.
     ///
-    /// Due to [a `ghci` bug][1], the `ghci` session must be restarted when Haskell modules are removed
-    /// or renamed.
+    /// Haskell modules which are removed or renamed are removed from the `ghci` session with
+    /// `:unadd`. If that runs into [a `ghci` bug][1], the session is restarted instead.
     ///
     /// See `--reload-globs` for more details.
     ///
@@ -198,6 +359,41 @@ impl WatchOpts {
     }
 }
 
+/// Options for timing out `ghci` commands which never finish.
+#[derive(Debug, Clone, clap::Args)]
+#[clap(next_help_heading = "Timeout options")]
+pub struct TimeoutOpts {
+    /// Interrupt `ghci` if a reload doesn't finish compiling within this duration.
+    ///
+    /// The timeout applies to each `:reload`, `:add`, and `:unadd` command. When it elapses,
+    /// `ghci` is interrupted with `Ctrl-C` (`SIGINT`) and the timeout is reported as an error
+    /// diagnostic, which is included in the error log.
+    #[arg(long, value_name = "DURATION", value_parser = crate::clap::DurationValueParser::default())]
+    pub compile_timeout: Option<Duration>,
+
+    /// Interrupt `ghci` if an eval command doesn't finish within this duration.
+    ///
+    /// See `--compile-timeout` for details.
+    #[arg(long, value_name = "DURATION", value_parser = crate::clap::DurationValueParser::default())]
+    pub eval_timeout: Option<Duration>,
+
+    /// Interrupt `ghci` if a `--test-ghci` command doesn't finish within this duration.
+    ///
+    /// See `--compile-timeout` for details.
+    #[arg(long, value_name = "DURATION", value_parser = crate::clap::DurationValueParser::default())]
+    pub test_timeout: Option<Duration>,
+
+    /// After a timeout, restart `ghci` if it doesn't respond to being interrupted within this
+    /// duration.
+    #[arg(
+        long,
+        default_value = "5s",
+        value_name = "DURATION",
+        value_parser = crate::clap::DurationValueParser::default(),
+    )]
+    pub interrupt_grace_period: Duration,
+}
+
 // TODO: Possibly set `RUST_LIB_BACKTRACE` from `RUST_BACKTRACE` as well, so that `full`
 // enables source snippets for spantraces?
 // https://docs.rs/color-eyre/latest/color_eyre/#multiple-report-format-verbosity-levels
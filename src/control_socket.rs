@@ -0,0 +1,311 @@
+//! A Unix domain socket for controlling a running `ghciwatch` session.
+//!
+//! Clients connect to the socket and write newline-delimited requests. Each request gets a
+//! single-line JSON response.
+
+use std::fmt::Display;
+use std::os::unix::fs::FileTypeExt;
+use std::str::FromStr;
+
+use camino::Utf8PathBuf;
+use miette::miette;
+use miette::Context;
+use miette::IntoDiagnostic;
+use serde::Serialize;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::net::UnixListener;
+use tokio::net::UnixStream;
+use tokio::sync::mpsc;
+use tokio::sync::watch;
+use tokio::task::JoinSet;
+use tracing::instrument;
+
+use crate::cli::Opts;
+use crate::ghci::manager::GhciEvent;
+use crate::ghci::GhciOpts;
+use crate::ghci::GhciStatus;
+use crate::shutdown::ShutdownHandle;
+
+/// Options for [`run_control_socket`]. This is like a lower-effort builder interface, mostly
+/// provided because Rust tragically lacks named arguments.
+pub struct ControlSocketOpts {
+    /// The path to listen on.
+    pub path: Utf8PathBuf,
+    /// Receiver for the `ghci` session's status, used to answer `status` requests.
+    pub status: watch::Receiver<GhciStatus>,
+}
+
+impl ControlSocketOpts {
+    /// Construct options for [`run_control_socket`] from parsed command-line interface arguments
+    /// as [`Opts`].
+    ///
+    /// Returns `None` if the control socket isn't enabled.
+    pub fn from_cli(opts: &Opts, ghci_opts: &GhciOpts) -> Option<Self> {
+        opts.control_socket.as_ref().map(|path| Self {
+            path: path.clone(),
+            status: ghci_opts.status.subscribe(),
+        })
+    }
+}
+
+/// A request read from the control socket.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Request {
+    /// Reload the `ghci` session, even if no files have changed.
+    Reload,
+    /// Restart the `ghci` session.
+    Restart,
+    /// Run the test hooks.
+    Test,
+    /// Report the session's [`GhciStatus`].
+    Status,
+    /// Shut down `ghciwatch`.
+    Shutdown,
+}
+
+impl FromStr for Request {
+    type Err = miette::Report;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "reload" => Ok(Self::Reload),
+            "restart" => Ok(Self::Restart),
+            "test" => Ok(Self::Test),
+            "status" => Ok(Self::Status),
+            "shutdown" => Ok(Self::Shutdown),
+            other => Err(miette!(
+                "Unknown request {other:?}; expected one of `reload`, `restart`, `test`, `status`, or `shutdown`"
+            )),
+        }
+    }
+}
+
+impl Display for Request {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Request::Reload => write!(f, "reload"),
+            Request::Restart => write!(f, "restart"),
+            Request::Test => write!(f, "test"),
+            Request::Status => write!(f, "status"),
+            Request::Shutdown => write!(f, "shutdown"),
+        }
+    }
+}
+
+/// A response written to the control socket, serialized as a single line of JSON.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "response", rename_all = "kebab-case")]
+enum Response {
+    /// The request was accepted.
+    Ok,
+    /// The `ghci` session's status.
+    Status(GhciStatus),
+    /// The request failed.
+    Error {
+        /// A description of the failure.
+        message: String,
+    },
+}
+
+/// Listen on a Unix domain socket for requests and forward them to the `ghci` session.
+#[instrument(level = "debug", skip_all)]
+pub async fn run_control_socket(
+    mut handle: ShutdownHandle,
+    ghci_sender: mpsc::Sender<GhciEvent>,
+    opts: ControlSocketOpts,
+) -> miette::Result<()> {
+    remove_stale_socket(&opts.path).await?;
+    let listener = UnixListener::bind(&opts.path)
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed to listen on control socket {}", opts.path))?;
+    tracing::info!(path = %opts.path, "Listening on control socket");
+
+    let mut connections = JoinSet::new();
+    loop {
+        tokio::select! {
+            _ = handle.on_shutdown_requested() => {
+                break;
+            }
+            accepted = listener.accept() => {
+                let (stream, _address) = accepted
+                    .into_diagnostic()
+                    .wrap_err("Failed to accept control socket connection")?;
+                tracing::debug!("Accepted control socket connection");
+                connections.spawn(handle_connection(
+                    stream,
+                    handle.clone(),
+                    ghci_sender.clone(),
+                    opts.status.clone(),
+                ));
+            }
+            Some(result) = connections.join_next() => {
+                match result {
+                    Ok(Ok(())) => {}
+                    Ok(Err(err)) => {
+                        tracing::debug!("Control socket connection failed: {err:?}");
+                    }
+                    Err(err) => {
+                        tracing::debug!("Control socket connection task failed: {err}");
+                    }
+                }
+            }
+        }
+    }
+
+    connections.shutdown().await;
+    if let Err(err) = tokio::fs::remove_file(&opts.path).await {
+        tracing::debug!(path = %opts.path, "Failed to remove control socket: {err}");
+    }
+
+    Ok(())
+}
+
+/// If a socket from a previous `ghciwatch` session was left behind, remove it so we can bind the
+/// path again.
+async fn remove_stale_socket(path: &Utf8PathBuf) -> miette::Result<()> {
+    let metadata = match tokio::fs::symlink_metadata(path).await {
+        Ok(metadata) => metadata,
+        // Nothing to remove.
+        Err(_) => return Ok(()),
+    };
+
+    if !metadata.file_type().is_socket() {
+        return Err(miette!(
+            "Control socket path {path} already exists and is not a socket"
+        ));
+    }
+
+    if UnixStream::connect(path).await.is_ok() {
+        return Err(miette!(
+            "Another process is already listening on control socket {path}"
+        ));
+    }
+
+    tracing::debug!(%path, "Removing stale control socket");
+    tokio::fs::remove_file(path)
+        .await
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed to remove stale control socket {path}"))
+}
+
+#[instrument(level = "debug", skip_all)]
+async fn handle_connection(
+    stream: UnixStream,
+    shutdown: ShutdownHandle,
+    ghci_sender: mpsc::Sender<GhciEvent>,
+    status: watch::Receiver<GhciStatus>,
+) -> miette::Result<()> {
+    let (reader, mut writer) = stream.into_split();
+    let mut lines = BufReader::new(reader).lines();
+
+    while let Some(line) = lines.next_line().await.into_diagnostic()? {
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let response = match line.parse::<Request>() {
+            Ok(request) => {
+                tracing::info!("Received `{request}` request on control socket");
+                respond(request, &shutdown, &ghci_sender, &status).await
+            }
+            Err(err) => Response::Error {
+                message: err.to_string(),
+            },
+        };
+
+        let mut response = serde_json::to_string(&response).into_diagnostic()?;
+        response.push('\n');
+        writer
+            .write_all(response.as_bytes())
+            .await
+            .into_diagnostic()?;
+    }
+
+    Ok(())
+}
+
+async fn respond(
+    request: Request,
+    shutdown: &ShutdownHandle,
+    ghci_sender: &mpsc::Sender<GhciEvent>,
+    status: &watch::Receiver<GhciStatus>,
+) -> Response {
+    let event = match request {
+        Request::Reload => GhciEvent::Reload {
+            events: Default::default(),
+            force: true,
+        },
+        Request::Restart => GhciEvent::Restart,
+        Request::Test => GhciEvent::Test,
+        Request::Status => {
+            return Response::Status(status.borrow().clone());
+        }
+        Request::Shutdown => {
+            return match shutdown.request_shutdown() {
+                Ok(()) => Response::Ok,
+                Err(err) => Response::Error {
+                    message: format!("Failed to request shutdown: {err}"),
+                },
+            };
+        }
+    };
+
+    match ghci_sender.send(event).await {
+        Ok(()) => Response::Ok,
+        Err(err) => Response::Error {
+            message: format!("Failed to send request to ghci: {err}"),
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::ghci::GhciState;
+
+    #[test]
+    fn test_parse_request() {
+        assert_eq!("reload".parse::<Request>().unwrap(), Request::Reload);
+        assert_eq!("restart\n".parse::<Request>().unwrap(), Request::Restart);
+        assert_eq!("  test ".parse::<Request>().unwrap(), Request::Test);
+        assert_eq!("status".parse::<Request>().unwrap(), Request::Status);
+        assert_eq!("shutdown".parse::<Request>().unwrap(), Request::Shutdown);
+
+        // Negative cases.
+        assert!("".parse::<Request>().is_err());
+        assert!("Reload".parse::<Request>().is_err());
+        assert!("reload now".parse::<Request>().is_err());
+    }
+
+    #[test]
+    fn test_serialize_response() {
+        assert_eq!(
+            serde_json::to_string(&Response::Ok).unwrap(),
+            r#"{"response":"ok"}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Response::Status(GhciStatus {
+                state: GhciState::Idle,
+                result: Some(crate::ghci::parse::CompilationResult::Ok),
+                modules_loaded: Some(3),
+                errors: 0,
+                warnings: 1,
+                tests: None,
+            }))
+            .unwrap(),
+            r#"{"response":"status","state":"idle","result":"ok","modules_loaded":3,"errors":0,"warnings":1}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&Response::Error {
+                message: "Oh no".to_owned()
+            })
+            .unwrap(),
+            r#"{"response":"error","message":"Oh no"}"#
+        );
+    }
+}
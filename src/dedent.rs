@@ -0,0 +1,31 @@
+/// Remove the common indentation from `lines` and join them with newlines.
+///
+/// Blank lines don't count towards the common indentation, and trailing whitespace is removed.
+pub(crate) fn dedent(lines: &[&str]) -> String {
+    let indent = lines
+        .iter()
+        .filter(|line| !line.trim().is_empty())
+        .map(|line| line.len() - line.trim_start().len())
+        .min()
+        .unwrap_or(0);
+
+    lines
+        .iter()
+        .map(|line| line.get(indent..).unwrap_or("").trim_end())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_dedent() {
+        assert_eq!(dedent(&[]), "");
+        assert_eq!(dedent(&["    Oh", "", "      no  "]), "Oh\n\n  no");
+        assert_eq!(dedent(&["Oh", "  no"]), "Oh\n  no");
+    }
+}
@@ -7,10 +7,12 @@ use camino::Utf8PathBuf;
 use miette::IntoDiagnostic;
 use notify_debouncer_full::notify::EventKind;
 use notify_debouncer_full::DebouncedEvent;
+use serde::Serialize;
 
 /// A set of filesystem events that `ghci` will need to respond to. Due to the way that `ghci` is,
 /// we need to divide these into a few different classes so that we can respond appropriately.
-#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(tag = "kind", content = "path", rename_all = "lowercase")]
 pub enum FileEvent {
     /// Existing files that are modified, or new files that are created.
     ///
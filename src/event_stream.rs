@@ -0,0 +1,383 @@
+//! A newline-delimited JSON stream of lifecycle events, for editor plugins, status bars, and other
+//! external tools.
+//!
+//! Unlike the `--log-json` output, which dumps internal tracing spans, the events written here
+//! follow a documented schema, versioned with [`SCHEMA_VERSION`].
+
+use std::process::ExitStatus;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use camino::Utf8PathBuf;
+use miette::Context;
+use miette::IntoDiagnostic;
+use serde::Serialize;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tokio::sync::broadcast;
+use tracing::instrument;
+
+use crate::cli::Opts;
+use crate::event_filter::FileEvent;
+use crate::ghci::parse::CompilationResult;
+use crate::ghci::parse::CompilationSummary;
+use crate::ghci::parse::GhcDiagnostic;
+use crate::ghci::parse::GhcMessage;
+use crate::ghci::parse::TestMessage;
+use crate::ghci::parse::TestSummary;
+use crate::ghci::GhciOpts;
+use crate::ghci::GhciReloadKind;
+use crate::hooks::Command;
+use crate::hooks::Hook;
+use crate::hooks::LifecycleEvent;
+use crate::shutdown::ShutdownHandle;
+
+/// The version of the event schema. This is incremented whenever an existing event or field is
+/// changed or removed; new events and fields may be added without changing the version.
+pub const SCHEMA_VERSION: u32 = 1;
+
+/// How many events can be buffered before a slow reader starts missing them.
+const EVENT_CAPACITY: usize = 1024;
+
+/// An event in the `ghciwatch` lifecycle.
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "event", rename_all = "kebab-case")]
+pub enum StreamEvent {
+    /// Changed files were received from the file watcher.
+    FilesChanged {
+        /// The file changes.
+        changes: Vec<FileEvent>,
+    },
+    /// The kind of reload needed to respond to changes was chosen.
+    ReloadKind {
+        /// The kind of reload.
+        kind: GhciReloadKind,
+    },
+    /// The `ghci` session is being restarted.
+    Restart,
+    /// A module was compiled.
+    Compiling {
+        /// The module's name.
+        module: String,
+        /// The module's path.
+        path: Utf8PathBuf,
+    },
+    /// The compiler emitted an error or warning.
+    Diagnostic(GhcDiagnostic),
+    /// Compilation finished with a summary.
+    Summary(CompilationSummary),
+    /// A test suite finished with a summary.
+    TestSummary(TestSummary),
+    /// A startup, reload, or restart finished, including running hooks.
+    CompilationFinished {
+        /// `startup`, `reload`, or `restart`.
+        lifecycle: &'static str,
+        /// The result of compilation, if `ghci` printed a summary.
+        result: Option<CompilationResult>,
+        /// How long compilation took, in milliseconds.
+        duration_ms: u128,
+    },
+    /// A lifecycle hook started.
+    HookStarted {
+        /// The hook, like `before-reload` or `test`.
+        hook: String,
+        /// `ghci` or `shell`.
+        kind: String,
+        /// The command being run.
+        command: String,
+    },
+    /// A lifecycle hook finished.
+    HookFinished {
+        /// The hook, like `before-reload` or `test`.
+        hook: String,
+        /// `ghci` or `shell`.
+        kind: String,
+        /// The command that was run.
+        command: String,
+        /// The shell command's exit code. This is `null` for `ghci` commands, asynchronous shell
+        /// commands, and shell commands killed by a signal.
+        exit_code: Option<i32>,
+    },
+    /// An eval command was run.
+    Eval {
+        /// The file containing the eval command.
+        path: Utf8PathBuf,
+        /// The 1-based line number of the eval command.
+        line: usize,
+        /// The command being evaluated.
+        command: String,
+    },
+}
+
+impl StreamEvent {
+    /// Get the event for a [`GhcMessage`], if any.
+    pub fn from_ghc_message(message: &GhcMessage) -> Option<Self> {
+        match message {
+            GhcMessage::Compiling(module) => Some(Self::Compiling {
+                module: module.name.clone(),
+                path: module.path.clone(),
+            }),
+            GhcMessage::Diagnostic(diagnostic) => Some(Self::Diagnostic(diagnostic.clone())),
+            GhcMessage::Summary(summary) => Some(Self::Summary(*summary)),
+            _ => None,
+        }
+    }
+
+    /// Get the event for a [`TestMessage`]. Test failures are reported as diagnostics.
+    pub fn from_test_message(message: &TestMessage) -> Self {
+        match message {
+            TestMessage::Failure(failure) => Self::Diagnostic(failure.clone().into()),
+            TestMessage::Summary(summary) => Self::TestSummary(*summary),
+        }
+    }
+
+    /// Construct a [`StreamEvent::CompilationFinished`] event.
+    pub fn compilation_finished(
+        event: LifecycleEvent,
+        result: Option<CompilationResult>,
+        duration: Duration,
+    ) -> Self {
+        Self::CompilationFinished {
+            lifecycle: event.event_name(),
+            result,
+            duration_ms: duration.as_millis(),
+        }
+    }
+
+    /// Construct a [`StreamEvent::HookStarted`] event.
+    pub fn hook_started(hook: &Hook<Command>) -> Self {
+        Self::HookStarted {
+            hook: hook.to_string(),
+            kind: hook.command.kind().to_string(),
+            command: hook.command.to_string(),
+        }
+    }
+
+    /// Construct a [`StreamEvent::HookFinished`] event.
+    pub fn hook_finished(hook: &Hook<Command>, status: Option<ExitStatus>) -> Self {
+        Self::HookFinished {
+            hook: hook.to_string(),
+            kind: hook.command.kind().to_string(),
+            command: hook.command.to_string(),
+            exit_code: status.and_then(|status| status.code()),
+        }
+    }
+}
+
+/// A [`StreamEvent`] and when it happened.
+#[derive(Debug, Clone)]
+pub struct EventRecord {
+    timestamp: SystemTime,
+    event: StreamEvent,
+}
+
+/// The serialized form of an [`EventRecord`].
+#[derive(Serialize)]
+struct SerializedRecord<'a> {
+    version: u32,
+    timestamp: String,
+    #[serde(flatten)]
+    event: &'a StreamEvent,
+}
+
+impl EventRecord {
+    fn to_json_line(&self) -> miette::Result<String> {
+        let mut line = serde_json::to_string(&SerializedRecord {
+            version: SCHEMA_VERSION,
+            timestamp: humantime::format_rfc3339_millis(self.timestamp).to_string(),
+            event: &self.event,
+        })
+        .into_diagnostic()?;
+        line.push('\n');
+        Ok(line)
+    }
+}
+
+/// A clonable sender for [`StreamEvent`]s.
+///
+/// Sending events never fails or blocks; if nothing is listening, events are dropped.
+#[derive(Debug, Clone)]
+pub struct EventSender(broadcast::Sender<EventRecord>);
+
+impl Default for EventSender {
+    fn default() -> Self {
+        Self(broadcast::channel(EVENT_CAPACITY).0)
+    }
+}
+
+impl EventSender {
+    /// Send an event.
+    pub fn send(&self, event: StreamEvent) {
+        // This only fails if there's no receivers.
+        let _ = self.0.send(EventRecord {
+            timestamp: SystemTime::now(),
+            event,
+        });
+    }
+
+    /// Subscribe to events sent after this call.
+    pub fn subscribe(&self) -> broadcast::Receiver<EventRecord> {
+        self.0.subscribe()
+    }
+}
+
+/// Options for [`run_events_file`]. This is like a lower-effort builder interface, mostly
+/// provided because Rust tragically lacks named arguments.
+pub struct EventsFileOpts {
+    /// The path to write events to.
+    pub path: Utf8PathBuf,
+    /// Receiver for the events to write.
+    pub receiver: broadcast::Receiver<EventRecord>,
+}
+
+impl EventsFileOpts {
+    /// Construct options for [`run_events_file`] from parsed command-line interface arguments as
+    /// [`Opts`].
+    ///
+    /// Returns `None` if the events file isn't enabled.
+    pub fn from_cli(opts: &Opts, ghci_opts: &GhciOpts) -> Option<Self> {
+        opts.events_file.as_ref().map(|path| Self {
+            path: path.clone(),
+            receiver: ghci_opts.events.subscribe(),
+        })
+    }
+}
+
+/// Write lifecycle events to a file as newline-delimited JSON.
+#[instrument(level = "debug", skip_all)]
+pub async fn run_events_file(
+    mut handle: ShutdownHandle,
+    mut opts: EventsFileOpts,
+) -> miette::Result<()> {
+    let mut file = File::create(&opts.path)
+        .await
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed to open events file {}", opts.path))?;
+
+    loop {
+        let record = tokio::select! {
+            _ = handle.on_shutdown_requested() => {
+                break;
+            }
+            record = opts.receiver.recv() => record,
+        };
+
+        match record {
+            Ok(record) => {
+                file.write_all(record.to_json_line()?.as_bytes())
+                    .await
+                    .into_diagnostic()?;
+                // Flush after every event so that readers following the file see it right away.
+                file.flush().await.into_diagnostic()?;
+            }
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::warn!("Events file writer fell behind; skipped {skipped} events");
+            }
+            Err(broadcast::error::RecvError::Closed) => {
+                break;
+            }
+        }
+    }
+
+    // Write out any events sent before the shutdown.
+    while let Ok(record) = opts.receiver.try_recv() {
+        file.write_all(record.to_json_line()?.as_bytes())
+            .await
+            .into_diagnostic()?;
+    }
+    file.shutdown().await.into_diagnostic()?;
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::ghci::parse::parse_ghc_messages;
+
+    fn to_json_line(event: StreamEvent) -> String {
+        EventRecord {
+            timestamp: SystemTime::UNIX_EPOCH + Duration::from_millis(1_700_000_000_123),
+            event,
+        }
+        .to_json_line()
+        .unwrap()
+    }
+
+    #[test]
+    fn test_serialize_events() {
+        assert_eq!(
+            to_json_line(StreamEvent::FilesChanged {
+                changes: vec![
+                    FileEvent::Modify("src/A.hs".into()),
+                    FileEvent::Remove("src/B.hs".into())
+                ],
+            }),
+            "{\"version\":1,\"timestamp\":\"2023-11-14T22:13:20.123Z\",\"event\":\"files-changed\",\"changes\":[{\"kind\":\"modify\",\"path\":\"src/A.hs\"},{\"kind\":\"remove\",\"path\":\"src/B.hs\"}]}\n"
+        );
+
+        assert_eq!(
+            to_json_line(StreamEvent::ReloadKind {
+                kind: GhciReloadKind::Restart
+            }),
+            "{\"version\":1,\"timestamp\":\"2023-11-14T22:13:20.123Z\",\"event\":\"reload-kind\",\"kind\":\"restart\"}\n"
+        );
+
+        assert_eq!(
+            to_json_line(StreamEvent::Restart),
+            "{\"version\":1,\"timestamp\":\"2023-11-14T22:13:20.123Z\",\"event\":\"restart\"}\n"
+        );
+
+        assert_eq!(
+            to_json_line(StreamEvent::compilation_finished(
+                LifecycleEvent::Reload(crate::hooks::When::After),
+                Some(CompilationResult::Err),
+                Duration::from_millis(1234),
+            )),
+            "{\"version\":1,\"timestamp\":\"2023-11-14T22:13:20.123Z\",\"event\":\"compilation-finished\",\"lifecycle\":\"reload\",\"result\":\"err\",\"duration_ms\":1234}\n"
+        );
+    }
+
+    #[test]
+    fn test_events_from_ghc_messages() {
+        let events = parse_ghc_messages(
+            "[1 of 1] Compiling MyLib ( src/MyLib.hs, interpreted )\n\
+            src/MyLib.hs:1:1: error:\n    Oh no\n\
+            Failed, no modules loaded.\n",
+        )
+        .unwrap()
+        .iter()
+        .filter_map(StreamEvent::from_ghc_message)
+        .map(|event| serde_json::to_value(event).unwrap())
+        .collect::<Vec<_>>();
+
+        assert_eq!(
+            events,
+            vec![
+                serde_json::json!({
+                    "event": "compiling",
+                    "module": "MyLib",
+                    "path": "src/MyLib.hs",
+                }),
+                serde_json::json!({
+                    "event": "diagnostic",
+                    "severity": "error",
+                    "path": "src/MyLib.hs",
+                    "span": {
+                        "start": { "line": 1, "column": 1 },
+                        "end": { "line": 1, "column": 1 },
+                    },
+                    "message": "\n    Oh no\n",
+                }),
+                serde_json::json!({
+                    "event": "summary",
+                    "result": "err",
+                    "modules_loaded": 0,
+                }),
+            ]
+        );
+    }
+}
@@ -0,0 +1,47 @@
+use std::fmt::Write;
+
+use camino::Utf8Path;
+
+/// Percent-encode a path for use in a URI, leaving `/` separators and unreserved characters as-is.
+pub fn percent_encode_path(path: &Utf8Path) -> String {
+    let mut encoded = String::with_capacity(path.as_str().len());
+    for byte in path.as_str().bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'.' | b'_' | b'~' | b'/' => {
+                encoded.push(byte as char);
+            }
+            _ => {
+                let _ = write!(encoded, "%{byte:02X}");
+            }
+        }
+    }
+    encoded
+}
+
+/// Convert an absolute path to a `file://` URI.
+pub fn file_uri(path: &Utf8Path) -> String {
+    format!("file://{}", percent_encode_path(path))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_file_uri() {
+        assert_eq!(
+            file_uri("/home/me/src/MyLib.hs".into()),
+            "file:///home/me/src/MyLib.hs"
+        );
+        assert_eq!(
+            file_uri("/home/me/my project/Ünïcode.hs".into()),
+            "file:///home/me/my%20project/%C3%9Cn%C3%AFcode.hs"
+        );
+        assert_eq!(
+            percent_encode_path("src/My Module.hs".into()),
+            "src/My%20Module.hs"
+        );
+    }
+}
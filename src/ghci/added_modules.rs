@@ -0,0 +1,196 @@
+//! Remembering which modules `ghciwatch` has `:add`ed, so they can be added again after restarts.
+
+use std::sync::Arc;
+
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use itertools::Itertools;
+use miette::Context;
+use miette::IntoDiagnostic;
+use serde::Deserialize;
+use serde::Serialize;
+use tokio::sync::Mutex;
+use tracing::instrument;
+
+use crate::normal_path::NormalPath;
+
+use super::error_log::write_atomic;
+use super::parse::ModuleSet;
+use super::parse::TargetKind;
+
+/// The modules `ghciwatch` has `:add`ed to the `ghci` session.
+///
+/// When `ghci` restarts, the new session only loads the targets from its command line, so these
+/// modules are added again. Clones share the same set, so it's kept across restarts and crashes.
+///
+/// If a state file is given, the set is written to it whenever it changes and read from it on
+/// startup, so that it's also kept when `ghciwatch` restarts. The set stays locked while it's
+/// written, so that sessions sharing it write the state file one at a time and in order.
+#[derive(Debug, Clone, Default)]
+pub struct AddedModules {
+    /// The modules, and how they were added.
+    modules: Arc<Mutex<ModuleSet>>,
+    /// The state file to persist the set in.
+    path: Option<Utf8PathBuf>,
+}
+
+/// The contents of the state file.
+#[derive(Debug, Default, PartialEq, Eq, Serialize, Deserialize)]
+struct StateFile {
+    /// The modules `ghciwatch` has `:add`ed.
+    added_modules: Vec<AddedModule>,
+}
+
+/// A module in the [`StateFile`].
+#[derive(Debug, PartialEq, Eq, Serialize, Deserialize)]
+struct AddedModule {
+    /// The module's source path, relative to the working directory.
+    path: Utf8PathBuf,
+    /// Whether the module was added by path or by name.
+    kind: TargetKind,
+}
+
+impl AddedModules {
+    /// Load the set of added modules from the state file at `path`, if any.
+    ///
+    /// If the state file doesn't exist yet, the set starts out empty.
+    pub fn load(path: Option<Utf8PathBuf>) -> miette::Result<Self> {
+        let path = match path {
+            Some(path) => path,
+            None => return Ok(Self::default()),
+        };
+
+        let modules = match std::fs::read_to_string(&path) {
+            Ok(contents) => {
+                let state = serde_json::from_str::<StateFile>(&contents)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("Failed to parse state file {path}"))?;
+                ModuleSet::from_paths(
+                    state
+                        .added_modules
+                        .into_iter()
+                        .map(|module| (module.path, module.kind)),
+                    crate::current_dir_utf8()?,
+                )?
+            }
+            Err(err) if err.kind() == std::io::ErrorKind::NotFound => ModuleSet::default(),
+            Err(err) => {
+                return Err(err)
+                    .into_diagnostic()
+                    .wrap_err_with(|| format!("Failed to read state file {path}"));
+            }
+        };
+
+        Ok(Self {
+            modules: Arc::new(Mutex::new(modules)),
+            path: Some(path),
+        })
+    }
+
+    /// Get the added modules, in order.
+    pub async fn modules(&self) -> Vec<(NormalPath, TargetKind)> {
+        self.modules
+            .lock()
+            .await
+            .iter_with_kinds()
+            .map(|(path, kind)| (path.clone(), kind))
+            .sorted()
+            .collect()
+    }
+
+    /// Record that a module was added, and update the state file.
+    pub async fn insert(&self, path: &NormalPath, kind: TargetKind) -> miette::Result<()> {
+        let mut modules = self.modules.lock().await;
+        if modules.contains_source_path(path) {
+            return Ok(());
+        }
+        modules.insert_source_path(path.clone(), kind);
+        self.write(&modules).await
+    }
+
+    /// Record that modules were removed, and update the state file.
+    pub async fn remove(&self, paths: &[NormalPath]) -> miette::Result<()> {
+        let mut modules = self.modules.lock().await;
+        let mut removed = false;
+        for path in paths {
+            removed |= modules.remove_source_path(path);
+        }
+        if !removed {
+            return Ok(());
+        }
+        self.write(&modules).await
+    }
+
+    /// Write the state file, if any. The caller holds the lock on `modules`.
+    #[instrument(skip_all, name = "state_file_write", level = "debug")]
+    async fn write(&self, modules: &ModuleSet) -> miette::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        write_state_file(path, &state_file(modules)).await
+    }
+}
+
+/// Get the contents of the state file for a set of added `modules`.
+fn state_file(modules: &ModuleSet) -> StateFile {
+    StateFile {
+        added_modules: modules
+            .iter_with_kinds()
+            .map(|(path, kind)| AddedModule {
+                path: path.relative().to_owned(),
+                kind,
+            })
+            .sorted_by(|a, b| a.path.cmp(&b.path))
+            .collect(),
+    }
+}
+
+async fn write_state_file(path: &Utf8Path, state: &StateFile) -> miette::Result<()> {
+    let mut contents = serde_json::to_string_pretty(state).into_diagnostic()?;
+    contents.push('\n');
+    write_atomic(path, contents.as_bytes())
+        .await
+        .wrap_err_with(|| format!("Failed to write state file {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_state_file() {
+        let modules = ModuleSet::from_paths(
+            [
+                ("src/MyModule.hs", TargetKind::Path),
+                ("src/MyLib.hs", TargetKind::Module),
+            ],
+            "/project",
+        )
+        .unwrap();
+        let state = state_file(&modules);
+        let contents = serde_json::to_string_pretty(&state).unwrap();
+        assert_eq!(
+            contents,
+            indoc!(
+                r#"
+                {
+                  "added_modules": [
+                    {
+                      "path": "src/MyLib.hs",
+                      "kind": "module"
+                    },
+                    {
+                      "path": "src/MyModule.hs",
+                      "kind": "path"
+                    }
+                  ]
+                }"#
+            )
+        );
+        assert_eq!(serde_json::from_str::<StateFile>(&contents).unwrap(), state);
+    }
+}
@@ -1,14 +1,31 @@
+use camino::Utf8PathBuf;
+
 use crate::ghci::parse::CompilationResult;
 use crate::ghci::parse::CompilationSummary;
 use crate::ghci::parse::GhcDiagnostic;
 use crate::ghci::parse::GhcMessage;
 use crate::ghci::parse::Severity;
+use crate::ghci::parse::TestMessage;
+use crate::ghci::parse::TestSummary;
+use crate::ghci::parse::CANT_FIND_FILE_MESSAGE;
+
+use super::timings::ModuleTiming;
 
 /// A log of messages from compilation, used to write the error log.
 #[derive(Debug, Clone, Default)]
 pub struct CompilationLog {
+    /// The compilation summary, if compilation finished.
     pub summary: Option<CompilationSummary>,
+    /// Errors and warnings from compilation, followed by test failures.
     pub diagnostics: Vec<GhcDiagnostic>,
+    /// Test results, if a test suite printed a summary.
+    pub tests: Option<TestSummary>,
+    /// The number of diagnostics hidden by `--hide-diagnostics` rules.
+    pub hidden: usize,
+    /// Source paths of the modules which were compiled, as printed by `ghci`.
+    pub compiled: Vec<Utf8PathBuf>,
+    /// How long each compiled module took to compile.
+    pub timings: Vec<ModuleTiming>,
 }
 
 impl CompilationLog {
@@ -16,6 +33,18 @@ impl CompilationLog {
     pub fn result(&self) -> Option<CompilationResult> {
         self.summary.map(|summary| summary.result)
     }
+
+    /// Did `ghci` fail to find the source file for a module it previously loaded?
+    ///
+    /// This is how [GHC bug #11596][bug] shows up after a module is removed: `ghci` keeps looking
+    /// for the missing file until it's restarted.
+    ///
+    /// [bug]: https://gitlab.haskell.org/ghc/ghc/-/issues/11596
+    pub fn has_missing_source_file(&self) -> bool {
+        self.diagnostics
+            .iter()
+            .any(|diagnostic| diagnostic.message == CANT_FIND_FILE_MESSAGE)
+    }
 }
 
 impl Extend<GhcMessage> for CompilationLog {
@@ -24,6 +53,7 @@ impl Extend<GhcMessage> for CompilationLog {
             match message {
                 GhcMessage::Compiling(module) => {
                     tracing::debug!(module = %module.name, path = %module.path, "Compiling");
+                    self.compiled.push(module.path);
                 }
                 GhcMessage::Diagnostic(diagnostic) => {
                     if let GhcDiagnostic {
@@ -55,3 +85,23 @@ impl Extend<GhcMessage> for CompilationLog {
         }
     }
 }
+
+impl Extend<TestMessage> for CompilationLog {
+    fn extend<T: IntoIterator<Item = TestMessage>>(&mut self, iter: T) {
+        for message in iter {
+            match message {
+                TestMessage::Failure(failure) => {
+                    tracing::debug!(test = failure.description, "Test failed");
+                    self.diagnostics.push(failure.into());
+                }
+                TestMessage::Summary(summary) => {
+                    // Several test suites may be run by the test hooks.
+                    self.tests = Some(match self.tests {
+                        Some(tests) => tests.combine(summary),
+                        None => summary,
+                    });
+                }
+            }
+        }
+    }
+}
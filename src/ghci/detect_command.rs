@@ -0,0 +1,416 @@
+//! Detect the command to start `ghci` with from the files in a project, like `ghcid` does.
+//!
+//! See: <https://github.com/ndmitchell/ghcid/blob/e2852979aa644c8fed92d46ab529d2c6c1c62b59/src/Ghcid.hs#L142-L171>
+
+use std::collections::BTreeSet;
+use std::fmt::Display;
+
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use miette::Context;
+use miette::IntoDiagnostic;
+
+use crate::clonable_command::ClonableCommand;
+
+/// A command to start `ghci` with, detected from the files in a project.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DetectedCommand {
+    /// The command to start `ghci` with.
+    pub command: ClonableCommand,
+    /// A shell command to run before starting `ghci`, like `hpack` to generate a `.cabal` file.
+    pub before_startup: Option<ClonableCommand>,
+    /// Why the command was chosen.
+    pub reason: String,
+}
+
+impl DetectedCommand {
+    fn new(command: ClonableCommand, reason: impl Into<String>) -> Self {
+        Self {
+            command,
+            before_startup: None,
+            reason: reason.into(),
+        }
+    }
+}
+
+/// Detect the command to start `ghci` with for the project in `dir`.
+///
+/// In order of preference:
+///
+/// 1. `stack ghci` if `stack.yaml` exists.
+/// 2. `ghci` if `.ghci` exists.
+/// 3. `cabal repl` if a `.cabal` file exists. If the package has multiple components, the
+///    library is loaded.
+/// 4. `hpack` and then `cabal repl` if `package.yaml` exists.
+/// 5. `cabal repl` if `cabal.project` exists. If `.cabal` files are found in subdirectories, the
+///    first package with a library is loaded.
+/// 6. `cabal repl` otherwise.
+pub fn detect_command(dir: &Utf8Path) -> miette::Result<DetectedCommand> {
+    let files = list_files(dir)?;
+
+    if files.contains("stack.yaml") {
+        return Ok(DetectedCommand::new(
+            ClonableCommand::new("stack").arg("ghci"),
+            "`stack.yaml` exists",
+        ));
+    }
+
+    if files.contains(".ghci") {
+        return Ok(DetectedCommand::new(
+            ClonableCommand::new("ghci"),
+            "`.ghci` exists",
+        ));
+    }
+
+    let cabal_files = files
+        .iter()
+        .filter(|file| is_cabal_file(file))
+        .map(|file| dir.join(file))
+        .collect::<Vec<_>>();
+    if !cabal_files.is_empty() {
+        let packages = read_cabal_files(&cabal_files)?;
+        return Ok(cabal_repl(
+            &packages,
+            format!("{} exists", format_paths(dir, &cabal_files)),
+        ));
+    }
+
+    if files.contains("package.yaml") {
+        return Ok(DetectedCommand {
+            command: ClonableCommand::new("cabal").arg("repl"),
+            before_startup: Some(ClonableCommand::new("hpack")),
+            reason: "only `package.yaml` exists, so `hpack` will generate a `.cabal` file".into(),
+        });
+    }
+
+    if files.contains("cabal.project") {
+        let mut cabal_files = Vec::new();
+        for subdir in list_dirs(dir)? {
+            let subdir = dir.join(subdir);
+            cabal_files.extend(
+                list_files(&subdir)?
+                    .iter()
+                    .filter(|file| is_cabal_file(file))
+                    .map(|file| subdir.join(file)),
+            );
+        }
+        let packages = read_cabal_files(&cabal_files)?;
+        return Ok(cabal_repl(&packages, "`cabal.project` exists"));
+    }
+
+    Ok(DetectedCommand::new(
+        ClonableCommand::new("cabal").arg("repl"),
+        "no `stack.yaml`, `.ghci`, `.cabal`, `package.yaml`, or `cabal.project` files were found",
+    ))
+}
+
+/// Construct a `cabal repl` command, choosing a target if the packages have multiple components.
+fn cabal_repl(packages: &[CabalPackage], reason: impl Display) -> DetectedCommand {
+    let command = ClonableCommand::new("cabal").arg("repl");
+    match pick_target(packages) {
+        Some((target, why)) => DetectedCommand::new(
+            command.arg(&target),
+            format!("{reason} and {why}, so `{target}` is loaded"),
+        ),
+        None => DetectedCommand::new(command, reason.to_string()),
+    }
+}
+
+/// Choose a `cabal repl` target, and say why.
+///
+/// Returns `None` if there's only one component to load, which `cabal repl` will load by default.
+fn pick_target(packages: &[CabalPackage]) -> Option<(String, String)> {
+    match packages {
+        [] => None,
+        [package] => {
+            if package.components.len() <= 1 {
+                return None;
+            }
+            let component = package.main_component()?;
+            Some((
+                component.target(&package.name),
+                format!("`{}` has multiple components", package.name),
+            ))
+        }
+        _ => {
+            let package = packages
+                .iter()
+                .find(|package| package.library().is_some())
+                .unwrap_or(&packages[0]);
+            let component = package.main_component()?;
+            Some((
+                component.target(&package.name),
+                format!("there are {} packages", packages.len()),
+            ))
+        }
+    }
+}
+
+/// A package, parsed from a `.cabal` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct CabalPackage {
+    /// The package's name.
+    name: String,
+    /// The package's components, in the order they're declared.
+    components: Vec<CabalComponent>,
+}
+
+impl CabalPackage {
+    /// Parse the package name and components from the contents of a `.cabal` file.
+    ///
+    /// This only looks at top-level fields and stanza headers, which is all we need to pick a
+    /// target.
+    fn parse(contents: &str) -> Option<Self> {
+        let mut name = None;
+        let mut components = Vec::new();
+
+        for line in contents.lines() {
+            // Fields inside stanzas and continuation lines are indented.
+            if line.starts_with(char::is_whitespace) || line.starts_with("--") {
+                continue;
+            }
+
+            if let Some((field, value)) = line.split_once(':') {
+                if field.trim().eq_ignore_ascii_case("name") {
+                    name = Some(value.trim().to_owned());
+                }
+                continue;
+            }
+
+            let mut words = line.split_whitespace();
+            let stanza = match words.next() {
+                Some(stanza) => stanza.to_ascii_lowercase(),
+                None => continue,
+            };
+            let stanza_name = words.next().map(ToOwned::to_owned);
+            let component = match (stanza.as_str(), stanza_name) {
+                ("library", name) => CabalComponent::Library(name),
+                ("executable", Some(name)) => CabalComponent::Executable(name),
+                ("test-suite", Some(name)) => CabalComponent::TestSuite(name),
+                ("benchmark", Some(name)) => CabalComponent::Benchmark(name),
+                ("foreign-library", Some(name)) => CabalComponent::ForeignLibrary(name),
+                _ => continue,
+            };
+            components.push(component);
+        }
+
+        Some(Self {
+            name: name?,
+            components,
+        })
+    }
+
+    /// The package's main (unnamed) library, if any.
+    fn library(&self) -> Option<&CabalComponent> {
+        self.components
+            .iter()
+            .find(|component| matches!(component, CabalComponent::Library(None)))
+    }
+
+    /// The component to load by default: the main library if there is one, otherwise the first
+    /// executable, otherwise the first component.
+    fn main_component(&self) -> Option<&CabalComponent> {
+        self.library()
+            .or_else(|| {
+                self.components
+                    .iter()
+                    .find(|component| matches!(component, CabalComponent::Executable(_)))
+            })
+            .or_else(|| self.components.first())
+    }
+}
+
+/// A component in a `.cabal` file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum CabalComponent {
+    /// A library. The main library is unnamed.
+    Library(Option<String>),
+    /// An executable.
+    Executable(String),
+    /// A test suite.
+    TestSuite(String),
+    /// A benchmark.
+    Benchmark(String),
+    /// A foreign library.
+    ForeignLibrary(String),
+}
+
+impl CabalComponent {
+    /// The `cabal` target for this component in the given package, like `lib:my-package`.
+    fn target(&self, package: &str) -> String {
+        match self {
+            CabalComponent::Library(None) => format!("lib:{package}"),
+            CabalComponent::Library(Some(name)) => format!("lib:{name}"),
+            CabalComponent::Executable(name) => format!("exe:{name}"),
+            CabalComponent::TestSuite(name) => format!("test:{name}"),
+            CabalComponent::Benchmark(name) => format!("bench:{name}"),
+            CabalComponent::ForeignLibrary(name) => format!("flib:{name}"),
+        }
+    }
+}
+
+fn is_cabal_file(name: &str) -> bool {
+    Utf8Path::new(name).extension() == Some("cabal")
+}
+
+fn read_cabal_files(paths: &[Utf8PathBuf]) -> miette::Result<Vec<CabalPackage>> {
+    let mut packages = Vec::new();
+    for path in paths {
+        let contents = std::fs::read_to_string(path)
+            .into_diagnostic()
+            .wrap_err_with(|| format!("Failed to read {path}"))?;
+        match CabalPackage::parse(&contents) {
+            Some(package) => packages.push(package),
+            None => tracing::debug!(%path, "No package name found in `.cabal` file"),
+        }
+    }
+    Ok(packages)
+}
+
+/// List the names of the files in `dir`, sorted.
+fn list_files(dir: &Utf8Path) -> miette::Result<BTreeSet<String>> {
+    list_entries(dir, |file_type| file_type.is_file())
+}
+
+/// List the names of the non-hidden directories in `dir`, sorted.
+fn list_dirs(dir: &Utf8Path) -> miette::Result<BTreeSet<String>> {
+    let mut dirs = list_entries(dir, |file_type| file_type.is_dir())?;
+    dirs.retain(|name| !name.starts_with('.') && name != "dist-newstyle");
+    Ok(dirs)
+}
+
+fn list_entries(
+    dir: &Utf8Path,
+    keep: impl Fn(std::fs::FileType) -> bool,
+) -> miette::Result<BTreeSet<String>> {
+    let mut names = BTreeSet::new();
+    for entry in dir
+        .read_dir_utf8()
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed to list {dir}"))?
+    {
+        let entry = entry.into_diagnostic()?;
+        // Follow symlinks.
+        let is_kept = std::fs::metadata(entry.path())
+            .map(|metadata| keep(metadata.file_type()))
+            .unwrap_or(false);
+        if is_kept {
+            names.insert(entry.file_name().to_owned());
+        }
+    }
+    Ok(names)
+}
+
+/// Format paths like `` `foo.cabal` `` or `` `foo.cabal` and `bar.cabal` ``.
+fn format_paths(dir: &Utf8Path, paths: &[Utf8PathBuf]) -> String {
+    let paths = paths
+        .iter()
+        .map(|path| format!("`{}`", path.strip_prefix(dir).unwrap_or(path)))
+        .collect::<Vec<_>>();
+    match paths.as_slice() {
+        [path] => path.clone(),
+        [init @ .., last] => format!("{} and {last}", init.join(", ")),
+        [] => String::new(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    fn package(contents: &str) -> CabalPackage {
+        CabalPackage::parse(contents).unwrap()
+    }
+
+    #[test]
+    fn test_parse_cabal_package() {
+        assert_eq!(
+            package(indoc!(
+                "
+                cabal-version: 2.0
+                -- A comment.
+                Name:           my-package
+                version:        0.1.0.0
+
+                common warnings
+                  ghc-options: -Wall
+
+                Library
+                  exposed-modules: MyLib
+                  build-depends:
+                      base
+
+                library internal
+                  exposed-modules: Internal
+
+                executable my-exe
+                  main-is: Main.hs
+
+                test-suite my-tests
+                  type: exitcode-stdio-1.0
+
+                benchmark my-bench
+                  type: exitcode-stdio-1.0
+                "
+            )),
+            CabalPackage {
+                name: "my-package".into(),
+                components: vec![
+                    CabalComponent::Library(None),
+                    CabalComponent::Library(Some("internal".into())),
+                    CabalComponent::Executable("my-exe".into()),
+                    CabalComponent::TestSuite("my-tests".into()),
+                    CabalComponent::Benchmark("my-bench".into()),
+                ],
+            }
+        );
+
+        // Negative cases.
+        assert_eq!(CabalPackage::parse("library\n  exposed-modules: A\n"), None);
+    }
+
+    #[test]
+    fn test_pick_target() {
+        let library = package("name: lib-only\nlibrary\n");
+        let library_and_tests = package("name: my-package\nlibrary\ntest-suite tests\n");
+        let executables = package("name: my-app\ntest-suite tests\nexecutable app\n");
+
+        assert_eq!(pick_target(&[]), None);
+        assert_eq!(pick_target(&[library.clone()]), None);
+        assert_eq!(
+            pick_target(&[library_and_tests.clone()]),
+            Some((
+                "lib:my-package".into(),
+                "`my-package` has multiple components".into()
+            ))
+        );
+        assert_eq!(
+            pick_target(&[executables.clone()]),
+            Some(("exe:app".into(), "`my-app` has multiple components".into()))
+        );
+        assert_eq!(
+            pick_target(&[executables, library_and_tests]),
+            Some(("lib:my-package".into(), "there are 2 packages".into()))
+        );
+    }
+
+    #[test]
+    fn test_format_paths() {
+        let dir = Utf8Path::new("/project");
+        assert_eq!(format_paths(dir, &["/project/a.cabal".into()]), "`a.cabal`");
+        assert_eq!(
+            format_paths(
+                dir,
+                &[
+                    "/project/a.cabal".into(),
+                    "/project/b.cabal".into(),
+                    "/project/c.cabal".into()
+                ]
+            ),
+            "`a.cabal`, `b.cabal` and `c.cabal`"
+        );
+    }
+}
@@ -0,0 +1,182 @@
+//! Rules for hiding diagnostics.
+
+use crate::ignore::GlobMatcher;
+
+use super::parse::GhcDiagnostic;
+use super::CompilationLog;
+
+/// Rules for hiding diagnostics from the terminal summary and the error log.
+///
+/// Each rule is one of:
+///
+/// - A warning flag, like `-Wmissing-export-lists`.
+/// - An error code, like `GHC-83865`.
+/// - A path glob, like `src/Generated/**`, with the same semantics as `--reload-glob`.
+///
+/// Hidden diagnostics are still counted in [`CompilationLog::hidden`].
+#[derive(Debug, Clone)]
+pub struct DiagnosticFilter {
+    globs: GlobMatcher,
+    flags: Vec<String>,
+    codes: Vec<String>,
+}
+
+impl Default for DiagnosticFilter {
+    fn default() -> Self {
+        Self {
+            globs: GlobMatcher::empty(),
+            flags: Vec::new(),
+            codes: Vec::new(),
+        }
+    }
+}
+
+impl DiagnosticFilter {
+    /// Build a filter from the given rules.
+    pub fn from_rules(rules: impl IntoIterator<Item = impl AsRef<str>>) -> miette::Result<Self> {
+        let mut globs = Vec::new();
+        let mut flags = Vec::new();
+        let mut codes = Vec::new();
+
+        for rule in rules {
+            let rule = rule.as_ref();
+            if rule.starts_with("-W") {
+                flags.push(rule.to_owned());
+            } else if is_error_code(rule) {
+                codes.push(rule.to_owned());
+            } else {
+                globs.push(rule.to_owned());
+            }
+        }
+
+        Ok(Self {
+            globs: GlobMatcher::from_globs(globs)?,
+            flags,
+            codes,
+        })
+    }
+
+    /// Returns true if no diagnostics are hidden.
+    pub fn is_empty(&self) -> bool {
+        self.globs.is_empty() && self.flags.is_empty() && self.codes.is_empty()
+    }
+
+    /// Determine if a diagnostic should be hidden.
+    pub fn is_hidden(&self, diagnostic: &GhcDiagnostic) -> bool {
+        diagnostic
+            .flag
+            .as_ref()
+            .map(|flag| self.flags.contains(flag))
+            .unwrap_or(false)
+            || diagnostic
+                .code
+                .as_ref()
+                .map(|code| self.codes.contains(code))
+                .unwrap_or(false)
+            || diagnostic
+                .path
+                .as_ref()
+                .map(|path| self.globs.matched(path).is_whitelist())
+                .unwrap_or(false)
+    }
+
+    /// Remove hidden diagnostics from a compilation `log`, counting them in
+    /// [`CompilationLog::hidden`].
+    pub fn apply(&self, log: &mut CompilationLog) {
+        if self.is_empty() {
+            return;
+        }
+        let before = log.diagnostics.len();
+        log.diagnostics
+            .retain(|diagnostic| !self.is_hidden(diagnostic));
+        log.hidden += before - log.diagnostics.len();
+    }
+}
+
+/// Is the rule an error code like `GHC-83865`?
+fn is_error_code(rule: &str) -> bool {
+    match rule.split_once('-') {
+        Some((namespace, number)) => {
+            !namespace.is_empty()
+                && namespace.chars().all(|c| c.is_ascii_uppercase())
+                && !number.is_empty()
+                && number.chars().all(|c| c.is_ascii_digit())
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::ghci::parse::Severity;
+
+    fn warning(path: &str, code: Option<&str>, flag: Option<&str>) -> GhcDiagnostic {
+        GhcDiagnostic {
+            code: code.map(ToOwned::to_owned),
+            flag: flag.map(ToOwned::to_owned),
+            ..GhcDiagnostic::new(Severity::Warning, Some(path), "Oh no")
+        }
+    }
+
+    #[test]
+    fn test_diagnostic_filter() {
+        let filter = DiagnosticFilter::from_rules([
+            "-Wmissing-export-lists",
+            "GHC-83865",
+            "src/Generated/**",
+            "!src/Generated/Keep.hs",
+        ])
+        .unwrap();
+
+        assert!(filter.is_hidden(&warning(
+            "src/MyLib.hs",
+            Some("GHC-38417"),
+            Some("-Wmissing-export-lists")
+        )));
+        assert!(filter.is_hidden(&warning("src/MyLib.hs", Some("GHC-83865"), None)));
+        assert!(filter.is_hidden(&warning("src/Generated/Schema.hs", None, None)));
+
+        assert!(!filter.is_hidden(&warning(
+            "src/MyLib.hs",
+            Some("GHC-66111"),
+            Some("-Wunused-imports")
+        )));
+        assert!(!filter.is_hidden(&warning("src/Generated/Keep.hs", None, None)));
+        assert!(!filter.is_hidden(&GhcDiagnostic::new(Severity::Warning, None, "Oh no")));
+    }
+
+    #[test]
+    fn test_diagnostic_filter_apply() {
+        let mut log = CompilationLog {
+            diagnostics: vec![
+                warning("src/A.hs", None, Some("-Wunused-imports")),
+                warning("src/B.hs", None, Some("-Wmissing-export-lists")),
+                warning("src/C.hs", None, Some("-Wmissing-export-lists")),
+            ],
+            ..Default::default()
+        };
+
+        DiagnosticFilter::default().apply(&mut log);
+        assert_eq!(log.diagnostics.len(), 3);
+        assert_eq!(log.hidden, 0);
+
+        DiagnosticFilter::from_rules(["-Wmissing-export-lists"])
+            .unwrap()
+            .apply(&mut log);
+        assert_eq!(
+            log.diagnostics,
+            vec![warning("src/A.hs", None, Some("-Wunused-imports"))]
+        );
+        assert_eq!(log.hidden, 2);
+    }
+
+    #[test]
+    fn test_is_error_code() {
+        assert!(is_error_code("GHC-83865"));
+        assert!(!is_error_code("-Wunused-imports"));
+        assert!(!is_error_code("src/GHC-1.hs"));
+        assert!(!is_error_code("GHC-"));
+    }
+}
@@ -0,0 +1,193 @@
+//! Warnings carried forward across reloads.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::path::Path;
+
+use crate::normal_path::NormalPath;
+
+use super::parse::GhcDiagnostic;
+use super::parse::Severity;
+use super::CompilationLog;
+
+/// A per-module store of warnings.
+///
+/// After a `:reload`, `ghci` only prints warnings for the modules it recompiled. To show the
+/// full set of current warnings, we keep each module's warnings until the module is recompiled
+/// or removed from the session, like `ghcid` does.
+///
+/// Errors aren't kept: modules which fail to compile aren't loaded, so `ghci` tries to compile
+/// them (and prints their errors) again on every reload.
+#[derive(Debug, Clone, Default)]
+pub struct DiagnosticStore {
+    modules: BTreeMap<NormalPath, Vec<GhcDiagnostic>>,
+}
+
+impl DiagnosticStore {
+    /// Update the store from a compilation `log`, and add warnings for modules which weren't
+    /// recompiled to the end of the `log`.
+    ///
+    /// Relative paths are resolved from `cwd`. Returns the warnings added to the `log`.
+    pub fn update(
+        &mut self,
+        log: &mut CompilationLog,
+        cwd: impl AsRef<Path>,
+    ) -> miette::Result<Vec<GhcDiagnostic>> {
+        let cwd = cwd.as_ref();
+
+        // A module's warnings are replaced if it was recompiled or if `ghci` printed new
+        // diagnostics for it (like errors found before compilation starts).
+        let mut updated = log
+            .compiled
+            .iter()
+            .map(|path| NormalPath::new(path, cwd))
+            .collect::<Result<BTreeSet<_>, _>>()?;
+        let mut warnings: BTreeMap<NormalPath, Vec<GhcDiagnostic>> = BTreeMap::new();
+        for diagnostic in &log.diagnostics {
+            if let Some(path) = &diagnostic.path {
+                let path = NormalPath::new(path, cwd)?;
+                if let Severity::Warning = diagnostic.severity {
+                    warnings
+                        .entry(path.clone())
+                        .or_default()
+                        .push(diagnostic.clone());
+                }
+                updated.insert(path);
+            }
+        }
+
+        for path in &updated {
+            match warnings.remove(path) {
+                Some(warnings) => {
+                    self.modules.insert(path.clone(), warnings);
+                }
+                None => {
+                    self.modules.remove(path);
+                }
+            }
+        }
+
+        let kept = self
+            .modules
+            .iter()
+            .filter(|(path, _)| !updated.contains(*path))
+            .flat_map(|(_, warnings)| warnings.iter().cloned())
+            .collect::<Vec<_>>();
+
+        log.diagnostics.extend(kept.iter().cloned());
+        Ok(kept)
+    }
+
+    /// Forget the warnings for a module which was removed from the session.
+    pub fn remove(&mut self, path: &NormalPath) {
+        self.modules.remove(path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    fn log(compiled: &[&str], diagnostics: Vec<GhcDiagnostic>) -> CompilationLog {
+        CompilationLog {
+            compiled: compiled.iter().map(Into::into).collect(),
+            diagnostics,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_diagnostic_store() {
+        let mut store = DiagnosticStore::default();
+
+        // On startup, everything is compiled.
+        let mut startup = log(
+            &["src/A.hs", "src/B.hs", "src/C.hs"],
+            vec![
+                GhcDiagnostic::new(Severity::Warning, Some("src/A.hs"), "unused import"),
+                GhcDiagnostic::new(Severity::Warning, Some("src/B.hs"), "missing export list"),
+            ],
+        );
+        assert_eq!(store.update(&mut startup, "/project").unwrap(), vec![]);
+        assert_eq!(startup.diagnostics.len(), 2);
+
+        // After a reload, warnings for modules which weren't recompiled are kept.
+        let mut reload = log(
+            &["src/A.hs", "src/C.hs"],
+            vec![GhcDiagnostic::new(
+                Severity::Warning,
+                Some("src/C.hs"),
+                "unused variable",
+            )],
+        );
+        assert_eq!(
+            store.update(&mut reload, "/project").unwrap(),
+            vec![GhcDiagnostic::new(
+                Severity::Warning,
+                Some("src/B.hs"),
+                "missing export list"
+            )]
+        );
+        assert_eq!(
+            reload.diagnostics,
+            vec![
+                GhcDiagnostic::new(Severity::Warning, Some("src/C.hs"), "unused variable"),
+                GhcDiagnostic::new(Severity::Warning, Some("src/B.hs"), "missing export list"),
+            ]
+        );
+
+        // Errors replace a module's warnings but aren't kept.
+        let mut failed = log(
+            &["src/B.hs"],
+            vec![GhcDiagnostic::new(
+                Severity::Error,
+                Some("src/B.hs"),
+                "type error",
+            )],
+        );
+        assert_eq!(
+            store.update(&mut failed, "/project").unwrap(),
+            vec![GhcDiagnostic::new(
+                Severity::Warning,
+                Some("src/C.hs"),
+                "unused variable"
+            )]
+        );
+        let mut reload = log(&[], vec![]);
+        assert_eq!(
+            store.update(&mut reload, "/project").unwrap(),
+            vec![GhcDiagnostic::new(
+                Severity::Warning,
+                Some("src/C.hs"),
+                "unused variable"
+            )]
+        );
+
+        // Removed modules lose their warnings.
+        store.remove(&NormalPath::new("src/C.hs", "/project").unwrap());
+        let mut reload = log(&[], vec![]);
+        assert_eq!(store.update(&mut reload, "/project").unwrap(), vec![]);
+    }
+
+    #[test]
+    fn test_diagnostic_store_without_path() {
+        let mut store = DiagnosticStore::default();
+        let mut startup = log(
+            &[],
+            vec![GhcDiagnostic {
+                severity: Severity::Warning,
+                path: None,
+                span: Default::default(),
+                code: None,
+                flag: None,
+                message: "-Wmissing-home-modules".into(),
+                colored: None,
+            }],
+        );
+        assert_eq!(store.update(&mut startup, "/project").unwrap(), vec![]);
+        let mut reload = log(&[], vec![]);
+        assert_eq!(store.update(&mut reload, "/project").unwrap(), vec![]);
+    }
+}
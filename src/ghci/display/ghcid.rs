@@ -0,0 +1,223 @@
+//! A `ghcid`-style summary of a compilation which fits in the terminal.
+
+use std::cmp::Reverse;
+use std::time::SystemTime;
+
+use camino::Utf8Path;
+
+use crate::ghci::parse::CompilationResult;
+use crate::ghci::parse::Severity;
+use crate::ghci::CompilationLog;
+
+/// Render a compilation `log` like `ghcid` does.
+///
+/// Successful compilations start with `All good (N modules)`. Errors are shown before warnings,
+/// and diagnostics in more recently modified files are shown first, so the one you're working on
+/// is at the top. If `height` is given, the output is cut off to fit in that many rows of `width`
+/// columns, ending with a count of the diagnostics which didn't fit.
+///
+/// Relative paths are resolved from `cwd`.
+pub fn render(
+    log: &CompilationLog,
+    cwd: &Utf8Path,
+    colors: bool,
+    width: usize,
+    height: Option<usize>,
+) -> String {
+    let mut diagnostics = log.diagnostics.iter().collect::<Vec<_>>();
+    // The sort is stable, so diagnostics in the same file stay in `ghci`'s order.
+    diagnostics.sort_by_cached_key(|diagnostic| {
+        let modified = diagnostic
+            .path
+            .as_ref()
+            .and_then(|path| modified(&cwd.join(path)));
+        (diagnostic.severity != Severity::Error, Reverse(modified))
+    });
+
+    let mut headline = None;
+    if let Some(summary) = log.summary {
+        if let CompilationResult::Ok = summary.result {
+            let modules_loaded = if summary.modules_loaded != 1 {
+                format!("{} modules", summary.modules_loaded)
+            } else {
+                format!("{} module", summary.modules_loaded)
+            };
+            headline = Some(format!("All good ({modules_loaded})\n"));
+        }
+    }
+    let texts = diagnostics
+        .iter()
+        .map(|diagnostic| {
+            if colors {
+                diagnostic.colored()
+            } else {
+                diagnostic.to_string()
+            }
+        })
+        .collect::<Vec<_>>();
+
+    // The number of terminal rows a line takes up.
+    let width = width.max(1);
+    let rows = |line: &str| textwrap::core::display_width(line).saturating_sub(1) / width + 1;
+
+    let mut rendered = headline.unwrap_or_default();
+    let total_rows = rendered
+        .lines()
+        .chain(texts.iter().flat_map(|text| text.lines()))
+        .map(rows)
+        .sum::<usize>();
+    let height = match height {
+        Some(height) if total_rows > height => height,
+        _ => {
+            rendered.extend(texts);
+            return rendered;
+        }
+    };
+
+    // Leave a row for the count of diagnostics which didn't fit.
+    let mut available = height.saturating_sub(rendered.lines().map(rows).sum::<usize>() + 1);
+    let mut shown = 0;
+    'fill: for text in &texts {
+        for line in text.lines() {
+            let rows = rows(line);
+            if rows > available {
+                break 'fill;
+            }
+            available -= rows;
+            rendered.push_str(line);
+            rendered.push('\n');
+        }
+        shown += 1;
+    }
+    if colors {
+        // Don't let colors leak from a diagnostic which was cut off.
+        rendered.push_str("\x1b[0m");
+    }
+
+    // Diagnostics which were cut off partway through count as not shown.
+    let not_shown = texts.len() - shown;
+    rendered.push_str(&format!(
+        "...and {not_shown} more {}\n",
+        if not_shown == 1 {
+            "diagnostic"
+        } else {
+            "diagnostics"
+        }
+    ));
+    rendered
+}
+
+/// Get the modification time of a file, if it exists.
+fn modified(path: &Utf8Path) -> Option<SystemTime> {
+    std::fs::metadata(path)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use crate::ghci::parse::CompilationSummary;
+    use crate::ghci::parse::GhcDiagnostic;
+
+    fn log(result: CompilationResult, diagnostics: Vec<GhcDiagnostic>) -> CompilationLog {
+        CompilationLog {
+            summary: Some(CompilationSummary {
+                result,
+                modules_loaded: 3,
+            }),
+            diagnostics,
+            ..Default::default()
+        }
+    }
+
+    fn cwd() -> &'static Utf8Path {
+        env!("CARGO_MANIFEST_DIR").into()
+    }
+
+    #[test]
+    fn test_render_all_good() {
+        assert_eq!(
+            render(
+                &log(CompilationResult::Ok, vec![]),
+                cwd(),
+                false,
+                80,
+                Some(10)
+            ),
+            "All good (3 modules)\n"
+        );
+    }
+
+    #[test]
+    fn test_render_order() {
+        let log = log(
+            CompilationResult::Err,
+            vec![
+                GhcDiagnostic::new(Severity::Warning, Some("Cargo.toml"), "\n    A warning\n"),
+                GhcDiagnostic::new(Severity::Error, Some("Missing.hs"), "\n    Missing error\n"),
+                GhcDiagnostic::new(Severity::Error, Some("Cargo.toml"), "\n    An error\n"),
+                GhcDiagnostic::new(Severity::Error, Some("Cargo.toml"), "\n    Another error\n"),
+            ],
+        );
+        assert_eq!(
+            render(&log, cwd(), false, 80, None),
+            indoc!(
+                "
+                Cargo.toml: error:
+                    An error
+                Cargo.toml: error:
+                    Another error
+                Missing.hs: error:
+                    Missing error
+                Cargo.toml: warning:
+                    A warning
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_fits_height() {
+        let log = log(
+            CompilationResult::Ok,
+            vec![
+                GhcDiagnostic::new(Severity::Warning, Some("A.hs"), "\n    One\n"),
+                GhcDiagnostic::new(Severity::Warning, Some("B.hs"), "\n    Two\n    Three\n"),
+                GhcDiagnostic::new(Severity::Warning, Some("C.hs"), "\n    Four\n"),
+            ],
+        );
+
+        // Everything fits.
+        assert_eq!(render(&log, cwd(), false, 80, Some(8)).lines().count(), 8);
+
+        assert_eq!(
+            render(&log, cwd(), false, 80, Some(5)),
+            indoc!(
+                "
+                All good (3 modules)
+                A.hs: warning:
+                    One
+                B.hs: warning:
+                ...and 2 more diagnostics
+                "
+            )
+        );
+
+        // Long lines wrap onto multiple rows.
+        assert_eq!(
+            render(&log, cwd(), false, 10, Some(5)),
+            indoc!(
+                "
+                All good (3 modules)
+                A.hs: warning:
+                ...and 3 more diagnostics
+                "
+            )
+        );
+    }
+}
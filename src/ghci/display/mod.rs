@@ -0,0 +1,112 @@
+//! Displaying compilation results in the terminal.
+
+use std::io::IsTerminal;
+
+use camino::Utf8Path;
+use miette::GraphicalReportHandler;
+use miette::GraphicalTheme;
+
+use super::CompilationLog;
+
+mod ghcid;
+mod pretty;
+
+/// How to display compilation results in the terminal.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum DisplayMode {
+    /// Show `ghci`'s output as-is.
+    #[default]
+    Ghci,
+    /// Hide `ghci`'s compiler output, and show each diagnostic with a snippet of the current
+    /// source, errors first.
+    Pretty,
+    /// Like `ghcid`, clear the screen after each compilation and show a summary which fits in
+    /// the terminal, starting with the most recently modified files.
+    Ghcid,
+}
+
+/// Renders compilation results for the terminal, according to a [`DisplayMode`].
+#[derive(Debug, Clone, Default)]
+pub struct TerminalDisplay {
+    mode: DisplayMode,
+    /// Whether the output is shown directly in a terminal (rather than the TUI or a pipe).
+    ///
+    /// If so, file names and error codes are linked with OSC 8 hyperlinks, and the `ghcid`
+    /// display clears the screen and fits in it.
+    terminal: bool,
+    /// Whether to use colors.
+    colors: bool,
+}
+
+impl TerminalDisplay {
+    /// Construct a display for the given mode.
+    ///
+    /// In TUI mode, colors are always used (the TUI renders them), and the TUI handles scrolling.
+    pub fn new(mode: DisplayMode, tui: bool) -> Self {
+        Self {
+            mode,
+            terminal: !tui && std::io::stdout().is_terminal(),
+            colors: tui || supports_color::on(supports_color::Stream::Stdout).is_some(),
+        }
+    }
+
+    /// Does this display replace `ghci`'s compiler output?
+    pub fn hides_ghci_output(&self) -> bool {
+        self.mode != DisplayMode::Ghci
+    }
+
+    /// Render the diagnostics from a compilation `log`.
+    ///
+    /// Relative paths are resolved from `cwd`. Returns `None` if `ghci`'s output is shown as-is
+    /// instead.
+    pub fn render(&self, log: &CompilationLog, cwd: &Utf8Path) -> Option<String> {
+        match self.mode {
+            DisplayMode::Ghci => None,
+            DisplayMode::Pretty => Some(pretty::render(
+                &log.diagnostics,
+                cwd,
+                &self.report_handler(),
+                self.terminal,
+            )),
+            DisplayMode::Ghcid => {
+                let mut rendered = String::new();
+                let mut height = None;
+                if self.terminal {
+                    rendered.push_str(&clear_sequence());
+                    // Leave the last row for the cursor.
+                    height = crossterm::terminal::size()
+                        .ok()
+                        .map(|(_columns, rows)| usize::from(rows).saturating_sub(1));
+                }
+                rendered.push_str(&ghcid::render(
+                    log,
+                    cwd,
+                    self.colors,
+                    textwrap::termwidth(),
+                    height,
+                ));
+                Some(rendered)
+            }
+        }
+    }
+
+    fn report_handler(&self) -> GraphicalReportHandler {
+        let theme = if self.colors {
+            GraphicalTheme::unicode()
+        } else {
+            GraphicalTheme::unicode_nocolor()
+        };
+        GraphicalReportHandler::new_themed(theme)
+            .with_links(self.terminal)
+            .with_width(textwrap::termwidth())
+    }
+}
+
+/// Get the escape sequence to clear the terminal.
+fn clear_sequence() -> String {
+    let mut sequence = Vec::new();
+    if let Err(err) = clearscreen::ClearScreen::default().clear_to(&mut sequence) {
+        tracing::debug!("Failed to clear the terminal: {err}");
+    }
+    String::from_utf8_lossy(&sequence).into_owned()
+}
@@ -0,0 +1,402 @@
+//! Rendering diagnostics with source snippets, using [`miette`]'s graphical reports.
+
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fmt::Write;
+
+use camino::Utf8Path;
+use miette::Diagnostic;
+use miette::GraphicalReportHandler;
+use miette::LabeledSpan;
+use miette::NamedSource;
+use miette::SourceCode;
+use miette::SourceSpan;
+
+use crate::dedent::dedent;
+use crate::file_uri::file_uri;
+use crate::ghci::parse::GhcDiagnostic;
+use crate::ghci::parse::Position;
+use crate::ghci::parse::PositionRange;
+use crate::ghci::parse::Severity;
+
+/// Render `diagnostics`, errors first.
+///
+/// Source snippets are read from the files as they are now, rather than taken from `ghci`'s
+/// output. Relative paths are resolved from `cwd`.
+pub fn render(
+    diagnostics: &[GhcDiagnostic],
+    cwd: &Utf8Path,
+    handler: &GraphicalReportHandler,
+    links: bool,
+) -> String {
+    let mut diagnostics = diagnostics.iter().collect::<Vec<_>>();
+    // The sort is stable, so diagnostics of the same severity stay in `ghci`'s order.
+    diagnostics.sort_by_key(|diagnostic| diagnostic.severity != Severity::Error);
+
+    let mut sources: HashMap<&Utf8Path, Option<String>> = HashMap::new();
+    let mut rendered = String::new();
+    for diagnostic in diagnostics {
+        let source = diagnostic.path.as_deref().and_then(|path| {
+            let contents = sources
+                .entry(path)
+                .or_insert_with(|| match std::fs::read_to_string(cwd.join(path)) {
+                    Ok(contents) => Some(contents),
+                    Err(err) => {
+                        tracing::debug!(%path, "Failed to read source file: {err}");
+                        None
+                    }
+                })
+                .clone()?;
+            let line = diagnostic.span.start().line();
+            Some((source_name(path, cwd, line, links), contents))
+        });
+
+        let pretty = PrettyDiagnostic::new(diagnostic, source);
+        if let Err(err) = handler.render_report(&mut rendered, &pretty) {
+            tracing::debug!("Failed to render diagnostic: {err}");
+            rendered.push_str(&diagnostic.to_string());
+        }
+    }
+    rendered
+}
+
+/// The name to show for a source file: its path relative to `cwd`, optionally wrapped in an OSC
+/// 8 hyperlink to the `line` in the file.
+fn source_name(path: &Utf8Path, cwd: &Utf8Path, line: usize, links: bool) -> String {
+    let absolute = cwd.join(path);
+    let relative = absolute.strip_prefix(cwd).unwrap_or(path);
+    if links {
+        format!(
+            "\x1b]8;;{}#{line}\x1b\\{relative}\x1b]8;;\x1b\\",
+            file_uri(&absolute)
+        )
+    } else {
+        relative.to_string()
+    }
+}
+
+/// A [`GhcDiagnostic`] adapted for rendering with [`miette`].
+#[derive(Debug)]
+struct PrettyDiagnostic {
+    severity: Severity,
+    /// The error code or warning flag.
+    code: Option<String>,
+    url: Option<String>,
+    message: String,
+    /// The source file and the diagnostic's span in it, if the span could be found.
+    source: Option<(NamedSource, SourceSpan)>,
+}
+
+impl PrettyDiagnostic {
+    /// Adapt a diagnostic, given the name and current contents of its source file.
+    fn new(diagnostic: &GhcDiagnostic, source: Option<(String, String)>) -> Self {
+        let source = source.and_then(|(name, contents)| {
+            let span = source_span(&contents, diagnostic.span)?;
+            Some((NamedSource::new(name, contents), span))
+        });
+
+        Self {
+            severity: diagnostic.severity,
+            code: diagnostic.code.clone().or_else(|| diagnostic.flag.clone()),
+            url: diagnostic.error_index_url(),
+            message: message(diagnostic, source.is_some()),
+            source,
+        }
+    }
+}
+
+impl Display for PrettyDiagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl std::error::Error for PrettyDiagnostic {}
+
+impl Diagnostic for PrettyDiagnostic {
+    fn code<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.code
+            .as_ref()
+            .map(|code| Box::new(code) as Box<dyn Display>)
+    }
+
+    fn severity(&self) -> Option<miette::Severity> {
+        Some(match self.severity {
+            Severity::Warning => miette::Severity::Warning,
+            Severity::Error => miette::Severity::Error,
+        })
+    }
+
+    fn url<'a>(&'a self) -> Option<Box<dyn Display + 'a>> {
+        self.url
+            .as_ref()
+            .map(|url| Box::new(url) as Box<dyn Display>)
+    }
+
+    fn source_code(&self) -> Option<&dyn SourceCode> {
+        self.source
+            .as_ref()
+            .map(|(source, _)| source as &dyn SourceCode)
+    }
+
+    fn labels(&self) -> Option<Box<dyn Iterator<Item = LabeledSpan> + '_>> {
+        self.source.as_ref().map(|(_, span)| {
+            Box::new(std::iter::once(LabeledSpan::new_with_span(None, *span)))
+                as Box<dyn Iterator<Item = LabeledSpan>>
+        })
+    }
+}
+
+/// Get the message to show for a diagnostic.
+///
+/// The error code and warning flag are removed (they're shown in the report's header), along with
+/// common indentation. If the report includes a source snippet, `ghci`'s snippet (which may be out
+/// of date) is removed; otherwise, the location is shown before the message.
+fn message(diagnostic: &GhcDiagnostic, has_snippet: bool) -> String {
+    let (first_line, rest) = diagnostic
+        .message
+        .split_once('\n')
+        .unwrap_or((&diagnostic.message, ""));
+
+    let mut first_line = first_line.trim();
+    while let Some((_, after)) = first_line
+        .strip_prefix('[')
+        .and_then(|group| group.split_once(']'))
+    {
+        first_line = after.trim_start();
+    }
+
+    let lines = rest
+        .lines()
+        .filter(|line| !(has_snippet && is_snippet_line(line)))
+        .collect::<Vec<_>>();
+
+    let message = format!("{first_line}\n{}", dedent(&lines));
+    let message = message.trim();
+
+    match &diagnostic.path {
+        Some(path) if !has_snippet => {
+            let mut location = path.to_string();
+            if !diagnostic.span.is_zero() {
+                let _ = write!(location, ":{}", diagnostic.span);
+            }
+            format!("{location}: {message}")
+        }
+        _ => message.to_owned(),
+    }
+}
+
+/// Is this line part of a source snippet printed by GHC, like `4 | example = 1`?
+fn is_snippet_line(line: &str) -> bool {
+    line.trim_start()
+        .trim_start_matches(|c: char| c.is_ascii_digit())
+        .trim_start()
+        .starts_with('|')
+}
+
+/// Convert a `span` of lines and columns into a span of bytes in `source`.
+///
+/// Returns `None` if the span isn't in `source`, which may have changed since it was compiled.
+fn source_span(source: &str, span: PositionRange) -> Option<SourceSpan> {
+    let start = offset(source, span.start())?;
+    // The end column is inclusive.
+    let end = offset(source, span.end())?;
+    if end < start {
+        return None;
+    }
+    // Include the last character, unless the span ends at the end of the line.
+    let end = end
+        + source[end..]
+            .chars()
+            .next()
+            .filter(|c| *c != '\n')
+            .map(char::len_utf8)
+            .unwrap_or(0);
+    Some((start, end - start).into())
+}
+
+/// Convert a 1-based line and column into a byte offset in `source`.
+///
+/// Like GHC, tabs advance the column to the next multiple of 8. Columns past the end of the line
+/// are clamped to the end of the line.
+fn offset(source: &str, position: Position) -> Option<usize> {
+    let line_start = match position.line() {
+        0 => return None,
+        1 => 0,
+        line => source.match_indices('\n').nth(line - 2)?.0 + 1,
+    };
+    let line = source[line_start..].split('\n').next().unwrap_or_default();
+
+    let mut column = 1;
+    for (offset, c) in line.char_indices() {
+        if column >= position.column() {
+            return Some(line_start + offset);
+        }
+        column = if c == '\t' {
+            ((column - 1) / 8 + 1) * 8 + 1
+        } else {
+            column + 1
+        };
+    }
+    Some(line_start + line.len())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use expect_test::expect;
+    use indoc::indoc;
+    use miette::GraphicalTheme;
+    use pretty_assertions::assert_eq;
+
+    fn handler() -> GraphicalReportHandler {
+        GraphicalReportHandler::new_themed(GraphicalTheme::unicode_nocolor())
+            .with_links(false)
+            .with_width(80)
+    }
+
+    fn render_one(diagnostic: &GhcDiagnostic, source: &str) -> String {
+        let mut rendered = String::new();
+        handler()
+            .render_report(
+                &mut rendered,
+                &PrettyDiagnostic::new(
+                    diagnostic,
+                    Some(("src/MyLib.hs".to_owned(), source.to_owned())),
+                ),
+            )
+            .unwrap();
+        rendered
+    }
+
+    #[test]
+    fn test_render_snippet() {
+        let diagnostic = GhcDiagnostic {
+            code: Some("GHC-39999".into()),
+            message: indoc!(
+                "
+                [GHC-39999]
+                    • No instance for ‘Num String’ arising from a use of ‘+’
+                    • In the expression: \"foo\" + 1
+                  |
+                4 | example = \"foo\" + 1
+                  |           ^^^^^^^^^
+                "
+            )
+            .into(),
+            span: PositionRange::new(4, 11, 4, 19),
+            ..GhcDiagnostic::new(Severity::Error, Some("src/MyLib.hs"), "")
+        };
+        let source = indoc!(
+            "
+            module MyLib (example) where
+
+            -- An example.
+            example = \"foo\" + 1
+            "
+        );
+
+        expect![[r#"
+            GHC-39999 (https://errors.haskell.org/messages/GHC-39999)
+
+              × • No instance for ‘Num String’ arising from a use of ‘+’
+              │ • In the expression: "foo" + 1
+               ╭─[src/MyLib.hs:3:1]
+             3 │ -- An example.
+             4 │ example = "foo" + 1
+               ·           ─────────
+               ╰────
+        "#]]
+        .assert_eq(&render_one(&diagnostic, source));
+    }
+
+    #[test]
+    fn test_render_without_snippet() {
+        let diagnostic = GhcDiagnostic {
+            flag: Some("-Wunused-imports".into()),
+            message: "[-Wunused-imports]\n    The import of ‘Data.List’ is redundant\n".into(),
+            span: PositionRange::new(3, 1, 3, 17),
+            ..GhcDiagnostic::new(Severity::Warning, Some("src/Gone.hs"), "")
+        };
+
+        expect![[r#"
+            -Wunused-imports
+
+              ⚠ src/Gone.hs:3:1-17: The import of ‘Data.List’ is redundant
+        "#]]
+        .assert_eq(&render(
+            &[diagnostic],
+            "/does/not/exist".into(),
+            &handler(),
+            false,
+        ));
+    }
+
+    #[test]
+    fn test_render_errors_first() {
+        let warning = GhcDiagnostic {
+            span: PositionRange::new(1, 1, 1, 1),
+            ..GhcDiagnostic::new(Severity::Warning, Some("A.hs"), "\n    First warning\n")
+        };
+        let error = GhcDiagnostic {
+            span: PositionRange::new(1, 1, 1, 1),
+            ..GhcDiagnostic::new(Severity::Error, Some("B.hs"), "\n    An error\n")
+        };
+        let mut second_warning = warning.clone();
+        second_warning.message = "\n    Second warning\n".into();
+
+        let rendered = render(
+            &[warning, error, second_warning],
+            "/does/not/exist".into(),
+            &handler(),
+            false,
+        );
+        let order = ["An error", "First warning", "Second warning"]
+            .map(|message| rendered.find(message).unwrap());
+        assert!(order.windows(2).all(|pair| pair[0] < pair[1]), "{rendered}");
+    }
+
+    #[test]
+    fn test_source_span() {
+        let source = "one\n\ttwo ü\nthree\n";
+        assert_eq!(
+            source_span(source, PositionRange::new(1, 1, 1, 3)),
+            Some((0, 3).into())
+        );
+        // Tabs advance to the next multiple of 8 columns.
+        assert_eq!(
+            source_span(source, PositionRange::new(2, 9, 2, 13)),
+            Some((5, 6).into())
+        );
+        // Multi-line spans.
+        assert_eq!(
+            source_span(source, PositionRange::new(2, 13, 3, 1)),
+            Some((9, 4).into())
+        );
+        // Zero-width spans at the end of a line.
+        assert_eq!(
+            source_span(source, PositionRange::new(1, 4, 1, 4)),
+            Some((3, 0).into())
+        );
+        // The file has changed since it was compiled.
+        assert_eq!(source_span(source, PositionRange::new(10, 1, 10, 1)), None);
+    }
+
+    #[test]
+    fn test_source_name() {
+        assert_eq!(
+            source_name("src/MyLib.hs".into(), "/home/me/project".into(), 4, false),
+            "src/MyLib.hs"
+        );
+        assert_eq!(
+            source_name(
+                "/home/me/project/src/MyLib.hs".into(),
+                "/home/me/project".into(),
+                4,
+                true
+            ),
+            "\x1b]8;;file:///home/me/project/src/MyLib.hs#4\x1b\\src/MyLib.hs\x1b]8;;\x1b\\"
+        );
+    }
+}
@@ -0,0 +1,210 @@
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use miette::Context;
+use miette::IntoDiagnostic;
+use serde::Serialize;
+use tokio::fs::File;
+use tokio::io::AsyncWriteExt;
+use tracing::instrument;
+
+use super::parse::CompilationResult;
+use super::parse::CompilationSummary;
+use super::parse::GhcDiagnostic;
+use super::CompilationLog;
+
+mod sarif;
+
+/// The format to write the error log in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ErrorLogFormat {
+    /// `ghcid`-compatible plain text.
+    #[default]
+    Ghcid,
+    /// A JSON object with the compilation summary and a list of diagnostics.
+    Json,
+    /// A SARIF 2.1.0 log, for code scanning tools.
+    Sarif,
+}
+
+/// Error log writer.
+///
+/// By default, this produces `ghcid`-compatible output, which can be consumed by `ghcid` plugins
+/// in your editor of choice.
+pub struct ErrorLog {
+    path: Option<Utf8PathBuf>,
+    format: ErrorLogFormat,
+}
+
+impl ErrorLog {
+    /// Construct a new error log writer for the given path.
+    pub fn new(path: Option<Utf8PathBuf>, format: ErrorLogFormat) -> Self {
+        Self { path, format }
+    }
+
+    /// Write the error log, if any, with the given compilation summary and diagnostic messages.
+    ///
+    /// The log is written to a temporary file and then renamed into place, so readers never see a
+    /// partially-written log.
+    #[instrument(skip(self, log), name = "error_log_write", level = "debug")]
+    pub async fn write(&mut self, log: &CompilationLog) -> miette::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => {
+                tracing::debug!("No error log path, not writing");
+                return Ok(());
+            }
+        };
+
+        let contents = match self.format {
+            ErrorLogFormat::Ghcid => ghcid_contents(log),
+            ErrorLogFormat::Json => {
+                let mut contents =
+                    serde_json::to_string_pretty(&JsonLog::new(log)).into_diagnostic()?;
+                contents.push('\n');
+                contents
+            }
+            ErrorLogFormat::Sarif => sarif::sarif_contents(log)?,
+        };
+
+        write_atomic(path, contents.as_bytes())
+            .await
+            .wrap_err_with(|| format!("Failed to write error log to {path}"))
+    }
+}
+
+/// The error log in [`ErrorLogFormat::Json`].
+#[derive(Serialize)]
+struct JsonLog<'a> {
+    /// The compilation summary, if compilation finished.
+    summary: Option<CompilationSummary>,
+    /// Errors and warnings from compilation, followed by test failures.
+    diagnostics: &'a [GhcDiagnostic],
+}
+
+impl<'a> JsonLog<'a> {
+    fn new(log: &'a CompilationLog) -> Self {
+        Self {
+            summary: log.summary,
+            diagnostics: &log.diagnostics,
+        }
+    }
+}
+
+/// Format the error log like `ghcid` does.
+fn ghcid_contents(log: &CompilationLog) -> String {
+    let mut contents = String::new();
+
+    if let Some(summary) = log.summary {
+        // `ghcid` only writes the headline if there's no errors.
+        if let CompilationResult::Ok = summary.result {
+            tracing::debug!("Writing 'All good'");
+            let modules_loaded = if summary.modules_loaded != 1 {
+                format!("{} modules", summary.modules_loaded)
+            } else {
+                format!("{} module", summary.modules_loaded)
+            };
+            contents.push_str(&format!("All good ({modules_loaded})\n"));
+        }
+    }
+
+    for diagnostic in &log.diagnostics {
+        tracing::debug!(%diagnostic, "Writing diagnostic");
+        contents.push_str(&diagnostic.to_string());
+    }
+
+    contents
+}
+
+/// Write `contents` to a temporary file next to `path` and then rename it to `path`.
+pub async fn write_atomic(path: &Utf8Path, contents: &[u8]) -> miette::Result<()> {
+    let temp_path = path.with_file_name(format!(
+        ".{}.tmp",
+        path.file_name().unwrap_or("ghciwatch-error-log")
+    ));
+
+    let mut file = File::create(&temp_path).await.into_diagnostic()?;
+    file.write_all(contents).await.into_diagnostic()?;
+    // This is load-bearing! If we don't properly flush/shutdown the handle, nothing gets
+    // written!
+    file.shutdown().await.into_diagnostic()?;
+    drop(file);
+
+    tokio::fs::rename(&temp_path, path)
+        .await
+        .into_diagnostic()
+        .wrap_err_with(|| format!("Failed to rename {temp_path} to {path}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use crate::ghci::parse::parse_ghc_messages;
+
+    fn compilation_log(output: &str) -> CompilationLog {
+        let mut log = CompilationLog::default();
+        log.extend(parse_ghc_messages(output).unwrap());
+        log
+    }
+
+    #[test]
+    fn test_ghcid_contents() {
+        assert_eq!(
+            ghcid_contents(&compilation_log("Ok, 3 modules loaded.\n")),
+            "All good (3 modules)\n"
+        );
+
+        assert_eq!(
+            ghcid_contents(&compilation_log(indoc!(
+                "
+                src/MyLib.hs:4:11: error:
+                    Oh no!
+
+                Failed, no modules loaded.
+                "
+            ))),
+            indoc!(
+                "
+                src/MyLib.hs:4:11: error:
+                    Oh no!
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_json_contents() {
+        let log = compilation_log(indoc!(
+            "
+            src/MyLib.hs:4:11-13: warning: [-Wunused-matches]
+                Defined but not used: `x'
+
+            Ok, one module loaded.
+            "
+        ));
+        assert_eq!(
+            serde_json::to_value(JsonLog::new(&log)).unwrap(),
+            serde_json::json!({
+                "summary": {
+                    "result": "ok",
+                    "modules_loaded": 1,
+                },
+                "diagnostics": [
+                    {
+                        "severity": "warning",
+                        "path": "src/MyLib.hs",
+                        "span": {
+                            "start": { "line": 4, "column": 11 },
+                            "end": { "line": 4, "column": 13 },
+                        },
+                        "flag": "-Wunused-matches",
+                        "message": "[-Wunused-matches]\n    Defined but not used: `x'\n",
+                    },
+                ],
+            })
+        );
+    }
+}
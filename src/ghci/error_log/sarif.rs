@@ -0,0 +1,288 @@
+//! [SARIF 2.1.0][sarif] output for the error log.
+//!
+//! [sarif]: https://docs.oasis-open.org/sarif/sarif/v2.1.0/sarif-v2.1.0.html
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+
+use camino::Utf8Path;
+use miette::IntoDiagnostic;
+use serde::Serialize;
+
+use crate::file_uri::file_uri;
+use crate::file_uri::percent_encode_path;
+use crate::ghci::parse::GhcDiagnostic;
+use crate::ghci::parse::Severity;
+use crate::ghci::CompilationLog;
+
+/// The URI base ID that relative paths in the log are resolved against.
+const SRCROOT: &str = "%SRCROOT%";
+
+/// Format the error log as a SARIF log.
+pub fn sarif_contents(log: &CompilationLog) -> miette::Result<String> {
+    let src_root = crate::current_dir_utf8()?;
+    let mut contents =
+        serde_json::to_string_pretty(&SarifLog::new(log, &src_root)).into_diagnostic()?;
+    contents.push('\n');
+    Ok(contents)
+}
+
+#[derive(Debug, Serialize)]
+struct SarifLog {
+    #[serde(rename = "$schema")]
+    schema: &'static str,
+    version: &'static str,
+    runs: [Run; 1],
+}
+
+impl SarifLog {
+    fn new(log: &CompilationLog, src_root: &Utf8Path) -> Self {
+        // The base URI must end with a slash to resolve relative paths against it.
+        let mut src_root = file_uri(src_root);
+        if !src_root.ends_with('/') {
+            src_root.push('/');
+        }
+
+        Self {
+            schema: "https://json.schemastore.org/sarif-2.1.0.json",
+            version: "2.1.0",
+            runs: [Run {
+                tool: Tool {
+                    driver: Driver {
+                        name: "ghciwatch",
+                        version: env!("CARGO_PKG_VERSION"),
+                        information_uri: env!("CARGO_PKG_REPOSITORY"),
+                        rules: rules(log),
+                    },
+                },
+                original_uri_base_ids: BTreeMap::from([(
+                    SRCROOT,
+                    ArtifactLocation {
+                        uri: src_root,
+                        uri_base_id: None,
+                    },
+                )]),
+                results: log.diagnostics.iter().map(SarifResult::from).collect(),
+            }],
+        }
+    }
+}
+
+/// Get a rule for each error code and warning flag in the log, linking error codes to the Haskell
+/// Error Index.
+fn rules(log: &CompilationLog) -> Vec<ReportingDescriptor> {
+    log.diagnostics
+        .iter()
+        .filter_map(|diagnostic| {
+            rule_id(diagnostic).map(|id| (id.to_owned(), diagnostic.error_index_url()))
+        })
+        .collect::<BTreeSet<_>>()
+        .into_iter()
+        .map(|(id, help_uri)| ReportingDescriptor { id, help_uri })
+        .collect()
+}
+
+/// Diagnostics are identified by their error code, or their warning flag on older compilers.
+fn rule_id(diagnostic: &GhcDiagnostic) -> Option<&str> {
+    diagnostic.code.as_deref().or(diagnostic.flag.as_deref())
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Run {
+    tool: Tool,
+    original_uri_base_ids: BTreeMap<&'static str, ArtifactLocation>,
+    results: Vec<SarifResult>,
+}
+
+#[derive(Debug, Serialize)]
+struct Tool {
+    driver: Driver,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Driver {
+    name: &'static str,
+    version: &'static str,
+    information_uri: &'static str,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    rules: Vec<ReportingDescriptor>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ReportingDescriptor {
+    id: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    help_uri: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SarifResult {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    rule_id: Option<String>,
+    level: &'static str,
+    message: Message,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    locations: Vec<Location>,
+}
+
+impl From<&GhcDiagnostic> for SarifResult {
+    fn from(diagnostic: &GhcDiagnostic) -> Self {
+        let locations = match &diagnostic.path {
+            Some(path) => {
+                let region = (!diagnostic.span.is_zero()).then(|| {
+                    let start = diagnostic.span.start();
+                    let end = diagnostic.span.end();
+                    Region {
+                        start_line: start.line(),
+                        start_column: start.column(),
+                        end_line: end.line(),
+                        // SARIF end columns are exclusive; GHC's are inclusive.
+                        end_column: end.column() + 1,
+                    }
+                });
+                let artifact_location = if path.is_absolute() {
+                    ArtifactLocation {
+                        uri: file_uri(path),
+                        uri_base_id: None,
+                    }
+                } else {
+                    ArtifactLocation {
+                        uri: percent_encode_path(path),
+                        uri_base_id: Some(SRCROOT),
+                    }
+                };
+                vec![Location {
+                    physical_location: PhysicalLocation {
+                        artifact_location,
+                        region,
+                    },
+                }]
+            }
+            None => Vec::new(),
+        };
+
+        Self {
+            rule_id: rule_id(diagnostic).map(ToOwned::to_owned),
+            level: match diagnostic.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            },
+            message: Message {
+                text: diagnostic.message.trim().to_owned(),
+            },
+            locations,
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+struct Message {
+    text: String,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Location {
+    physical_location: PhysicalLocation,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhysicalLocation {
+    artifact_location: ArtifactLocation,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    region: Option<Region>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct ArtifactLocation {
+    uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    uri_base_id: Option<&'static str>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Region {
+    start_line: usize,
+    start_column: usize,
+    end_line: usize,
+    end_column: usize,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use crate::ghci::parse::parse_ghc_messages;
+
+    #[test]
+    fn test_sarif_log() {
+        let mut log = CompilationLog::default();
+        log.extend(
+            parse_ghc_messages(indoc!(
+                "
+                src/My Lib.hs:(4,11)-(5,2): error: [GHC-83865]
+                    Oh no!
+
+                <no location info>: warning:
+                    Uh oh.
+
+                Failed, no modules loaded.
+                "
+            ))
+            .unwrap(),
+        );
+
+        let sarif = serde_json::to_value(SarifLog::new(&log, "/home/me/project".into())).unwrap();
+        let run = &sarif["runs"][0];
+        assert_eq!(sarif["version"], "2.1.0");
+        assert_eq!(
+            run["tool"]["driver"]["rules"],
+            serde_json::json!([{
+                "id": "GHC-83865",
+                "helpUri": "https://errors.haskell.org/messages/GHC-83865",
+            }])
+        );
+        assert_eq!(
+            run["originalUriBaseIds"],
+            serde_json::json!({ "%SRCROOT%": { "uri": "file:///home/me/project/" } })
+        );
+        assert_eq!(
+            run["results"],
+            serde_json::json!([
+                {
+                    "ruleId": "GHC-83865",
+                    "level": "error",
+                    "message": { "text": "[GHC-83865]\n    Oh no!" },
+                    "locations": [{
+                        "physicalLocation": {
+                            "artifactLocation": {
+                                "uri": "src/My%20Lib.hs",
+                                "uriBaseId": "%SRCROOT%",
+                            },
+                            "region": {
+                                "startLine": 4,
+                                "startColumn": 11,
+                                "endLine": 5,
+                                "endColumn": 3,
+                            },
+                        },
+                    }],
+                },
+                {
+                    "level": "warning",
+                    "message": { "text": "Uh oh." },
+                },
+            ])
+        );
+    }
+}
@@ -1,13 +1,17 @@
 //! Subsystem for [`Ghci`] to support graceful shutdown.
 
 use std::collections::BTreeSet;
+use std::collections::VecDeque;
 use std::sync::Arc;
+use std::time::Duration;
+use std::time::Instant;
 
 use miette::miette;
 use miette::Context;
 use miette::IntoDiagnostic;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
+use tokio::sync::watch;
 use tokio::sync::Mutex;
 use tracing::instrument;
 
@@ -15,11 +19,22 @@ use crate::event_filter::FileEvent;
 use crate::ghci::CompilationLog;
 use crate::hooks;
 use crate::hooks::LifecycleEvent;
+use crate::shutdown::ShutdownError;
 use crate::shutdown::ShutdownHandle;
 
+use super::supervisor::Restart;
+use super::supervisor::Supervisor;
+use super::supervisor::CRASH_LOOP_CRASHES;
+use super::supervisor::CRASH_LOOP_WINDOW;
 use super::Ghci;
+use super::GhciCommand;
 use super::GhciOpts;
 use super::GhciReloadKind;
+use super::GhciState;
+use super::GhciStatus;
+
+/// How long to wait to notice that `ghci` exited after talking to it fails.
+const EXIT_GRACE_PERIOD: Duration = Duration::from_secs(1);
 
 /// An event sent to [`Ghci`].
 #[derive(Debug, Clone)]
@@ -28,49 +43,165 @@ pub enum GhciEvent {
     Reload {
         /// The file events to respond to.
         events: BTreeSet<FileEvent>,
+        /// Reload the session even if none of the `events` require it.
+        force: bool,
     },
+    /// Restart the `ghci` session.
+    Restart,
+    /// Run the test hooks.
+    Test,
+    /// Run a command typed by the user.
+    ///
+    /// Commands are queued and run in order once the session is idle; they don't interrupt
+    /// reloads, and aren't interrupted by them.
+    Command(GhciCommand),
+    /// Run the `--run` command, until it finishes or another event interrupts it.
+    ///
+    /// This is sent internally once the session is idle after a successful compilation.
+    Run,
+    /// Report test failures and the test summary from the test session.
+    ///
+    /// These are published once the session is idle; they don't interrupt other events.
+    TestResults(CompilationLog),
 }
 
 impl GhciEvent {
     /// When we interrupt an event to reload, add the file events together so that we don't lose
     /// work.
     fn merge(&mut self, other: GhciEvent) {
-        match (self, other) {
+        match (&mut *self, other) {
             (
-                GhciEvent::Reload { events },
+                GhciEvent::Reload { events, force },
                 GhciEvent::Reload {
                     events: other_events,
+                    force: other_force,
                 },
             ) => {
                 events.extend(other_events);
+                *force |= other_force;
+            }
+            // A restart reloads everything, so it subsumes any other event.
+            (GhciEvent::Restart, _) => {}
+            (_, GhciEvent::Restart) => {
+                *self = GhciEvent::Restart;
+            }
+            // Tests are run after successful reloads, so force a reload to make sure they run.
+            (GhciEvent::Reload { force, .. }, GhciEvent::Test) => {
+                *force = true;
+            }
+            (GhciEvent::Test, GhciEvent::Reload { events, .. }) => {
+                *self = GhciEvent::Reload {
+                    events,
+                    force: true,
+                };
+            }
+            (GhciEvent::Test, GhciEvent::Test) => {}
+            // Commands and test results are queued separately, so they're never merged.
+            (GhciEvent::Command(_), _)
+            | (_, GhciEvent::Command(_))
+            | (GhciEvent::TestResults(_), _)
+            | (_, GhciEvent::TestResults(_)) => {}
+            // The `--run` command is run again later if needed, so other events replace it.
+            (GhciEvent::Run, other) => {
+                *self = other;
             }
+            (_, GhciEvent::Run) => {}
         }
     }
 }
 
 /// Start the [`Ghci`] subsystem.
+///
+/// If `ghci` exits unexpectedly, it's restarted after a delay. If it fails to start or is
+/// crashing repeatedly, it's restarted when files change.
 #[instrument(skip_all, level = "debug")]
 pub async fn run_ghci(
     mut handle: ShutdownHandle,
     opts: GhciOpts,
     mut receiver: mpsc::Receiver<GhciEvent>,
 ) -> miette::Result<()> {
+    let mut supervisor = Supervisor::default();
+    loop {
+        let started = Instant::now();
+        let restart = match run_session(&mut handle, opts.clone(), &mut receiver).await? {
+            SessionEnd::Shutdown => break,
+            SessionEnd::StartupFailed => {
+                supervisor.reset();
+                tracing::error!("ghci failed to start; waiting for changes to restart it");
+                Restart::OnChange
+            }
+            SessionEnd::Crashed => {
+                let restart = supervisor.crashed(started, Instant::now());
+                match restart {
+                    Restart::After(delay) => {
+                        tracing::warn!("Restarting ghci in {delay:.2?}");
+                    }
+                    Restart::OnChange => {
+                        tracing::error!(
+                            "ghci crashed {CRASH_LOOP_CRASHES} times in {}s; waiting for changes to restart it",
+                            CRASH_LOOP_WINDOW.as_secs()
+                        );
+                    }
+                }
+                restart
+            }
+        };
+
+        opts.set_status(|status| status.state = GhciState::Crashed);
+        if !wait_to_restart(&mut handle, &mut receiver, restart).await? {
+            break;
+        }
+        opts.clear();
+    }
+
+    Ok(())
+}
+
+/// How a `ghci` session ended.
+enum SessionEnd {
+    /// `ghciwatch` is shutting down.
+    Shutdown,
+    /// `ghci` failed to start or to load modules for the first time.
+    StartupFailed,
+    /// `ghci` exited unexpectedly.
+    Crashed,
+}
+
+/// Run a single `ghci` session until it ends.
+async fn run_session(
+    handle: &mut ShutdownHandle,
+    opts: GhciOpts,
+    receiver: &mut mpsc::Receiver<GhciEvent>,
+) -> miette::Result<SessionEnd> {
     // This function is pretty tricky! We need to handle shutdowns at each stage, and the process
     // is a little different each time, so the `select!`s can't be consolidated.
 
+    opts.set_status(|status| status.state = GhciState::Starting);
+    let mut status = opts.status.subscribe();
+
     let no_interrupt_reloads = opts.no_interrupt_reloads;
-    let mut ghci = Ghci::new(handle.clone(), opts)
+    let mut ghci = match Ghci::new(handle.clone(), opts)
         .await
-        .wrap_err("Failed to start `ghci`")?;
+        .wrap_err("Failed to start `ghci`")
+    {
+        Ok(ghci) => ghci,
+        Err(err) => {
+            return crashed(err, SessionEnd::StartupFailed);
+        }
+    };
 
     // Wait for ghci to finish loading.
     let mut log = CompilationLog::default();
     tokio::select! {
         _ = handle.on_shutdown_requested() => {
             ghci.stop().await.wrap_err("Failed to quit ghci")?;
+            return Ok(SessionEnd::Shutdown);
         }
         startup_result = ghci.initialize(&mut log, [LifecycleEvent::Startup(hooks::When::After)]) => {
-            startup_result?;
+            if let Err(err) = startup_result {
+                ghci.stop().await.wrap_err("Failed to quit ghci")?;
+                return crashed(err, SessionEnd::StartupFailed);
+            }
         }
     }
 
@@ -78,28 +209,45 @@ pub async fn run_ghci(
     // The event to respond to. If we interrupt a reload, we may begin the loop with `Some(_)` in
     // here.
     let mut maybe_event = None;
+    // Commands typed by the user, to run once the session is idle.
+    let mut commands = VecDeque::new();
+    // The latest results from the test session, to publish once the session is idle.
+    let mut test_results = None;
     loop {
-        let mut event = match maybe_event.take() {
+        // Planned restarts come first, so that an unresponsive `ghci` isn't sent more commands.
+        // Other events are kept for after the restart.
+        let mut event = match planned_restart(&ghci)
+            .await
+            .or_else(|| maybe_event.take())
+            .or_else(|| commands.pop_front().map(GhciEvent::Command))
+            .or_else(|| test_results.take().map(GhciEvent::TestResults))
+        {
             Some(event) => event,
-            None => {
-                // If we don't already have an event to respond to, wait for filesystem events.
-                let event = tokio::select! {
-                    _ = handle.on_shutdown_requested() => {
-                        ghci.lock().await.stop().await.wrap_err("Failed to quit ghci")?;
-                        break;
-                    }
-                    ret = receiver.recv() => {
-                        ret.ok_or_else(|| miette!("ghci event channel closed"))?
-                    }
-                };
-                tracing::debug!(?event, "Received ghci event from watcher");
-                event
-            }
+            None => match idle_event(&ghci).await {
+                Some(event) => event,
+                None => {
+                    // If we don't already have an event to respond to, wait for one.
+                    let event = tokio::select! {
+                        _ = handle.on_shutdown_requested() => {
+                            ghci.lock().await.stop().await.wrap_err("Failed to quit ghci")?;
+                            return Ok(SessionEnd::Shutdown);
+                        }
+                        _ = wait_for_crash(&mut status) => {
+                            return Ok(SessionEnd::Crashed);
+                        }
+                        ret = receiver.recv() => {
+                            ret.ok_or_else(|| miette!("ghci event channel closed"))?
+                        }
+                    };
+                    tracing::debug!(?event, "Received ghci event");
+                    event
+                }
+            },
         };
 
         // This channel notifies us what kind of reload is triggered, which we can use to inform
         // our decision to interrupt the reload or not.
-        let (reload_sender, reload_receiver) = oneshot::channel();
+        let (reload_sender, mut reload_receiver) = oneshot::channel();
         // Dispatch the event. We spawn it into a new task so it can run in parallel to any
         // shutdown requests.
         let mut task = Box::pin(tokio::task::spawn(dispatch(
@@ -107,38 +255,203 @@ pub async fn run_ghci(
             event.clone(),
             reload_sender,
         )));
-        tokio::select! {
-            _ = handle.on_shutdown_requested() => {
-                // Cancel any in-progress reloads. This releases the lock so we don't block here.
-                task.abort();
-                ghci.lock().await.stop().await.wrap_err("Failed to quit ghci")?;
-                break;
-            }
-            Some(new_event) = receiver.recv() => {
-                tracing::debug!(?new_event, "Received ghci event from watcher while reloading");
-                if !no_interrupt_reloads && should_interrupt(reload_receiver).await {
+        // Whether the event can be interrupted, once we've heard from the task.
+        let mut interruptible = None;
+        loop {
+            tokio::select! {
+                _ = handle.on_shutdown_requested() => {
+                    // Cancel any in-progress reloads. This releases the lock so we don't block here.
+                    task.abort();
+                    ghci.lock().await.stop().await.wrap_err("Failed to quit ghci")?;
+                    return Ok(SessionEnd::Shutdown);
+                }
+                _ = wait_for_crash(&mut status) => {
+                    task.abort();
+                    return Ok(SessionEnd::Crashed);
+                }
+                Some(new_event) = receiver.recv() => {
+                    tracing::debug!(?new_event, "Received ghci event while reloading");
+                    // The `--run` command runs until it's interrupted, so it's always
+                    // interruptible.
+                    let running = matches!(event, GhciEvent::Run);
+                    let new_event = match new_event {
+                        GhciEvent::Command(command) => {
+                            commands.push_back(command);
+                            None
+                        }
+                        GhciEvent::TestResults(results) => {
+                            // Newer results replace older ones, and never interrupt anything.
+                            test_results = Some(results);
+                            continue;
+                        }
+                        new_event => Some(new_event),
+                    };
+                    if new_event.is_none() && !running {
+                        // Keep waiting for the current event to finish.
+                        continue;
+                    }
+                    let interrupt = running
+                        || (!no_interrupt_reloads
+                            && match interruptible {
+                                Some(interruptible) => interruptible,
+                                None => *interruptible
+                                    .insert(should_interrupt(&mut reload_receiver).await),
+                            });
+                    if !interrupt {
+                        // Respond to the new event once the current one finishes.
+                        if let Some(new_event) = new_event {
+                            match &mut maybe_event {
+                                Some(pending) => pending.merge(new_event),
+                                None => maybe_event = Some(new_event),
+                            }
+                        }
+                        continue;
+                    }
+
                     // Merge the events together so we don't lose progress.
                     // Then, the next iteration of the loop will pick up the `maybe_event` value
                     // and respond immediately.
-                    event.merge(new_event);
-                    maybe_event = Some(event);
+                    let pending = maybe_event.take();
+                    if pending.is_some() || new_event.is_some() {
+                        for new_event in pending.into_iter().chain(new_event) {
+                            event.merge(new_event);
+                        }
+                        maybe_event = Some(event);
+                    }
 
                     // Cancel the in-progress reload. This releases the `ghci` lock to prevent a deadlock.
                     task.abort();
 
                     // Send a SIGINT to interrupt the reload.
                     // NB: This may take a couple seconds to register.
-                    ghci.lock().await.send_sigint().await?;
+                    let mut ghci = ghci.lock().await;
+                    if let Err(err) = ghci.send_sigint().await {
+                        let exited = exited(&mut status).await;
+                        ghci.stop().await.wrap_err("Failed to quit ghci")?;
+                        return dispatch_failed(err, exited);
+                    }
+                    break;
+                }
+                ret = &mut task => {
+                    if let Err(err) = ret.into_diagnostic()? {
+                        let exited = exited(&mut status).await;
+                        ghci.lock().await.stop().await.wrap_err("Failed to quit ghci")?;
+                        return dispatch_failed(err, exited);
+                    }
+                    tracing::debug!("Finished dispatching ghci event");
+                    break;
                 }
-            }
-            ret = &mut task => {
-                ret.into_diagnostic()??;
-                tracing::debug!("Finished dispatching ghci event");
             }
         }
     }
+}
 
-    Ok(())
+/// Get a [`GhciEvent::Restart`] if `ghci` is due for a planned restart.
+async fn planned_restart(ghci: &Mutex<Ghci>) -> Option<GhciEvent> {
+    let reason = ghci.lock().await.planned_restart_reason()?;
+    tracing::info!("{reason}");
+    Some(GhciEvent::Restart)
+}
+
+/// Get an event to respond to once the session is idle.
+///
+/// If compilation succeeded, the `--run` command is run.
+async fn idle_event(ghci: &Mutex<Ghci>) -> Option<GhciEvent> {
+    if ghci.lock().await.run_pending {
+        Some(GhciEvent::Run)
+    } else {
+        None
+    }
+}
+
+/// Wait for the session's state to become [`GhciState::Crashed`].
+async fn wait_for_crash(status: &mut watch::Receiver<GhciStatus>) {
+    if status
+        .wait_for(|status| status.state == GhciState::Crashed)
+        .await
+        .is_err()
+    {
+        // The session is gone, so it can't crash.
+        std::future::pending().await
+    }
+}
+
+/// Did `ghci` exit?
+///
+/// Talking to a `ghci` which exited fails a moment before the exit is noticed, so this waits
+/// briefly for the session's state to become [`GhciState::Crashed`].
+async fn exited(status: &mut watch::Receiver<GhciStatus>) -> bool {
+    tokio::time::timeout(EXIT_GRACE_PERIOD, wait_for_crash(status))
+        .await
+        .is_ok()
+}
+
+/// Handle an error from dispatching an event.
+///
+/// If `ghci` `exited`, the session crashed and is restarted. Other errors, like failing to read a
+/// file, are returned as-is.
+fn dispatch_failed(err: miette::Report, exited: bool) -> miette::Result<SessionEnd> {
+    if exited {
+        crashed(err, SessionEnd::Crashed)
+    } else {
+        Err(err)
+    }
+}
+
+/// Report an error which ended a `ghci` session.
+///
+/// Errors from shutting down are returned as-is, so that `ghciwatch` exits.
+fn crashed(err: miette::Report, end: SessionEnd) -> miette::Result<SessionEnd> {
+    if err.downcast_ref::<ShutdownError>().is_some() {
+        return Err(err);
+    }
+    tracing::error!("{err:?}");
+    Ok(end)
+}
+
+/// Wait until it's time to restart a crashed `ghci` session.
+///
+/// Returns `false` if `ghciwatch` is shutting down instead.
+async fn wait_to_restart(
+    handle: &mut ShutdownHandle,
+    receiver: &mut mpsc::Receiver<GhciEvent>,
+    restart: Restart,
+) -> miette::Result<bool> {
+    let delay = async {
+        match restart {
+            Restart::After(delay) => tokio::time::sleep(delay).await,
+            Restart::OnChange => std::future::pending().await,
+        }
+    };
+    tokio::pin!(delay);
+
+    loop {
+        tokio::select! {
+            _ = handle.on_shutdown_requested() => {
+                return Ok(false);
+            }
+            _ = &mut delay => {
+                return Ok(true);
+            }
+            ret = receiver.recv() => {
+                match ret.ok_or_else(|| miette!("ghci event channel closed"))? {
+                    GhciEvent::Test => {
+                        tracing::warn!("ghci isn't running; not running tests");
+                    }
+                    GhciEvent::Command(command) => {
+                        tracing::warn!("ghci isn't running; not running `{command}`");
+                    }
+                    GhciEvent::TestResults(_) => {
+                        tracing::debug!("ghci isn't running; not reporting test results");
+                    }
+                    event => {
+                        tracing::debug!(?event, "Restarting crashed ghci session");
+                        return Ok(true);
+                    }
+                }
+            }
+        }
+    }
 }
 
 #[instrument(level = "debug", skip(ghci, reload_sender))]
@@ -148,8 +461,34 @@ async fn dispatch(
     reload_sender: oneshot::Sender<GhciReloadKind>,
 ) -> miette::Result<()> {
     match event {
-        GhciEvent::Reload { events } => {
-            ghci.lock().await.reload(events, reload_sender).await?;
+        GhciEvent::Reload { events, force } => {
+            ghci.lock()
+                .await
+                .reload(events, force, reload_sender)
+                .await?;
+        }
+        GhciEvent::Restart => {
+            let _ = reload_sender.send(GhciReloadKind::Restart);
+            let mut ghci = ghci.lock().await;
+            ghci.opts.clear();
+            tracing::info!("Restarting ghci");
+            ghci.restart().await?;
+        }
+        GhciEvent::Test => {
+            let _ = reload_sender.send(GhciReloadKind::None);
+            ghci.lock().await.run_tests().await?;
+        }
+        GhciEvent::Command(command) => {
+            let _ = reload_sender.send(GhciReloadKind::None);
+            ghci.lock().await.run_user_command(&command).await?;
+        }
+        GhciEvent::Run => {
+            let _ = reload_sender.send(GhciReloadKind::None);
+            ghci.lock().await.run().await?;
+        }
+        GhciEvent::TestResults(results) => {
+            let _ = reload_sender.send(GhciReloadKind::None);
+            ghci.lock().await.publish_test_results(results).await?;
         }
     }
     Ok(())
@@ -157,7 +496,7 @@ async fn dispatch(
 
 /// Should we interrupt a reload with a new event?
 #[instrument(level = "debug", skip_all)]
-async fn should_interrupt(reload_receiver: oneshot::Receiver<GhciReloadKind>) -> bool {
+async fn should_interrupt(reload_receiver: &mut oneshot::Receiver<GhciReloadKind>) -> bool {
     let reload_kind = match reload_receiver.await {
         Ok(kind) => kind,
         Err(err) => {
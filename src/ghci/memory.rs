@@ -0,0 +1,112 @@
+//! Measuring how much memory the `ghci` session uses.
+
+use nix::unistd::Pid;
+
+/// Get the total resident set size of the processes in a process group, in bytes.
+///
+/// This reads `/proc`, so it only works on Linux; elsewhere, `None` is returned.
+pub fn process_group_rss(process_group_id: Pid) -> Option<u64> {
+    let entries = match std::fs::read_dir("/proc") {
+        Ok(entries) => entries,
+        Err(err) => {
+            tracing::debug!("Can't measure ghci's memory usage: {err}");
+            return None;
+        }
+    };
+
+    let mut total = 0;
+    for entry in entries.flatten() {
+        let pid = entry.file_name();
+        let pid = match pid.to_str() {
+            Some(pid) if pid.bytes().all(|byte| byte.is_ascii_digit()) => pid,
+            _ => continue,
+        };
+        // Processes can exit while we're looking at them, so errors here aren't a problem.
+        let in_group = std::fs::read_to_string(format!("/proc/{pid}/stat"))
+            .ok()
+            .and_then(|stat| parse_stat_process_group(&stat))
+            == Some(process_group_id.as_raw());
+        if !in_group {
+            continue;
+        }
+        if let Some(rss) = std::fs::read_to_string(format!("/proc/{pid}/status"))
+            .ok()
+            .and_then(|status| parse_status_rss(&status))
+        {
+            total += rss;
+        }
+    }
+    Some(total)
+}
+
+/// Get the process group ID from the contents of `/proc/<pid>/stat`.
+///
+/// See `proc(5)`: the fields are separated by spaces, but the second field is the command name in
+/// parentheses, which may itself contain spaces and parentheses.
+fn parse_stat_process_group(stat: &str) -> Option<i32> {
+    let (_pid_and_command, fields) = stat.rsplit_once(')')?;
+    // The fields after the command are the state, the parent PID, and then the process group ID.
+    fields.split_whitespace().nth(2)?.parse().ok()
+}
+
+/// Get the resident set size in bytes from the contents of `/proc/<pid>/status`.
+///
+/// Kernel threads don't have a `VmRSS` line.
+fn parse_status_rss(status: &str) -> Option<u64> {
+    let line = status
+        .lines()
+        .find_map(|line| line.strip_prefix("VmRSS:"))?;
+    let kilobytes = line.trim().strip_suffix("kB")?.trim();
+    kilobytes
+        .parse::<u64>()
+        .ok()
+        .map(|kilobytes| kilobytes * 1024)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_stat_process_group() {
+        assert_eq!(
+            parse_stat_process_group(
+                "4321 (ghc-9.6.2) S 4320 4300 4300 0 -1 4194560 12345 0 0 0 120 30 0 0 20 0 4 0\n"
+            ),
+            Some(4300)
+        );
+        assert_eq!(
+            parse_stat_process_group("17 (weird) name) R 1 17 17 0 -1"),
+            Some(17)
+        );
+        assert_eq!(parse_stat_process_group("garbage"), None);
+    }
+
+    #[test]
+    fn test_parse_status_rss() {
+        assert_eq!(
+            parse_status_rss(indoc!(
+                "
+                Name:\tghc-9.6.2
+                VmPeak:\t 2461028 kB
+                VmRSS:\t 1048576 kB
+                RssAnon:\t  1000000 kB
+                "
+            )),
+            Some(1 << 30)
+        );
+        assert_eq!(parse_status_rss("Name:\tkthreadd\n"), None);
+    }
+
+    #[test]
+    fn test_process_group_rss() {
+        if !cfg!(target_os = "linux") {
+            return;
+        }
+        let process_group_id = nix::unistd::getpgrp();
+        assert!(process_group_rss(process_group_id).unwrap() > 0);
+    }
+}
@@ -8,25 +8,33 @@ use owo_colors::Stream::Stdout;
 use std::borrow::Borrow;
 use std::collections::BTreeMap;
 use std::collections::BTreeSet;
+use std::collections::HashSet;
 use std::fmt::Debug;
 use std::path::Path;
 use std::process::ExitStatus;
 use std::process::Stdio;
+use std::sync::atomic::AtomicBool;
+use std::sync::Arc;
 use std::time::Instant;
 use tokio::io::DuplexStream;
 use tokio::sync::oneshot;
+use tokio::sync::watch;
 use tokio::task::JoinHandle;
 
 use aho_corasick::AhoCorasick;
 use camino::Utf8Path;
 use camino::Utf8PathBuf;
+use itertools::Itertools;
 use miette::miette;
 use miette::IntoDiagnostic;
 use miette::WrapErr;
 use nix::unistd::Pid;
+use serde::Serialize;
 use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncWriteExt;
 use tokio::io::BufReader;
 use tokio::sync::mpsc;
+use tokio::sync::mpsc::error::TrySendError;
 use tracing::instrument;
 
 mod stdin;
@@ -34,6 +42,7 @@ use stdin::GhciStdin;
 
 mod stdout;
 use stdout::GhciStdout;
+use stdout::ParseOutput;
 
 mod stderr;
 use stderr::GhciStderr;
@@ -42,12 +51,38 @@ mod process;
 use process::GhciProcess;
 
 pub mod manager;
+use manager::GhciEvent;
+
+mod test_session;
+pub use test_session::run_test_session;
+pub use test_session::TestSessionOpts;
 
 mod error_log;
 use error_log::ErrorLog;
+pub use error_log::ErrorLogFormat;
+
+mod module_graph;
+use module_graph::ModuleGraph;
+pub use module_graph::ModuleGraphFormat;
+
+mod diagnostic_store;
+use diagnostic_store::DiagnosticStore;
+
+mod diagnostic_filter;
+use diagnostic_filter::DiagnosticFilter;
+
+mod display;
+pub use display::DisplayMode;
+use display::TerminalDisplay;
+
+mod timings;
+use timings::slowest;
+use timings::TimingsFile;
+pub use timings::TimingsFormat;
 
 pub mod parse;
 use parse::parse_eval_commands;
+use parse::parse_imports;
 use parse::CompilationResult;
 use parse::EvalCommand;
 use parse::ModuleSet;
@@ -59,22 +94,51 @@ pub use ghci_command::GhciCommand;
 mod compilation_log;
 pub use compilation_log::CompilationLog;
 
+mod detect_command;
+use detect_command::detect_command;
+
+mod status;
+pub use status::GhciState;
+pub use status::GhciStatus;
+
+mod supervisor;
+
+mod standby;
+use standby::Standby;
+
+mod added_modules;
+use added_modules::AddedModules;
+
+mod memory;
+use memory::process_group_rss;
+
+mod timeout;
+use timeout::with_timeout;
+use timeout::Phase;
+
 mod writer;
 use crate::buffers::GHCI_BUFFER_CAPACITY;
 pub use crate::ghci::writer::GhciWriter;
+use crate::ghci::writer::Mute;
 
 use crate::aho_corasick::AhoCorasickExt;
 use crate::buffers::LINE_BUFFER_CAPACITY;
+use crate::clap::ByteSize;
 use crate::cli::Opts;
+use crate::cli::TimeoutOpts;
 use crate::clonable_command::ClonableCommand;
 use crate::event_filter::FileEvent;
+use crate::event_stream::EventSender;
+use crate::event_stream::StreamEvent;
 use crate::format_bulleted_list;
 use crate::haskell_source_file::is_haskell_source_file;
 use crate::hooks;
+use crate::hooks::Hook;
 use crate::hooks::HookOpts;
 use crate::hooks::LifecycleEvent;
 use crate::ignore::GlobMatcher;
 use crate::incremental_reader::IncrementalReader;
+use crate::maybe_async_command::MaybeAsyncCommand;
 use crate::normal_path::NormalPath;
 use crate::shutdown::ShutdownHandle;
 use crate::CommandExt;
@@ -98,22 +162,70 @@ pub struct GhciOpts {
     pub command: ClonableCommand,
     /// A path to write `ghci` errors to.
     pub error_path: Option<Utf8PathBuf>,
+    /// The format to write `ghci` errors in.
+    pub error_format: ErrorLogFormat,
+    /// Rules for hiding diagnostics from the terminal summary and the error log.
+    pub hide_diagnostics: DiagnosticFilter,
+    /// A path to write the module import graph to. The graph is only tracked if this is set.
+    pub module_graph_path: Option<Utf8PathBuf>,
+    /// The format to write the module import graph in.
+    pub module_graph_format: ModuleGraphFormat,
+    /// A path to write per-module compilation timings to.
+    pub timings_path: Option<Utf8PathBuf>,
+    /// The format to write per-module compilation timings in.
+    pub timings_format: TimingsFormat,
+    /// How many of the slowest modules to log after each compilation.
+    pub slowest_modules: usize,
     /// Enable running eval commands in files.
     pub enable_eval: bool,
     /// Lifecycle hooks, mostly `ghci` commands to run at certain points.
     pub hooks: HookOpts,
+    /// The modules `ghciwatch` has `:add`ed, to add again after restarts.
+    pub added_modules: AddedModules,
     /// Restart the `ghci` session when paths matching these globs are changed.
     pub restart_globs: GlobMatcher,
     /// Reload the `ghci` session when paths matching these globs are changed.
     pub reload_globs: GlobMatcher,
     /// Determines whether we should interrupt a reload in progress or not.
     pub no_interrupt_reloads: bool,
+    /// Restart the `ghci` session between reloads if it uses more than this much memory.
+    pub max_memory: Option<ByteSize>,
+    /// Restart the `ghci` session after this many reloads.
+    pub restart_every: Option<u64>,
+    /// Timeouts for compiling, eval commands, and tests.
+    pub timeouts: TimeoutOpts,
+    /// A long-running command to run after each successful compilation.
+    pub run: Option<GhciCommand>,
+    /// If tests run in a separate test session, where to send its events.
+    ///
+    /// After each successful compilation, the changes are sent there to be reloaded and tested,
+    /// instead of running the tests in this session.
+    pub test_session: Option<mpsc::Sender<GhciEvent>>,
+    /// For a test session, where to send test results, so that the main session can report them.
+    pub main_session: Option<mpsc::Sender<GhciEvent>>,
+    /// Log compilation progress at the `debug` level rather than `info`. This is set for the test
+    /// session, which compiles the same changes as the main session.
+    pub quiet: bool,
+    /// Keep a [`Standby`] session loaded in the background, to switch to when restarting.
+    pub standby: bool,
     /// Where to write what `ghci` emits to `stdout`. Inherits parent's `stdout` by default.
     pub stdout_writer: GhciWriter,
     /// Where to write what `ghci` emits to `stderr`. Inherits parent's `stderr` by default.
     pub stderr_writer: GhciWriter,
     /// Whether to clear the screen before reloads and restarts.
     pub clear: bool,
+    /// How to display compilation results.
+    pub display: TerminalDisplay,
+    /// Discards output from `stdout_writer` and `stderr_writer` while set, if `display` replaces
+    /// `ghci`'s compiler output.
+    pub mute: Mute,
+    /// Where to publish the session's [`GhciStatus`], for the control socket.
+    pub status: watch::Sender<GhciStatus>,
+    /// Where to publish the [`CompilationLog`] from each finished compilation, for the language
+    /// server.
+    pub compilation_log: watch::Sender<CompilationLog>,
+    /// Where to send lifecycle events, for the events file.
+    pub events: EventSender,
 }
 
 impl GhciOpts {
@@ -125,12 +237,28 @@ impl GhciOpts {
     /// If running in TUI mode, `ghci` output (from `stdout_writer` and `stderr_writer`) is sent to
     /// the stream given by the second return value.
     pub fn from_cli(opts: &Opts) -> miette::Result<(Self, Option<DuplexStream>)> {
-        // TODO: implement fancier default command
-        // See: https://github.com/ndmitchell/ghcid/blob/e2852979aa644c8fed92d46ab529d2c6c1c62b59/src/Ghcid.hs#L142-L171
+        let mut hooks = opts.hooks.clone();
         let command = match (&opts.file, &opts.command) {
             (Some(file), None) => ClonableCommand::new("ghci").arg(file.relative()),
             (None, Some(command)) => command.clone(),
-            (None, None) => ClonableCommand::new("cabal").arg("repl"),
+            (None, None) => {
+                let detected = detect_command(&crate::current_dir_utf8()?)?;
+                tracing::info!(
+                    "Using `{}` because {}; use `--command` to override",
+                    detected.command,
+                    detected.reason
+                );
+                if let Some(command) = detected.before_startup {
+                    hooks.prepend(Hook {
+                        event: LifecycleEvent::Startup(hooks::When::Before),
+                        command: hooks::Command::Shell(MaybeAsyncCommand {
+                            is_async: false,
+                            command,
+                        }),
+                    });
+                }
+                detected.command
+            }
             (Some(_), Some(_)) => unreachable!(),
         };
 
@@ -144,24 +272,65 @@ impl GhciOpts {
             stdout_writer = tui_writer.clone();
             stderr_writer = tui_writer.clone();
             tui_reader = Some(tui_reader_inner);
+        } else if opts.lsp {
+            // `stdout` is reserved for talking to the language client.
+            stdout_writer = GhciWriter::stderr();
+            stderr_writer = GhciWriter::stderr();
+            tui_reader = None;
         } else {
             stdout_writer = GhciWriter::stdout();
             stderr_writer = GhciWriter::stderr();
             tui_reader = None;
         }
 
+        if opts.max_memory.is_some() && !cfg!(target_os = "linux") {
+            tracing::warn!("`--max-memory` is only supported on Linux");
+        }
+
+        let display = TerminalDisplay::new(opts.display, opts.tui);
+        let mute = Mute::default();
+        let (stdout_writer, stderr_writer) = if display.hides_ghci_output() {
+            (
+                stdout_writer.with_mute(mute.clone()),
+                stderr_writer.with_mute(mute.clone()),
+            )
+        } else {
+            (stdout_writer, stderr_writer)
+        };
+
         Ok((
             Self {
                 command,
                 error_path: opts.error_file.clone(),
+                error_format: opts.error_file_format,
+                hide_diagnostics: DiagnosticFilter::from_rules(&opts.hide_diagnostics)?,
+                module_graph_path: opts.module_graph.clone(),
+                module_graph_format: opts.module_graph_format,
+                timings_path: opts.timings_file.clone(),
+                timings_format: opts.timings_file_format,
+                slowest_modules: opts.slowest_modules,
                 enable_eval: opts.enable_eval,
-                hooks: opts.hooks.clone(),
+                hooks,
+                added_modules: AddedModules::load(opts.state_file.clone())?,
                 restart_globs: opts.watch.restart_globs()?,
                 reload_globs: opts.watch.reload_globs()?,
                 no_interrupt_reloads: opts.no_interrupt_reloads,
+                max_memory: opts.max_memory,
+                restart_every: opts.restart_every,
+                timeouts: opts.timeouts.clone(),
+                run: opts.run.clone(),
+                test_session: None,
+                main_session: None,
+                quiet: false,
+                standby: opts.standby,
                 stdout_writer,
                 stderr_writer,
                 clear: opts.clear,
+                display,
+                mute,
+                status: watch::Sender::new(Default::default()),
+                compilation_log: watch::Sender::new(Default::default()),
+                events: Default::default(),
             },
             tui_reader,
         ))
@@ -176,6 +345,27 @@ impl GhciOpts {
             }
         }
     }
+
+    /// Hide `ghci`'s compiler output until compilation finishes, if `display` replaces it or
+    /// `quiet` is set.
+    fn hide_compiler_output(&self) {
+        self.mute
+            .set(self.quiet || self.display.hides_ghci_output());
+    }
+
+    /// Log progress through a compilation, at the `debug` level if `quiet` is set.
+    fn progress(&self, message: std::fmt::Arguments<'_>) {
+        if self.quiet {
+            tracing::debug!("{message}");
+        } else {
+            tracing::info!("{message}");
+        }
+    }
+
+    /// Update the published [`GhciStatus`].
+    fn set_status(&self, modify: impl FnOnce(&mut GhciStatus)) {
+        self.status.send_modify(modify);
+    }
 }
 
 /// A `ghci` session.
@@ -200,6 +390,8 @@ pub struct Ghci {
     restart_sender: mpsc::Sender<()>,
     /// Writer for `ghcid`-compatible output, useful for editor integration for diagnostics.
     error_log: ErrorLog,
+    /// Writer for per-module compilation timings.
+    timings_file: TimingsFile,
     /// The set of targets for this `ghci` session, from `:show targets`.
     ///
     /// Targets that fail to compile don't show up in `:show modules` and aren't, technically
@@ -208,12 +400,36 @@ pub struct Ghci {
     ///
     /// [ghc-13254]: https://gitlab.haskell.org/ghc/ghc/-/issues/13254
     targets: ModuleSet,
+    /// The import graph of the loaded modules, if `opts.module_graph_path` is set.
+    module_graph: ModuleGraph,
+    /// Warnings from previous compilations, kept until their modules are recompiled.
+    diagnostics: DiagnosticStore,
     /// Eval commands, if `opts.enable_eval` is set.
     eval_commands: BTreeMap<NormalPath, Vec<EvalCommand>>,
     /// Search paths / current working directory for this `ghci` session.
     search_paths: ShowPaths,
     /// Tasks running `async:` shell commands in the background.
     command_handles: Vec<JoinHandle<miette::Result<ExitStatus>>>,
+    /// The log from the last compilation, before any tests ran. Tests run on request start from
+    /// this log, so that stale test results are replaced.
+    last_compilation: CompilationLog,
+    /// How many times this session has been reloaded, for `opts.restart_every`.
+    reloads: u64,
+    /// Whether `ghci` didn't respond to being interrupted after a timeout. Unresponsive sessions
+    /// are restarted once they're idle.
+    unresponsive: bool,
+    /// Whether `opts.run` should be run once the session is idle.
+    ///
+    /// This is set after successful compilations, and cleared when compilation fails or the
+    /// command finishes on its own. If the command is interrupted, it's run again later.
+    run_pending: bool,
+    /// Changes which haven't been sent to the test session yet, if `opts.test_session` is set.
+    ///
+    /// These are sent after the next successful compilation, so that the test session adds and
+    /// removes the same modules as this one.
+    untested_events: BTreeSet<FileEvent>,
+    /// A session to switch to when restarting, if `opts.standby` is set.
+    standby: Option<Standby>,
 }
 
 impl Debug for Ghci {
@@ -239,6 +455,7 @@ impl Ghci {
                 .run_shell_hooks(
                     LifecycleEvent::Startup(hooks::When::Before),
                     &mut command_handles,
+                    &opts.events,
                 )
                 .await?;
         }
@@ -283,12 +500,17 @@ impl Ghci {
         // TODO: Is this a good capacity? Maybe it should just be 1.
         let (stderr_sender, stderr_receiver) = mpsc::channel(8);
 
-        let stdout = GhciStdout {
-            reader: IncrementalReader::new(stdout).with_writer(opts.stdout_writer.clone()),
+        let mut stdout = GhciStdout {
+            reader: IncrementalReader::new(stdout)
+                .with_writer(opts.stdout_writer.clone())
+                .with_timestamped_lines(AhoCorasick::from_anchored_patterns(["] Compiling "])),
             stderr_sender: stderr_sender.clone(),
+            events: opts.events.clone(),
+            parsing: Arc::new(AtomicBool::new(true)),
             buffer: vec![0; LINE_BUFFER_CAPACITY],
             prompt_patterns: AhoCorasick::from_anchored_patterns([PROMPT]),
         };
+        stdout.set_events(opts.events.clone());
 
         let stdin = GhciStdin {
             stdin,
@@ -316,12 +538,14 @@ impl Ghci {
                     shutdown,
                     restart_receiver,
                     process_group_id,
+                    status: opts.status.clone(),
                 }
                 .run(group)
             })
             .await;
 
-        let error_log = ErrorLog::new(opts.error_path.clone());
+        let error_log = ErrorLog::new(opts.error_path.clone(), opts.error_format);
+        let timings_file = TimingsFile::new(opts.timings_path.clone(), opts.timings_format);
 
         Ok(Ghci {
             opts,
@@ -331,13 +555,22 @@ impl Ghci {
             stdout,
             restart_sender,
             error_log,
+            timings_file,
             targets: Default::default(),
+            module_graph: Default::default(),
+            diagnostics: Default::default(),
             eval_commands: Default::default(),
             search_paths: ShowPaths {
                 cwd: crate::current_dir_utf8()?,
                 search_paths: Default::default(),
             },
             command_handles,
+            last_compilation: Default::default(),
+            reloads: 0,
+            unresponsive: false,
+            run_pending: false,
+            untested_events: Default::default(),
+            standby: None,
         })
     }
 
@@ -352,6 +585,8 @@ impl Ghci {
     ) -> miette::Result<()> {
         let start_instant = Instant::now();
 
+        self.opts.hide_compiler_output();
+
         // Wait for the stdout job to start up.
         self.stdout.initialize(log).await?;
 
@@ -360,10 +595,15 @@ impl Ghci {
 
         // Get the initial list of targets.
         self.refresh_targets().await?;
+        // Add the modules which were added to previous sessions.
+        self.add_previously_added_modules(log).await?;
         // Get the initial list of eval commands.
         self.refresh_eval_commands().await?;
+        // Build the module graph.
+        self.refresh_module_graph(&[]).await?;
 
         self.finish_compilation(start_instant, log, events).await?;
+        self.spawn_standby();
 
         Ok(())
     }
@@ -374,11 +614,11 @@ impl Ghci {
     ) -> miette::Result<ReloadActions> {
         // Once we know which paths were modified and which paths were removed, we can combine
         // that with information about this `ghci` session to determine which modules need to be
-        // reloaded, which modules need to be added, and which modules were removed. In the case
-        // of removed modules, the entire `ghci` session must be restarted.
+        // reloaded, which modules need to be added, and which modules were removed.
         let mut needs_restart = Vec::new();
         let mut needs_reload = Vec::new();
         let mut needs_add = Vec::new();
+        let mut needs_unadd = Vec::new();
         for event in events {
             let path = event.as_path();
             let path = self.relative_path(path)?;
@@ -395,7 +635,7 @@ impl Ghci {
             );
 
             // Don't restart if we've explicitly ignored this path in a glob.
-            if (!restart_match.is_ignore()
+            if !restart_match.is_ignore()
                 // Restart on `.cabal` and `.ghci` files.
                 && (path
                     .extension()
@@ -406,21 +646,19 @@ impl Ghci {
                     .map(|name| name == ".ghci")
                     .unwrap_or(false)
                 // Restart on explicit restart globs.
-                || restart_match.is_whitelist()))
-                // Even if we've explicitly ignored this path in a glob, `ghci` can't cope with
-                // removed modules, so we need to restart when modules are removed or renamed.
-                //
-                // See: https://gitlab.haskell.org/ghc/ghc/-/issues/11596
-                //
-                // TODO: I should investigate if `:unadd` works for some classes of removed
-                // modules.
-                || (matches!(event, FileEvent::Remove(_))
-                    && path_is_haskell_source_file
-                    && self.targets.contains_source_path(&path))
+                || restart_match.is_whitelist())
             {
                 // Restart for this path.
                 tracing::debug!(%path, "Needs restart");
                 needs_restart.push(path);
+            } else if matches!(event, FileEvent::Remove(_))
+                && path_is_haskell_source_file
+                && self.targets.contains_source_path(&path)
+            {
+                // Even if we've explicitly ignored this path in a glob, `ghci` can't cope with
+                // targets whose files are missing, so we need to remove them from the target set.
+                tracing::debug!(%path, "Needs unadd");
+                needs_unadd.push(path);
             } else if reload_match.is_whitelist() {
                 // Extra extensions are always reloaded, never added.
                 tracing::debug!(%path, "Needs reload");
@@ -447,6 +685,8 @@ impl Ghci {
             needs_restart,
             needs_reload,
             needs_add,
+            needs_unadd,
+            force: false,
         })
     }
 
@@ -454,24 +694,41 @@ impl Ghci {
     ///
     /// This may fully restart the `ghci` process.
     ///
+    /// If `force` is set, the session will be reloaded even if none of the `events` require it.
+    ///
     /// NOTE: We interrupt reloads when applicable, so this function may be canceled and dropped at
     /// any `await` point!
     #[instrument(skip_all, level = "debug")]
     pub async fn reload(
         &mut self,
         events: BTreeSet<FileEvent>,
+        force: bool,
         kind_sender: oneshot::Sender<GhciReloadKind>,
     ) -> miette::Result<()> {
         let start_instant = Instant::now();
-        let actions = self.get_reload_actions(events).await?;
-        let _ = kind_sender.send(actions.kind());
+        if !events.is_empty() {
+            self.opts.events.send(StreamEvent::FilesChanged {
+                changes: events.iter().cloned().collect(),
+            });
+            if self.opts.test_session.is_some() {
+                self.untested_events.extend(events.iter().cloned());
+            }
+            if let Some(standby) = &mut self.standby {
+                standby.record(&events);
+            }
+        }
+        let mut actions = self.get_reload_actions(events).await?;
+        actions.force = force;
+        let kind = actions.kind();
+        self.opts.events.send(StreamEvent::ReloadKind { kind });
+        let _ = kind_sender.send(kind);
 
         if actions.needs_restart() {
             self.opts.clear();
-            tracing::info!(
+            self.opts.progress(format_args!(
                 "Restarting ghci:\n{}",
                 format_bulleted_list(&actions.needs_restart)
-            );
+            ));
             self.restart().await?;
             // Once we restart, everything is freshly loaded. We don't need to add or
             // reload any other modules.
@@ -482,31 +739,58 @@ impl Ghci {
 
         if actions.needs_add_or_reload() {
             self.opts.clear();
+            self.run_pending = false;
+            self.opts
+                .set_status(|status| status.state = GhciState::Reloading);
             self.run_hooks(LifecycleEvent::Reload(hooks::When::Before), &mut log)
                 .await?;
+            self.opts.hide_compiler_output();
         }
 
-        if !actions.needs_add.is_empty() {
-            tracing::info!(
-                "Adding modules to ghci:\n{}",
-                format_bulleted_list(&actions.needs_add)
-            );
-            for path in &actions.needs_add {
-                self.add_module(path, &mut log).await?;
+        if !actions.needs_unadd.is_empty() {
+            self.opts.progress(format_args!(
+                "Removing modules from ghci:\n{}",
+                format_bulleted_list(&actions.needs_unadd)
+            ));
+            match with_timeout(
+                self.opts.timeouts.compile_timeout,
+                self.unadd_modules(&actions.needs_unadd, &mut log),
+            )
+            .await
+            {
+                Some(result) => result?,
+                None => {
+                    self.recover_from_timeout(Phase::Compile, &mut log).await?;
+                    return Ok(());
+                }
+            }
+
+            if log.has_missing_source_file() {
+                // `ghci` still remembers the removed modules. Only a restart will make it forget.
+                //
+                // See: https://gitlab.haskell.org/ghc/ghc/-/issues/11596
+                self.opts.progress(format_args!(
+                    "Restarting ghci:\n{}",
+                    format_bulleted_list(&actions.needs_unadd)
+                ));
+                self.restart().await?;
+                return Ok(());
             }
         }
 
-        if !actions.needs_reload.is_empty() {
-            tracing::info!(
-                "Reloading ghci:\n{}",
-                format_bulleted_list(&actions.needs_reload)
-            );
-            self.stdin.reload(&mut self.stdout, &mut log).await?;
-            self.refresh_eval_commands_for_paths(&actions.needs_reload)
-                .await?;
+        if !self.add_and_reload(&actions, &mut log).await? {
+            return Ok(());
         }
 
         if actions.needs_add_or_reload() {
+            let changed = actions
+                .needs_add
+                .iter()
+                .chain(&actions.needs_reload)
+                .cloned()
+                .collect::<Vec<_>>();
+            self.refresh_module_graph(&changed).await?;
+            self.reloads += 1;
             self.finish_compilation(
                 start_instant,
                 &mut log,
@@ -520,32 +804,374 @@ impl Ghci {
         Ok(())
     }
 
+    /// Add and reload modules as needed for the given `actions`.
+    ///
+    /// Returns `false` if compilation timed out, in which case the session has already recovered
+    /// and the compilation shouldn't be finished.
+    async fn add_and_reload(
+        &mut self,
+        actions: &ReloadActions,
+        log: &mut CompilationLog,
+    ) -> miette::Result<bool> {
+        if !actions.needs_add.is_empty() {
+            self.opts.progress(format_args!(
+                "Adding modules to ghci:\n{}",
+                format_bulleted_list(&actions.needs_add)
+            ));
+            match with_timeout(
+                self.opts.timeouts.compile_timeout,
+                self.add_modules(&actions.needs_add, log),
+            )
+            .await
+            {
+                Some(result) => result?,
+                None => {
+                    self.recover_from_timeout(Phase::Compile, log).await?;
+                    return Ok(false);
+                }
+            }
+        }
+
+        if !actions.needs_reload.is_empty() {
+            self.opts.progress(format_args!(
+                "Reloading ghci:\n{}",
+                format_bulleted_list(&actions.needs_reload)
+            ));
+            if self.opts.module_graph_path.is_some() {
+                tracing::debug!(
+                    affected = ?self.module_graph.affected_by(&actions.needs_reload),
+                    tests = ?self.module_graph.test_modules_affected_by(&actions.needs_reload),
+                    "Modules affected by changes"
+                );
+            }
+        } else if actions.force {
+            self.opts.progress(format_args!("Reloading ghci"));
+        }
+
+        if !actions.needs_reload.is_empty() || actions.force {
+            match with_timeout(
+                self.opts.timeouts.compile_timeout,
+                self.stdin.reload(&mut self.stdout, log),
+            )
+            .await
+            {
+                Some(result) => result?,
+                None => {
+                    self.recover_from_timeout(Phase::Compile, log).await?;
+                    return Ok(false);
+                }
+            }
+            self.refresh_eval_commands_for_paths(&actions.needs_reload)
+                .await?;
+        }
+
+        Ok(true)
+    }
+
     /// Restart the `ghci` session.
     #[instrument(skip_all, level = "debug")]
     async fn restart(&mut self) -> miette::Result<()> {
         let mut log = CompilationLog::default();
 
+        self.opts
+            .set_status(|status| status.state = GhciState::Restarting);
+        self.opts.events.send(StreamEvent::Restart);
+
         self.run_hooks(LifecycleEvent::Restart(hooks::When::Before), &mut log)
             .await?;
+        let standby = self.standby.take();
         self.stop().await?;
-        let new = Self::new(self.shutdown.clone(), self.opts.clone()).await?;
-        let _ = std::mem::replace(self, new);
-        self.initialize(
-            &mut log,
-            [
-                LifecycleEvent::Startup(hooks::When::After),
-                LifecycleEvent::Restart(hooks::When::After),
-            ],
-        )
-        .await?;
+        let untested_events = std::mem::take(&mut self.untested_events);
 
+        // Switch to the standby session if it's ready to use; otherwise, start a new session.
+        let standby = match standby {
+            Some(standby) => standby.promote(&self.opts).await?,
+            None => None,
+        };
+        match standby {
+            Some((new, actions)) => {
+                tracing::info!("Switching to the standby ghci session");
+                let _ = std::mem::replace(self, new);
+                self.untested_events = untested_events;
+                self.finish_standby_restart(actions, &mut log).await?;
+            }
+            None => {
+                let new = Self::new(self.shutdown.clone(), self.opts.clone()).await?;
+                let _ = std::mem::replace(self, new);
+                self.untested_events = untested_events;
+                self.initialize(
+                    &mut log,
+                    [
+                        LifecycleEvent::Startup(hooks::When::After),
+                        LifecycleEvent::Restart(hooks::When::After),
+                    ],
+                )
+                .await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Finish restarting after switching to a standby session, by compiling the changes since it
+    /// started and reporting the results.
+    ///
+    /// A new standby session is then started in the background.
+    #[instrument(skip_all, level = "debug")]
+    async fn finish_standby_restart(
+        &mut self,
+        mut actions: ReloadActions,
+        log: &mut CompilationLog,
+    ) -> miette::Result<()> {
+        let start_instant = Instant::now();
+        // The standby session's output was hidden while it started.
+        self.opts.hide_compiler_output();
+        // Files may have changed while the standby session was loading them, so always reload.
+        actions.force = true;
+        if self.add_and_reload(&actions, log).await? {
+            let changed = actions
+                .needs_add
+                .iter()
+                .chain(&actions.needs_reload)
+                .cloned()
+                .collect::<Vec<_>>();
+            self.refresh_module_graph(&changed).await?;
+            self.finish_compilation(
+                start_instant,
+                log,
+                [LifecycleEvent::Restart(hooks::When::After)],
+            )
+            .await?;
+        }
+        self.spawn_standby();
         Ok(())
     }
 
+    /// Start loading a standby session in the background, if `opts.standby` is set.
+    fn spawn_standby(&mut self) {
+        if self.opts.standby {
+            self.standby = Some(Standby::spawn(self.shutdown.clone(), &self.opts));
+        }
+    }
+
+    /// Start up a standby session: wait for `ghci` to load the targets, set the prompt, and run
+    /// the startup hooks, without reporting any diagnostics.
+    #[instrument(skip_all, level = "debug")]
+    async fn warm_up(&mut self) -> miette::Result<()> {
+        let mut log = CompilationLog::default();
+        self.stdout.initialize(&mut log).await?;
+        self.stdin.initialize(&mut self.stdout, &mut log).await?;
+        self.refresh_targets().await?;
+        self.add_previously_added_modules(&mut log).await?;
+        self.refresh_eval_commands().await?;
+        self.refresh_module_graph(&[]).await?;
+        // Remember the warnings, to show after switching to this session if their modules aren't
+        // recompiled.
+        self.diagnostics.update(&mut log, &self.search_paths.cwd)?;
+        self.run_hooks(LifecycleEvent::Startup(hooks::When::After), &mut log)
+            .await?;
+        Ok(())
+    }
+
+    /// Check if the session is due for a planned restart, because it's using more memory than
+    /// `opts.max_memory`, has been reloaded `opts.restart_every` times, or didn't respond to being
+    /// interrupted.
+    ///
+    /// Memory usage is only checked once the session has reloaded; restarting a fresh session
+    /// wouldn't free anything.
+    ///
+    /// Returns the reason for the restart.
+    pub fn planned_restart_reason(&self) -> Option<String> {
+        if self.unresponsive {
+            return Some(format!(
+                "ghci didn't respond to an interrupt within {}",
+                humantime::format_duration(self.opts.timeouts.interrupt_grace_period)
+            ));
+        }
+
+        if let Some(restart_every) = self.opts.restart_every {
+            if self.reloads >= restart_every {
+                return Some(format!(
+                    "ghci has reloaded {} times (`--restart-every {restart_every}`)",
+                    self.reloads
+                ));
+            }
+        }
+
+        if let Some(max_memory) = self.opts.max_memory.filter(|_| self.reloads > 0) {
+            let rss = ByteSize(process_group_rss(self.process_group_id)?);
+            tracing::debug!(%rss, "Measured ghci memory usage");
+            if rss > max_memory {
+                return Some(format!(
+                    "ghci is using {rss} of memory, more than `--max-memory {max_memory}`"
+                ));
+            }
+        }
+
+        None
+    }
+
     /// Run the user provided test command.
+    ///
+    /// If any test commands are run, the log is republished afterwards so that test results show
+    /// up in the error log.
     #[instrument(skip_all, level = "debug")]
     async fn test(&mut self, log: &mut CompilationLog) -> miette::Result<()> {
+        if self
+            .opts
+            .hooks
+            .select(LifecycleEvent::Test)
+            .next()
+            .is_none()
+        {
+            return Ok(());
+        }
+
+        self.opts
+            .set_status(|status| status.state = GhciState::Testing);
+        let compiler_diagnostics = log.diagnostics.len();
         self.run_hooks(LifecycleEvent::Test, log).await?;
+
+        if let Some(sender) = &self.opts.main_session {
+            let results = CompilationLog {
+                diagnostics: log.diagnostics[compiler_diagnostics..].to_vec(),
+                tests: log.tests,
+                ..Default::default()
+            };
+            if let Err(err) = sender.try_send(GhciEvent::TestResults(results)) {
+                tracing::debug!("Failed to send test results to main session: {err}");
+            }
+        }
+
+        if let Some(tests) = log.tests {
+            if tests.failed > 0 {
+                tracing::error!(
+                    "{} of {} tests failed ({} passed, {} pending)",
+                    tests.failed,
+                    tests.total,
+                    tests.passed(),
+                    tests.pending
+                );
+            } else {
+                tracing::info!(
+                    "{} tests passed ({} pending)",
+                    tests.passed(),
+                    tests.pending
+                );
+            }
+        }
+        self.publish_log(log).await?;
+        Ok(())
+    }
+
+    /// Run the user provided test command on request, outside of a reload.
+    #[instrument(skip_all, level = "debug")]
+    async fn run_tests(&mut self) -> miette::Result<()> {
+        if self
+            .opts
+            .hooks
+            .select(LifecycleEvent::Test)
+            .next()
+            .is_none()
+        {
+            tracing::warn!("No test commands to run; use `--test-ghci` or `--test-shell`");
+            return Ok(());
+        }
+
+        if self.opts.test_session.is_some() {
+            self.send_to_test_session(GhciEvent::Test);
+            return Ok(());
+        }
+
+        let mut log = self.last_compilation.clone();
+        self.test(&mut log).await?;
+        self.opts
+            .set_status(|status| status.state = GhciState::Idle);
+        Ok(())
+    }
+
+    /// Report results from the test session.
+    ///
+    /// The test failures and summary are added to the last compilation's log and published, like
+    /// they would be if the tests ran in this session.
+    #[instrument(skip_all, level = "debug")]
+    async fn publish_test_results(&mut self, results: CompilationLog) -> miette::Result<()> {
+        for diagnostic in &results.diagnostics {
+            self.opts
+                .events
+                .send(StreamEvent::Diagnostic(diagnostic.clone()));
+        }
+        if let Some(tests) = results.tests {
+            self.opts.events.send(StreamEvent::TestSummary(tests));
+        }
+
+        let mut log = self.last_compilation.clone();
+        log.diagnostics.extend(results.diagnostics);
+        log.tests = results.tests;
+        self.publish_log(&mut log).await
+    }
+
+    /// Send an event to the test session, if `opts.test_session` is set.
+    ///
+    /// If the test session is busy starting up, reload events are kept in `untested_events` to
+    /// send after the next compilation instead.
+    fn send_to_test_session(&mut self, event: GhciEvent) {
+        let sender = match &self.opts.test_session {
+            Some(sender) => sender,
+            None => return,
+        };
+        match sender.try_send(event) {
+            Ok(()) => {}
+            Err(TrySendError::Full(GhciEvent::Reload { events, .. })) => {
+                tracing::debug!("Test session is busy; sending changes later");
+                self.untested_events.extend(events);
+            }
+            Err(err) => {
+                tracing::debug!("Failed to send event to test session: {err}");
+            }
+        }
+    }
+
+    /// Run a command typed by the user, showing its output.
+    #[instrument(skip(self), level = "debug")]
+    async fn run_user_command(&mut self, command: &GhciCommand) -> miette::Result<()> {
+        // The output is shown as it's read; we don't need to keep any diagnostics from it, or
+        // report them as if they came from compiling.
+        self.stdin
+            .run_command(
+                &mut self.stdout,
+                command,
+                ParseOutput::Nothing,
+                &mut CompilationLog::default(),
+            )
+            .await
+    }
+
+    /// Run the `--run` command until it finishes or is interrupted.
+    ///
+    /// Its output is shown as it's read.
+    #[instrument(skip_all, level = "debug")]
+    async fn run(&mut self) -> miette::Result<()> {
+        let command = match &self.opts.run {
+            Some(command) => command.clone(),
+            None => return Ok(()),
+        };
+        tracing::info!("Running `{command}`");
+        self.opts
+            .set_status(|status| status.state = GhciState::Running);
+        self.stdin
+            .run_command(
+                &mut self.stdout,
+                &command,
+                // The program's output is only shown; it may look like compiler messages.
+                ParseOutput::Nothing,
+                &mut CompilationLog::default(),
+            )
+            .await?;
+        tracing::info!("`{command}` finished");
+        self.run_pending = false;
+        self.opts
+            .set_status(|status| status.state = GhciState::Idle);
         Ok(())
     }
 
@@ -562,6 +1188,11 @@ impl Ghci {
         for (path, commands) in self.eval_commands.clone() {
             for command in commands {
                 tracing::info!("{path}:{command}");
+                self.opts.events.send(StreamEvent::Eval {
+                    path: path.relative().to_owned(),
+                    line: command.line(),
+                    command: command.display_command().to_owned(),
+                });
                 // If the `module` was already compiled, `ghci` may have loaded the interface file instead
                 // of the interpreted bytecode, giving us this error message when we attempt to
                 // load the top-level scope with `:module + *{module}`:
@@ -569,13 +1200,33 @@ impl Ghci {
                 //     module 'Mercury.Typescript.Golden' is not interpreted
                 //
                 // We use `:add *{module}` to force interpreting the module. We do this here instead of in
-                // `add_module` to save time if eval commands aren't used (or aren't needed for a
+                // `add_modules` to save time if eval commands aren't used (or aren't needed for a
                 // particular module).
-                self.interpret_module(&path, log).await?;
+                let phase = Phase::Eval {
+                    path: path.relative().to_owned(),
+                    line: command.line(),
+                };
+                let timeout = phase.timeout(&self.opts.timeouts);
                 let module = self.search_paths.path_to_module(&path)?;
-                self.stdin
-                    .eval(&mut self.stdout, &module, &command.command, log)
-                    .await?;
+                let eval = async {
+                    self.interpret_module(&path, log).await?;
+                    self.stdin
+                        .eval(&mut self.stdout, &module, &command.command, log)
+                        .await
+                };
+                match with_timeout(timeout, eval).await {
+                    Some(result) => result?,
+                    None => {
+                        if self.recover_from_timeout(phase, log).await? {
+                            // Take the module's top-level scope back out of the context, which
+                            // the interrupted eval command didn't get to.
+                            self.stdin
+                                .remove_from_context(&mut self.stdout, &module, log)
+                                .await?;
+                        }
+                        return Ok(());
+                    }
+                }
             }
         }
 
@@ -653,29 +1304,226 @@ impl Ghci {
         Ok(commands)
     }
 
-    /// `:add` a module to the `ghci` session by path.
+    /// Get the source paths of the modules currently loaded, from the `:show modules` output.
+    #[instrument(skip_all, level = "debug")]
+    async fn loaded_paths(&mut self) -> miette::Result<HashSet<NormalPath>> {
+        self.stdin
+            .show_modules(&mut self.stdout)
+            .await?
+            .into_iter()
+            .map(|module| NormalPath::new(module.path, &self.search_paths.cwd))
+            .collect()
+    }
+
+    /// Refresh the module graph from the `:show modules` output and write it to
+    /// `opts.module_graph_path`, if set.
     ///
-    /// Optionally returns a compilation result.
+    /// Imports are only parsed for newly loaded modules and the given `changed` paths; modules
+    /// which are no longer loaded are removed from the graph.
+    #[instrument(skip_all, level = "debug")]
+    async fn refresh_module_graph(&mut self, changed: &[NormalPath]) -> miette::Result<()> {
+        let graph_path = match &self.opts.module_graph_path {
+            Some(path) => path.clone(),
+            None => {
+                return Ok(());
+            }
+        };
+
+        let modules = self.stdin.show_modules(&mut self.stdout).await?;
+        let mut loaded = HashSet::new();
+        for module in modules {
+            let path = NormalPath::new(&module.path, &self.search_paths.cwd)?;
+            if self.module_graph.path(&module.name) != Some(&path) || changed.contains(&path) {
+                let imports = Self::parse_imports(&path).await?;
+                self.module_graph.insert(module.name.clone(), path, imports);
+            }
+            loaded.insert(module.name);
+        }
+
+        let unloaded = self
+            .module_graph
+            .iter()
+            .filter(|name| !loaded.contains(*name))
+            .map(ToOwned::to_owned)
+            .collect::<Vec<_>>();
+        for name in unloaded {
+            self.module_graph.remove(&name);
+        }
+        tracing::debug!(modules = self.module_graph.len(), "Refreshed module graph");
+
+        self.module_graph
+            .write(&graph_path, self.opts.module_graph_format)
+            .await
+    }
+
+    /// Read and parse the names of the modules imported by the given `path`.
+    ///
+    /// If the file can't be read (if it was removed since it was loaded, for example), no imports
+    /// are returned.
+    #[instrument(level = "trace")]
+    async fn parse_imports(path: &Utf8Path) -> miette::Result<Vec<String>> {
+        let contents = match tokio::fs::read_to_string(path).await {
+            Ok(contents) => contents,
+            Err(err) => {
+                tracing::debug!(%path, "Failed to read module for imports: {err}");
+                return Ok(Vec::new());
+            }
+        };
+        parse_imports(&contents).wrap_err_with(|| format!("Failed to parse imports from {path}"))
+    }
+
+    /// `:add` modules to the `ghci` session by path, all at once.
+    ///
+    /// Adding the modules in one command means `ghci` only analyzes dependencies and compiles
+    /// once, rather than once per module. Only the modules which load are recorded as targets and
+    /// added again after restarts.
     #[instrument(skip(self), level = "debug")]
-    async fn add_module(
+    async fn add_modules(
         &mut self,
-        path: &NormalPath,
+        paths: &[NormalPath],
         log: &mut CompilationLog,
     ) -> miette::Result<()> {
-        if self.targets.contains_source_path(path.absolute()) {
-            tracing::debug!(%path, "Skipping `:add`ing already-loaded path");
+        let mut to_add = Vec::with_capacity(paths.len());
+        for path in paths {
+            if self.targets.contains_source_path(path.absolute()) {
+                tracing::debug!(%path, "Skipping `:add`ing already-loaded path");
+            } else {
+                to_add.push(path);
+            }
+        }
+        if to_add.is_empty() {
             return Ok(());
         }
 
+        let names = to_add
+            .iter()
+            .map(|path| path.relative().to_string())
+            .collect::<Vec<_>>();
         self.stdin
-            .add_module(&mut self.stdout, path.relative(), log)
+            .add_modules(&mut self.stdout, &names, log)
             .await?;
 
-        self.targets
-            .insert_source_path(path.clone(), TargetKind::Path);
+        let to_add = self.keep_loaded(to_add, |path| path, log).await?;
 
-        self.refresh_eval_commands_for_paths(std::iter::once(path))
+        for path in &to_add {
+            self.targets
+                .insert_source_path((*path).clone(), TargetKind::Path);
+            self.opts
+                .added_modules
+                .insert(path, TargetKind::Path)
+                .await?;
+        }
+
+        self.refresh_eval_commands_for_paths(to_add).await?;
+
+        Ok(())
+    }
+
+    /// `:add` the modules which `ghciwatch` added to previous sessions, all at once.
+    ///
+    /// Modules which are already targets are skipped, and modules whose files were removed are
+    /// forgotten.
+    #[instrument(skip_all, level = "debug")]
+    async fn add_previously_added_modules(
+        &mut self,
+        log: &mut CompilationLog,
+    ) -> miette::Result<()> {
+        let mut modules = Vec::new();
+        let mut removed = Vec::new();
+        for (path, kind) in self.opts.added_modules.modules().await {
+            if self.targets.contains_source_path(&path) {
+                continue;
+            }
+            if path.absolute().exists() {
+                modules.push((path, kind));
+            } else {
+                removed.push(path);
+            }
+        }
+        self.opts.added_modules.remove(&removed).await?;
+        if modules.is_empty() {
+            return Ok(());
+        }
+
+        self.opts.progress(format_args!(
+            "Adding modules from previous sessions:\n{}",
+            format_bulleted_list(modules.iter().map(|(path, _)| path))
+        ));
+        let names = modules
+            .iter()
+            .map(|(path, kind)| match kind {
+                TargetKind::Path => Ok(path.relative().to_string()),
+                TargetKind::Module => self.search_paths.path_to_module(path),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+        self.stdin
+            .add_modules(&mut self.stdout, &names, log)
             .await?;
+        // Modules which fail to load are still remembered, to add again after the next restart.
+        let modules = self.keep_loaded(modules, |(path, _)| path, log).await?;
+        for (path, kind) in modules {
+            self.targets.insert_source_path(path, kind);
+        }
+
+        Ok(())
+    }
+
+    /// After `:add`ing modules, keep only the ones which loaded.
+    ///
+    /// If compilation failed, some of the modules may not have loaded. They shouldn't be recorded
+    /// as targets, so that they're `:add`ed again when they change.
+    async fn keep_loaded<T>(
+        &mut self,
+        modules: Vec<T>,
+        path: impl Fn(&T) -> &NormalPath,
+        log: &CompilationLog,
+    ) -> miette::Result<Vec<T>> {
+        if log.result() != Some(CompilationResult::Err) {
+            return Ok(modules);
+        }
+        let loaded = self.loaded_paths().await?;
+        Ok(modules
+            .into_iter()
+            .filter(|module| {
+                let path = path(module);
+                let is_loaded = loaded.contains(path);
+                if !is_loaded {
+                    tracing::debug!(%path, "Module failed to load; not adding it to targets");
+                }
+                is_loaded
+            })
+            .collect())
+    }
+
+    /// `:unadd` modules from the `ghci` session by path.
+    ///
+    /// `ghci` reloads after removing the modules from the target set.
+    #[instrument(skip(self, log), level = "debug")]
+    async fn unadd_modules(
+        &mut self,
+        paths: &[NormalPath],
+        log: &mut CompilationLog,
+    ) -> miette::Result<()> {
+        // Modules have to be removed by the same name they were added with.
+        let names = paths
+            .iter()
+            .map(|path| {
+                self.targets
+                    .module_import_name(&self.search_paths, path)
+                    .map(|module| module.name)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        self.stdin
+            .unadd_modules(&mut self.stdout, &names, log)
+            .await?;
+
+        for path in paths {
+            self.targets.remove_source_path(path);
+            self.eval_commands.remove(path);
+            self.diagnostics.remove(path);
+        }
+        self.opts.added_modules.remove(paths).await?;
 
         Ok(())
     }
@@ -713,6 +1561,8 @@ impl Ghci {
         // Tell the `GhciProcess` to shut down `ghci` without requesting a shutdown for
         // `ghciwatch`.
         let _ = self.restart_sender.try_send(());
+        // Dropping the standby session stops it.
+        self.standby = None;
 
         Ok(())
     }
@@ -732,6 +1582,7 @@ impl Ghci {
             .prompt(
                 crate::incremental_reader::FindAt::Anywhere,
                 // Ignore compilation messages.
+                ParseOutput::Nothing,
                 &mut Default::default(),
             )
             .await?;
@@ -739,24 +1590,48 @@ impl Ghci {
         Ok(())
     }
 
-    #[instrument(skip_all, level = "trace")]
-    async fn before_startup_shell(command: &ClonableCommand) -> miette::Result<()> {
-        let program = &command.program;
-        let mut command = command.as_tokio();
-        command.kill_on_drop(true);
-        let command_formatted = command.display();
-        tracing::info!("$ {command_formatted}");
-        let status = command
-            .status()
-            .await
-            .into_diagnostic()
-            .wrap_err_with(|| format!("Failed to execute `{command_formatted}`"))?;
-        if status.success() {
-            tracing::debug!("{program:?} exited successfully: {status}");
-        } else {
-            tracing::error!("{program:?} failed: {status}");
+    /// Recover from a `phase` of work which didn't finish within its timeout.
+    ///
+    /// The timeout is reported as an error diagnostic in the `log`, which is published, and then
+    /// `ghci` is interrupted. If `ghci` doesn't return to a prompt within the grace period, it's
+    /// marked as unresponsive, so that it's restarted once the session is idle.
+    ///
+    /// After compilation times out, the session goes back to idling, without running any eval
+    /// commands, tests, or hooks.
+    ///
+    /// Returns whether `ghci` was interrupted and is ready for more commands.
+    #[instrument(skip(self, log), level = "debug")]
+    async fn recover_from_timeout(
+        &mut self,
+        phase: Phase,
+        log: &mut CompilationLog,
+    ) -> miette::Result<bool> {
+        let timeout = phase.timeout(&self.opts.timeouts).unwrap_or_default();
+        let diagnostic = phase.diagnostic(timeout);
+        self.opts.mute.set(false);
+        tracing::error!("{}; interrupting ghci", diagnostic.message);
+        log.diagnostics.push(diagnostic);
+        self.publish_log(log).await?;
+
+        let grace_period = self.opts.timeouts.interrupt_grace_period;
+        let interrupted = match tokio::time::timeout(grace_period, self.send_sigint()).await {
+            Ok(result) => {
+                result?;
+                true
+            }
+            Err(_) => {
+                tracing::debug!("ghci didn't respond to an interrupt; restarting it once idle");
+                self.unresponsive = true;
+                false
+            }
+        };
+
+        if let Phase::Compile = phase {
+            self.last_compilation = log.clone();
+            self.opts
+                .set_status(|status| status.state = GhciState::Idle);
         }
-        Ok(())
+        Ok(interrupted)
     }
 
     // Get rid of any handles for background commands that have finished.
@@ -776,8 +1651,61 @@ impl Ghci {
         log: &mut CompilationLog,
         events: [LifecycleEvent; N],
     ) -> miette::Result<()> {
+        let kept = self.diagnostics.update(log, &self.search_paths.cwd)?;
+        let kept = kept
+            .iter()
+            .filter(|diagnostic| !self.opts.hide_diagnostics.is_hidden(diagnostic))
+            .collect::<Vec<_>>();
+        if !kept.is_empty() && !self.opts.display.hides_ghci_output() && !self.opts.quiet {
+            tracing::warn!(
+                "{} from modules which weren't recompiled:",
+                if kept.len() == 1 {
+                    "1 warning".to_owned()
+                } else {
+                    format!("{} warnings", kept.len())
+                }
+            );
+            // Logs are written to files too, so the warnings are shown alongside `ghci`'s output
+            // instead, with its colors.
+            self.opts
+                .stdout_writer
+                .clone()
+                .write_all(
+                    kept.iter()
+                        .map(|diagnostic| diagnostic.colored())
+                        .join("")
+                        .as_bytes(),
+                )
+                .await
+                .into_diagnostic()?;
+        }
+        self.opts.hide_diagnostics.apply(log);
+        if log.hidden > 0 {
+            self.opts.progress(format_args!(
+                "Hid {} matching `--hide-diagnostics`",
+                if log.hidden == 1 {
+                    "1 diagnostic".to_owned()
+                } else {
+                    format!("{} diagnostics", log.hidden)
+                }
+            ));
+        }
+
+        // Show the rendered diagnostics, and then eval, test, and hook output.
+        self.opts.mute.set(false);
+        if let Some(rendered) = self.opts.display.render(log, &self.search_paths.cwd) {
+            self.opts
+                .stdout_writer
+                .clone()
+                .write_all(rendered.as_bytes())
+                .await
+                .into_diagnostic()?;
+        }
+
         // Allow hooks to consume the error log by updating it before running the hooks.
-        self.write_error_log(log).await?;
+        self.publish_log(log).await?;
+        self.last_compilation = log.clone();
+        self.report_timings(log).await?;
 
         for event in events {
             self.run_hooks(event, log).await?;
@@ -785,25 +1713,49 @@ impl Ghci {
 
         let event = events[N - 1];
 
+        self.opts.events.send(StreamEvent::compilation_finished(
+            event,
+            log.result(),
+            compilation_start.elapsed(),
+        ));
+
         if let Some(CompilationResult::Err) = log.result() {
-            tracing::error!(
-                "{} failed in {:.2?}",
-                event.event_noun().first_char_to_ascii_uppercase(),
-                compilation_start.elapsed()
-            );
+            if self.opts.quiet {
+                tracing::debug!("{} failed", event.event_noun());
+            } else {
+                tracing::error!(
+                    "{} failed in {:.2?}",
+                    event.event_noun().first_char_to_ascii_uppercase(),
+                    compilation_start.elapsed()
+                );
+            }
         } else {
-            tracing::info!(
+            self.opts.progress(format_args!(
                 "{} Finished {} in {:.2?}",
                 "All good!".if_supports_color(Stdout, |text| text.green()),
                 event.event_noun(),
                 compilation_start.elapsed()
-            );
+            ));
             // Run the eval commands, if any.
             self.eval(log).await?;
             // Run the user-provided test command, if any.
-            self.test(log).await?;
+            if self.opts.test_session.is_some() {
+                let events = std::mem::take(&mut self.untested_events);
+                self.send_to_test_session(GhciEvent::Reload {
+                    events,
+                    force: true,
+                });
+            } else {
+                self.test(log).await?;
+            }
         }
 
+        // Run the `--run` command once we're idle.
+        self.run_pending = self.opts.run.is_some() && log.result() != Some(CompilationResult::Err);
+
+        self.opts
+            .set_status(|status| status.state = GhciState::Idle);
+
         Ok(())
     }
 
@@ -813,27 +1765,84 @@ impl Ghci {
         event: LifecycleEvent,
         log: &mut CompilationLog,
     ) -> miette::Result<()> {
+        let mut timed_out = false;
         for hook in self.opts.hooks.select(event) {
+            if self.unresponsive && matches!(hook.command, hooks::Command::Ghci(_)) {
+                // `ghci` won't print a prompt until it's restarted.
+                tracing::debug!(command = %hook.command, "ghci isn't responding; skipping {hook} command");
+                continue;
+            }
             tracing::info!(command = %hook.command, "Running {hook} command");
-            match &hook.command {
+            self.opts.events.send(StreamEvent::hook_started(hook));
+            let status = match &hook.command {
                 hooks::Command::Ghci(command) => {
                     let start_time = Instant::now();
-                    self.stdin
-                        .run_command(&mut self.stdout, command, log)
-                        .await?;
+                    let (timeout, parse) = match &hook.event {
+                        LifecycleEvent::Test => {
+                            (self.opts.timeouts.test_timeout, ParseOutput::Tests)
+                        }
+                        _ => (None, ParseOutput::Compiler),
+                    };
+                    match with_timeout(
+                        timeout,
+                        self.stdin
+                            .run_command(&mut self.stdout, command, parse, log),
+                    )
+                    .await
+                    {
+                        Some(result) => result?,
+                        None => {
+                            self.opts
+                                .events
+                                .send(StreamEvent::hook_finished(hook, None));
+                            timed_out = true;
+                            break;
+                        }
+                    }
                     if let LifecycleEvent::Test = &hook.event {
                         tracing::info!("Finished running tests in {:.2?}", start_time.elapsed());
                     }
+                    None
                 }
-                hooks::Command::Shell(command) => {
-                    command.run_on(&mut self.command_handles).await?;
-                }
-            }
+                hooks::Command::Shell(command) => command.run_on(&mut self.command_handles).await?,
+            };
+            self.opts
+                .events
+                .send(StreamEvent::hook_finished(hook, status));
+        }
+
+        if timed_out {
+            self.recover_from_timeout(Phase::Test, log).await?;
         }
 
         Ok(())
     }
 
+    /// Write the error log and publish the log and status to other tasks.
+    ///
+    /// Diagnostics matching `--hide-diagnostics` are removed first, including test failures and
+    /// timeouts added to the log after compilation.
+    async fn publish_log(&mut self, log: &mut CompilationLog) -> miette::Result<()> {
+        self.opts.hide_diagnostics.apply(log);
+        self.write_error_log(log).await?;
+        self.opts.set_status(|status| status.set_compilation(log));
+        self.opts.compilation_log.send_replace(log.clone());
+        Ok(())
+    }
+
+    /// Log the slowest modules from a compilation and write the timings file.
+    #[instrument(skip_all, level = "trace")]
+    async fn report_timings(&mut self, log: &CompilationLog) -> miette::Result<()> {
+        // With only one module, there's nothing to compare.
+        if self.opts.slowest_modules > 0 && log.timings.len() > 1 {
+            tracing::info!(
+                "Slowest modules:\n{}",
+                format_bulleted_list(slowest(&log.timings, self.opts.slowest_modules))
+            );
+        }
+        self.timings_file.write(&log.timings).await
+    }
+
     #[instrument(skip(self), level = "trace")]
     async fn write_error_log(&mut self, log: &CompilationLog) -> miette::Result<()> {
         self.error_log.write(log).await
@@ -851,12 +1860,19 @@ struct ReloadActions {
     needs_reload: Vec<NormalPath>,
     /// Paths to modules which need an `:add`.
     needs_add: Vec<NormalPath>,
+    /// Paths to removed modules which need an `:unadd`.
+    needs_unadd: Vec<NormalPath>,
+    /// Whether to `:reload` even if no paths need it.
+    force: bool,
 }
 
 impl ReloadActions {
     /// Do any modules need to be added or reloaded?
     fn needs_add_or_reload(&self) -> bool {
-        !self.needs_add.is_empty() || !self.needs_reload.is_empty()
+        self.force
+            || !self.needs_add.is_empty()
+            || !self.needs_reload.is_empty()
+            || !self.needs_unadd.is_empty()
     }
 
     /// Is a session restart needed?
@@ -877,7 +1893,8 @@ impl ReloadActions {
 }
 
 /// How a [`Ghci`] session responds to a reload event.
-#[derive(Debug)]
+#[derive(Debug, Clone, Copy, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum GhciReloadKind {
     /// Noop. No actions needed.
     None,
@@ -0,0 +1,370 @@
+//! The module import graph for a `ghci` session.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::fmt::Write;
+
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use miette::Context;
+use miette::IntoDiagnostic;
+use serde::Serialize;
+use tracing::instrument;
+
+use crate::normal_path::NormalPath;
+
+use super::error_log::write_atomic;
+
+/// The format to write the module graph in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum ModuleGraphFormat {
+    /// A Graphviz DOT graph.
+    #[default]
+    Dot,
+    /// A JSON object with a list of modules and their imports.
+    Json,
+}
+
+/// The import graph of the modules loaded in a `ghci` session.
+///
+/// Modules are the ones listed in `:show modules`, and edges come from parsing the `import`
+/// declarations in each module's source file. Imports of modules which aren't loaded (like
+/// modules from other packages) are kept but ignored when querying or exporting the graph.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ModuleGraph {
+    /// Modules by name.
+    modules: BTreeMap<String, ModuleNode>,
+    /// Module names by source path.
+    names: HashMap<NormalPath, String>,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+struct ModuleNode {
+    path: NormalPath,
+    imports: BTreeSet<String>,
+}
+
+impl ModuleGraph {
+    /// Get the number of modules in the graph.
+    pub fn len(&self) -> usize {
+        self.modules.len()
+    }
+
+    /// Add a module to the graph, replacing any previous module with the same name.
+    pub fn insert(
+        &mut self,
+        name: String,
+        path: NormalPath,
+        imports: impl IntoIterator<Item = String>,
+    ) {
+        if let Some(old) = self.modules.remove(&name) {
+            self.names.remove(&old.path);
+        }
+        self.names.insert(path.clone(), name.clone());
+        self.modules.insert(
+            name,
+            ModuleNode {
+                path,
+                imports: imports.into_iter().collect(),
+            },
+        );
+    }
+
+    /// Remove a module from the graph by name.
+    ///
+    /// Returns whether the module was present.
+    pub fn remove(&mut self, name: &str) -> bool {
+        match self.modules.remove(name) {
+            Some(node) => {
+                self.names.remove(&node.path);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Get the source path of the module with the given name.
+    pub fn path(&self, name: &str) -> Option<&NormalPath> {
+        self.modules.get(name).map(|node| &node.path)
+    }
+
+    /// Get the name of the module with the given source path.
+    pub fn module_name(&self, path: &Utf8Path) -> Option<&str> {
+        self.names.get(path).map(String::as_str)
+    }
+
+    /// Iterate over the module names in the graph.
+    pub fn iter(&self) -> impl Iterator<Item = &str> {
+        self.modules.keys().map(String::as_str)
+    }
+
+    /// Iterate over the loaded modules imported by the module with the given name.
+    pub fn imports<'a>(&'a self, name: &str) -> impl Iterator<Item = &'a str> {
+        self.modules
+            .get(name)
+            .into_iter()
+            .flat_map(|node| node.imports.iter())
+            .filter(|import| self.modules.contains_key(*import))
+            .map(String::as_str)
+    }
+
+    /// Get the modules affected by changes to the given source paths: the modules themselves and
+    /// every module which transitively imports them.
+    pub fn affected_by<'a>(
+        &self,
+        paths: impl IntoIterator<Item = &'a NormalPath>,
+    ) -> BTreeSet<&str> {
+        let mut importers: HashMap<&str, Vec<&str>> = HashMap::new();
+        for (name, node) in &self.modules {
+            for import in &node.imports {
+                importers.entry(import).or_default().push(name);
+            }
+        }
+
+        let mut affected = BTreeSet::new();
+        let mut queue = paths
+            .into_iter()
+            .filter_map(|path| self.module_name(path.absolute()))
+            .collect::<Vec<_>>();
+        while let Some(name) = queue.pop() {
+            if affected.insert(name) {
+                if let Some(importers) = importers.get(name) {
+                    queue.extend(importers);
+                }
+            }
+        }
+        affected
+    }
+
+    /// Get the test modules which transitively import (or are) the given source paths.
+    ///
+    /// See [`is_test_module`] for which modules count as tests.
+    pub fn test_modules_affected_by<'a>(
+        &self,
+        paths: impl IntoIterator<Item = &'a NormalPath>,
+    ) -> BTreeSet<&str> {
+        self.affected_by(paths)
+            .into_iter()
+            .filter(|name| {
+                self.path(name)
+                    .map(|path| is_test_module(name, path.relative()))
+                    .unwrap_or(false)
+            })
+            .collect()
+    }
+
+    /// Render the graph in the given format.
+    pub fn render(&self, format: ModuleGraphFormat) -> miette::Result<String> {
+        match format {
+            ModuleGraphFormat::Dot => Ok(self.to_dot()),
+            ModuleGraphFormat::Json => {
+                let mut contents =
+                    serde_json::to_string_pretty(&self.to_json()).into_diagnostic()?;
+                contents.push('\n');
+                Ok(contents)
+            }
+        }
+    }
+
+    /// Write the graph to the given path.
+    #[instrument(skip(self), name = "module_graph_write", level = "debug")]
+    pub async fn write(&self, path: &Utf8Path, format: ModuleGraphFormat) -> miette::Result<()> {
+        let contents = self.render(format)?;
+        write_atomic(path, contents.as_bytes())
+            .await
+            .wrap_err_with(|| format!("Failed to write module graph to {path}"))
+    }
+
+    fn to_dot(&self) -> String {
+        let mut dot = String::from("digraph modules {\n");
+        for (name, node) in &self.modules {
+            let _ = writeln!(
+                dot,
+                "    {} [tooltip={}];",
+                dot_string(name),
+                dot_string(node.path.relative().as_str())
+            );
+        }
+        for name in self.modules.keys() {
+            for import in self.imports(name) {
+                let _ = writeln!(dot, "    {} -> {};", dot_string(name), dot_string(import));
+            }
+        }
+        dot.push_str("}\n");
+        dot
+    }
+
+    fn to_json(&self) -> JsonModuleGraph<'_> {
+        JsonModuleGraph {
+            modules: self
+                .modules
+                .iter()
+                .map(|(name, node)| JsonModule {
+                    name,
+                    path: node.path.relative().to_owned(),
+                    imports: self.imports(name).collect(),
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Serialize)]
+struct JsonModuleGraph<'a> {
+    modules: Vec<JsonModule<'a>>,
+}
+
+#[derive(Serialize)]
+struct JsonModule<'a> {
+    name: &'a str,
+    path: Utf8PathBuf,
+    imports: Vec<&'a str>,
+}
+
+/// Quote a string for use as a DOT identifier.
+fn dot_string(s: &str) -> String {
+    format!("\"{}\"", s.replace('\\', "\\\\").replace('"', "\\\""))
+}
+
+/// Determine if a module is a test module.
+///
+/// Test modules are modules in a `test` or `tests` directory, or whose names end in `Spec`,
+/// `Test`, or `Tests`.
+pub fn is_test_module(name: &str, path: &Utf8Path) -> bool {
+    let base_name = name.rsplit('.').next().unwrap_or(name);
+    ["Spec", "Test", "Tests"]
+        .iter()
+        .any(|suffix| base_name.ends_with(suffix))
+        || path
+            .parent()
+            .into_iter()
+            .flat_map(|parent| parent.components())
+            .any(|component| matches!(component.as_str(), "test" | "tests"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    fn path(path: &str) -> NormalPath {
+        NormalPath::new(path, "/project").unwrap()
+    }
+
+    fn graph() -> ModuleGraph {
+        let mut graph = ModuleGraph::default();
+        graph.insert(
+            "MyLib".into(),
+            path("src/MyLib.hs"),
+            ["Data.Text".into(), "MyLib.Types".into()],
+        );
+        graph.insert("MyLib.Types".into(), path("src/MyLib/Types.hs"), []);
+        graph.insert("MyApp".into(), path("app/MyApp.hs"), ["MyLib".into()]);
+        graph.insert(
+            "MyLibSpec".into(),
+            path("test/MyLibSpec.hs"),
+            ["MyLib".into()],
+        );
+        graph.insert(
+            "TypesTest".into(),
+            path("test/TypesTest.hs"),
+            ["MyLib.Types".into()],
+        );
+        graph
+    }
+
+    #[test]
+    fn test_affected_by() {
+        let graph = graph();
+        assert_eq!(
+            graph.affected_by([&path("src/MyLib.hs")]),
+            ["MyApp", "MyLib", "MyLibSpec"].into()
+        );
+        assert_eq!(
+            graph.affected_by([&path("src/MyLib/Types.hs")]),
+            ["MyApp", "MyLib", "MyLib.Types", "MyLibSpec", "TypesTest"].into()
+        );
+        assert_eq!(graph.affected_by([&path("src/Unknown.hs")]), [].into());
+    }
+
+    #[test]
+    fn test_test_modules_affected_by() {
+        let graph = graph();
+        assert_eq!(
+            graph.test_modules_affected_by([&path("src/MyLib/Types.hs")]),
+            ["MyLibSpec", "TypesTest"].into()
+        );
+        assert_eq!(
+            graph.test_modules_affected_by([&path("app/MyApp.hs")]),
+            [].into()
+        );
+    }
+
+    #[test]
+    fn test_insert_and_remove() {
+        let mut graph = graph();
+        graph.insert("MyLib".into(), path("src/MyLib2.hs"), []);
+        assert_eq!(graph.module_name(path("src/MyLib.hs").absolute()), None);
+        assert_eq!(
+            graph.module_name(path("src/MyLib2.hs").absolute()),
+            Some("MyLib")
+        );
+        assert_eq!(graph.imports("MyLib").count(), 0);
+
+        assert!(graph.remove("MyLib"));
+        assert!(!graph.remove("MyLib"));
+        assert_eq!(graph.module_name(path("src/MyLib2.hs").absolute()), None);
+        assert_eq!(graph.len(), 4);
+    }
+
+    #[test]
+    fn test_render_dot() {
+        let mut graph = ModuleGraph::default();
+        graph.insert("A".into(), path("src/A.hs"), ["B".into(), "Prelude".into()]);
+        graph.insert("B".into(), path("src/B.hs"), []);
+        assert_eq!(
+            graph.render(ModuleGraphFormat::Dot).unwrap(),
+            indoc!(
+                r#"
+                digraph modules {
+                    "A" [tooltip="src/A.hs"];
+                    "B" [tooltip="src/B.hs"];
+                    "A" -> "B";
+                }
+                "#
+            )
+        );
+    }
+
+    #[test]
+    fn test_render_json() {
+        let mut graph = ModuleGraph::default();
+        graph.insert("A".into(), path("src/A.hs"), ["B".into(), "Prelude".into()]);
+        graph.insert("B".into(), path("src/B.hs"), []);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(
+                &graph.render(ModuleGraphFormat::Json).unwrap()
+            )
+            .unwrap(),
+            serde_json::json!({
+                "modules": [
+                    {"name": "A", "path": "src/A.hs", "imports": ["B"]},
+                    {"name": "B", "path": "src/B.hs", "imports": []},
+                ]
+            })
+        );
+    }
+
+    #[test]
+    fn test_is_test_module() {
+        assert!(is_test_module("MyLibSpec", "src/MyLibSpec.hs".into()));
+        assert!(is_test_module("My.Lib.Tests", "src/My/Lib/Tests.hs".into()));
+        assert!(is_test_module("Main", "test/Main.hs".into()));
+        assert!(is_test_module("Main", "pkg/tests/Main.hs".into()));
+        assert!(!is_test_module("MyLib", "src/MyLib.hs".into()));
+        assert!(!is_test_module("Testing", "src/Testing.hs".into()));
+    }
+}
@@ -0,0 +1,163 @@
+//! Stripping ANSI escape sequences while keeping track of where the text came from.
+
+use std::ops::Range;
+
+/// Text with ANSI escape sequences (and other control characters, except newlines) removed.
+///
+/// Unlike [`strip_ansi_escapes::strip_str`], this remembers the offset in the original text of
+/// each byte in the stripped text, so that text parsed from the stripped text can be mapped back
+/// to the original, colored text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StrippedText<'a> {
+    original: &'a str,
+    text: String,
+    /// For each byte in `text`, the offset of that byte in `original`.
+    offsets: Vec<usize>,
+}
+
+impl<'a> StrippedText<'a> {
+    /// Strip ANSI escape sequences from the given text.
+    pub fn new(original: &'a str) -> Self {
+        let mut text = String::with_capacity(original.len());
+        let mut offsets = Vec::with_capacity(original.len());
+        let mut chars = original.char_indices().peekable();
+
+        while let Some((offset, c)) = chars.next() {
+            match c {
+                '\x1b' => match chars.next_if(|(_, c)| !c.is_control()).map(|(_, c)| c) {
+                    // Control Sequence Introducer: parameters and intermediates, then a final
+                    // byte.
+                    Some('[') => {
+                        while chars
+                            .next_if(|(_, c)| ('\x20'..='\x3f').contains(c))
+                            .is_some()
+                        {}
+                        chars.next_if(|(_, c)| ('\x40'..='\x7e').contains(c));
+                    }
+                    // Strings terminated by BEL or ESC `\`, like OSC 8 hyperlinks.
+                    Some(']' | 'P' | 'X' | '^' | '_') => {
+                        while let Some((_, c)) = chars.next() {
+                            if c == '\x07' {
+                                break;
+                            }
+                            if c == '\x1b' {
+                                chars.next_if(|(_, c)| *c == '\\');
+                                break;
+                            }
+                        }
+                    }
+                    // Other escape sequences have intermediates and then a final byte.
+                    Some(c) if ('\x20'..='\x2f').contains(&c) => {
+                        while chars
+                            .next_if(|(_, c)| ('\x20'..='\x2f').contains(c))
+                            .is_some()
+                        {}
+                        chars.next_if(|(_, c)| ('\x30'..='\x7e').contains(c));
+                    }
+                    _ => {}
+                },
+                '\n' => {
+                    text.push(c);
+                    offsets.push(offset);
+                }
+                c if c.is_control() => {}
+                c => {
+                    text.push(c);
+                    offsets.extend(offset..offset + c.len_utf8());
+                }
+            }
+        }
+
+        Self {
+            original,
+            text,
+            offsets,
+        }
+    }
+
+    /// Get the stripped text.
+    pub fn as_str(&self) -> &str {
+        &self.text
+    }
+
+    /// Get the original text for a range of the stripped text.
+    ///
+    /// Escape sequences between the previous character and the start of the range are included,
+    /// because they usually set the colors for the range. A reset sequence is added to the end
+    /// (before the trailing newline, if any) so that colors don't leak out of the range.
+    ///
+    /// Returns `None` if the original text for the range doesn't contain any escape sequences.
+    pub fn original(&self, range: Range<usize>) -> Option<String> {
+        if range.is_empty() {
+            return None;
+        }
+        let start = match range.start {
+            0 => 0,
+            start => self.offsets[start - 1] + 1,
+        };
+        let end = self.offsets[range.end - 1] + 1;
+        let original = &self.original[start..end];
+        if !original.contains('\x1b') {
+            return None;
+        }
+        Some(match original.strip_suffix('\n') {
+            Some(original) => format!("{original}\x1b[0m\n"),
+            None => format!("{original}\x1b[0m"),
+        })
+    }
+
+    /// Get the range in the stripped text of a substring of it.
+    ///
+    /// Panics if `substring` doesn't point into the stripped text.
+    pub fn range_of(&self, substring: &str) -> Range<usize> {
+        let start = substring.as_ptr() as usize - self.text.as_ptr() as usize;
+        assert!(start + substring.len() <= self.text.len());
+        start..start + substring.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_stripped_text() {
+        let original =
+            "\x1b[1msrc/MyLib.hs:4:11: \x1b[0m\x1b[1;31merror:\x1b[0m\x1b[0m\r\n    Oh no!\n";
+        let stripped = StrippedText::new(original);
+        assert_eq!(stripped.as_str(), "src/MyLib.hs:4:11: error:\n    Oh no!\n");
+        assert_eq!(
+            stripped.as_str(),
+            strip_ansi_escapes::strip_str(original),
+            "Stripped text is the same as `strip_ansi_escapes`"
+        );
+
+        assert_eq!(
+            stripped.original(0..stripped.as_str().len()).unwrap(),
+            format!("{}\x1b[0m\n", original.strip_suffix('\n').unwrap())
+        );
+        assert_eq!(
+            stripped.original(19..25).unwrap(),
+            "\x1b[0m\x1b[1;31merror:\x1b[0m"
+        );
+        // No escapes.
+        assert_eq!(stripped.original(26..37), None);
+        assert_eq!(stripped.original(0..0), None);
+    }
+
+    #[test]
+    fn test_stripped_text_escapes() {
+        // OSC 8 hyperlinks, with both terminators.
+        let original = "\x1b]8;;https://example.com\x1b\\link\x1b]8;;\x07 text ü\x1b(B\n";
+        let stripped = StrippedText::new(original);
+        assert_eq!(stripped.as_str(), "link text ü\n");
+        assert_eq!(stripped.as_str(), strip_ansi_escapes::strip_str(original));
+        assert_eq!(stripped.original(10..12), None);
+        assert_eq!(stripped.original(5..12), None);
+
+        let text = stripped.as_str();
+        assert_eq!(stripped.range_of(&text[5..9]), 5..9);
+    }
+}
@@ -37,6 +37,18 @@ pub struct EvalCommand {
     byte_span: Range<usize>,
 }
 
+impl EvalCommand {
+    /// The `command` in a user-friendly format.
+    pub fn display_command(&self) -> &str {
+        &self.display_command
+    }
+
+    /// The 1-based line number this command is from.
+    pub fn line(&self) -> usize {
+        self.line
+    }
+}
+
 impl Display for EvalCommand {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(f, "{}:{}: {}", self.line, self.column, self.display_command)
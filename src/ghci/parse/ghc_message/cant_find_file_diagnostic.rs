@@ -11,6 +11,9 @@ use crate::ghci::parse::ghc_message::GhcMessage;
 
 use super::GhcDiagnostic;
 
+/// The message of diagnostics parsed by [`cant_find_file_diagnostic`].
+pub const CANT_FIND_FILE_MESSAGE: &str = "can't find file";
+
 /// Parse a "can't find file" message like this:
 ///
 /// ```plain
@@ -28,7 +31,10 @@ pub fn cant_find_file_diagnostic(input: &mut &str) -> PResult<GhcMessage> {
         severity,
         path: Some(Utf8PathBuf::from(path)),
         span: Default::default(),
-        message: "can't find file".to_owned(),
+        code: None,
+        flag: None,
+        message: CANT_FIND_FILE_MESSAGE.to_owned(),
+        colored: None,
     }))
 }
 
@@ -50,7 +56,10 @@ mod tests {
                 severity: Severity::Error,
                 path: Some("Why.hs".into()),
                 span: Default::default(),
-                message: "can't find file".to_owned()
+                code: None,
+                flag: None,
+                message: "can't find file".to_owned(),
+                colored: None,
             })
         );
 
@@ -1,3 +1,4 @@
+use serde::Serialize;
 use winnow::ascii::digit1;
 use winnow::ascii::line_ending;
 use winnow::combinator::alt;
@@ -20,7 +21,7 @@ use super::GhcMessage;
 /// ```text
 /// Failed, 58 modules loaded.
 /// ```
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
 pub struct CompilationSummary {
     /// The compilation result; whether compilation succeeded or failed.
     pub result: CompilationResult,
@@ -0,0 +1,126 @@
+use winnow::ascii::digit1;
+use winnow::ascii::space0;
+use winnow::combinator::delimited;
+use winnow::combinator::eof;
+use winnow::combinator::preceded;
+use winnow::token::take_till;
+use winnow::token::take_while;
+use winnow::PResult;
+use winnow::Parser;
+
+/// The error code and warning flag from the start of a diagnostic message, like this:
+///
+/// ```text
+/// [GHC-40910] [-Wunused-top-binds, Werror=unused-top-binds]
+/// ```
+///
+/// GHC 9.6 and newer print error codes; warnings have printed their flag for much longer.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct DiagnosticHeader {
+    /// The error code, like `GHC-40910`.
+    pub code: Option<String>,
+    /// The warning flag, like `-Wunused-top-binds`.
+    pub flag: Option<String>,
+}
+
+impl DiagnosticHeader {
+    /// Parse the bracketed groups at the start of a diagnostic message.
+    ///
+    /// Messages without a header give an empty [`DiagnosticHeader`].
+    pub fn parse(message: &str) -> Self {
+        let mut header = Self::default();
+        let mut input = message;
+        while let Ok(group) = preceded(space0, bracketed).parse_next(&mut input) {
+            if error_code.parse(group).is_ok() {
+                header.code = Some(group.to_owned());
+            } else if let Some(flag) = warning_flag(group) {
+                header.flag = Some(flag);
+            }
+        }
+        header
+    }
+}
+
+/// Parse a `[...]` group on a single line.
+fn bracketed<'i>(input: &mut &'i str) -> PResult<&'i str> {
+    delimited('[', take_till(1.., [']', '\n']), ']').parse_next(input)
+}
+
+/// Parse an error code like `GHC-40910`.
+fn error_code(input: &mut &str) -> PResult<()> {
+    (
+        take_while(1.., |c: char| c.is_ascii_uppercase()),
+        '-',
+        digit1,
+        eof,
+    )
+        .void()
+        .parse_next(input)
+}
+
+/// Get the warning flag from a group like `-Wunused-top-binds, -Werror=unused-top-binds`.
+///
+/// GHC 9.8 and newer drop the leading `-` from the `Werror` part, so we only look at the first
+/// flag.
+fn warning_flag(group: &str) -> Option<String> {
+    let flag = group.split(',').next()?.trim();
+    flag.strip_prefix("-W")
+        .filter(|name| !name.is_empty() && !name.starts_with("error"))
+        .map(|_| flag.to_owned())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    fn header(code: Option<&str>, flag: Option<&str>) -> DiagnosticHeader {
+        DiagnosticHeader {
+            code: code.map(ToOwned::to_owned),
+            flag: flag.map(ToOwned::to_owned),
+        }
+    }
+
+    #[test]
+    fn test_parse_diagnostic_header() {
+        assert_eq!(
+            DiagnosticHeader::parse("[GHC-83865]\n    • Couldn't match type\n"),
+            header(Some("GHC-83865"), None)
+        );
+        assert_eq!(
+            DiagnosticHeader::parse("[GHC-66111] [-Wunused-imports]\n    The import\n"),
+            header(Some("GHC-66111"), Some("-Wunused-imports"))
+        );
+        assert_eq!(
+            DiagnosticHeader::parse("[-Wunused-matches]\n    Defined but not used: `x'\n"),
+            header(None, Some("-Wunused-matches"))
+        );
+        // `-Werror` in GHC 9.4 and GHC 9.8.
+        assert_eq!(
+            DiagnosticHeader::parse("[-Wunused-top-binds, -Werror=unused-top-binds]\n"),
+            header(None, Some("-Wunused-top-binds"))
+        );
+        assert_eq!(
+            DiagnosticHeader::parse("[GHC-40910] [-Wunused-top-binds, Werror=unused-top-binds]\n"),
+            header(Some("GHC-40910"), Some("-Wunused-top-binds"))
+        );
+    }
+
+    #[test]
+    fn test_parse_diagnostic_header_negative() {
+        assert_eq!(DiagnosticHeader::parse(""), header(None, None));
+        assert_eq!(
+            DiagnosticHeader::parse("\n    [GHC-83865] isn't on the first line\n"),
+            header(None, None)
+        );
+        assert_eq!(
+            DiagnosticHeader::parse("can't find file: [GHC-83865].hs\n"),
+            header(None, None)
+        );
+        assert_eq!(
+            DiagnosticHeader::parse("[Not a code] [-W]\n"),
+            header(None, None)
+        );
+    }
+}
@@ -9,6 +9,7 @@ use crate::ghci::parse::ghc_message::position;
 use crate::ghci::parse::ghc_message::severity;
 use crate::ghci::parse::ghc_message::GhcMessage;
 
+use super::DiagnosticHeader;
 use super::GhcDiagnostic;
 
 /// Parse a warning or error like this:
@@ -31,12 +32,16 @@ pub fn generic_diagnostic(input: &mut &str) -> PResult<GhcMessage> {
     let severity = severity::parse_severity_colon.parse_next(input)?;
     let _ = space0.parse_next(input)?;
     let message = parse_message_body.parse_next(input)?;
+    let header = DiagnosticHeader::parse(message);
 
     Ok(GhcMessage::Diagnostic(GhcDiagnostic {
         severity,
         path: Some(path.to_owned()),
         span,
+        code: header.code,
+        flag: header.flag,
         message: message.to_owned(),
+        colored: None,
     }))
 }
 
@@ -69,6 +74,8 @@ mod tests {
                 severity: Severity::Error,
                 path: Some("NotStockDeriveable.hs".into()),
                 span: PositionRange::new(6, 12, 6, 12),
+                code: Some("GHC-00158".into()),
+                flag: None,
                 message: indoc!(
                     "[GHC-00158]
                         • Can't make a derived instance of ‘MyClass MyType’:
@@ -80,7 +87,8 @@ mod tests {
                       |            ^^^^^^^
                     "
                 )
-                .into()
+                .into(),
+                colored: None,
             })
         );
 
@@ -109,6 +117,8 @@ mod tests {
                 severity: Severity::Error,
                 path: Some("src/MyModule.hs".into()),
                 span: PositionRange::new(4, 11, 4, 11),
+                code: None,
+                flag: None,
                 message: [
                     "",
                     "    • Couldn't match type ‘[Char]’ with ‘()’",
@@ -121,7 +131,8 @@ mod tests {
                     "  |           ^^^^^^^^^",
                     "",
                 ]
-                .join("\n")
+                .join("\n"),
+                colored: None,
             }
             .to_string(),
             indoc!(
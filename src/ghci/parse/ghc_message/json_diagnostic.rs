@@ -0,0 +1,323 @@
+use camino::Utf8PathBuf;
+use serde::Deserialize;
+use winnow::combinator::peek;
+use winnow::PResult;
+use winnow::Parser;
+
+use crate::ghci::parse::lines::until_newline;
+
+use super::GhcDiagnostic;
+use super::GhcMessage;
+use super::PositionRange;
+use super::Severity;
+
+/// A diagnostic printed by GHC with `-fdiagnostics-as-json`, supported in GHC 9.10 and later.
+///
+/// See: <https://downloads.haskell.org/ghc/latest/docs/users_guide/diagnostics-as-json-schema-1_1.json>
+#[derive(Debug, Deserialize)]
+struct JsonDiagnostic {
+    span: Option<JsonSpan>,
+    severity: JsonSeverity,
+    code: Option<u32>,
+    message: Vec<String>,
+    #[serde(default)]
+    hints: Vec<String>,
+    #[serde(default)]
+    reason: Option<JsonReason>,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonSpan {
+    file: Utf8PathBuf,
+    start: JsonPosition,
+    end: JsonPosition,
+}
+
+#[derive(Debug, Deserialize)]
+struct JsonPosition {
+    line: usize,
+    column: usize,
+}
+
+#[derive(Debug, Deserialize)]
+enum JsonSeverity {
+    Warning,
+    Error,
+}
+
+/// Why a warning was emitted.
+///
+/// Schema version 1.0 (GHC 9.10) gives this as a string, and later versions give an object with
+/// the warning flags or the custom warning category.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum JsonReason {
+    Flag(String),
+    Flags { flags: Vec<String> },
+    Category { category: String },
+}
+
+impl JsonReason {
+    /// The warning flags, formatted like `-Wunused-imports`.
+    fn flags(&self) -> Vec<String> {
+        fn format_flag(flag: &str) -> String {
+            let flag = flag.trim_start_matches('-');
+            let flag = flag.strip_prefix('W').unwrap_or(flag);
+            format!("-W{flag}")
+        }
+
+        match self {
+            JsonReason::Flag(flag) => vec![format_flag(flag)],
+            JsonReason::Flags { flags } => flags.iter().map(|flag| format_flag(flag)).collect(),
+            JsonReason::Category { category } => vec![format_flag(category)],
+        }
+    }
+}
+
+impl From<JsonDiagnostic> for GhcDiagnostic {
+    fn from(diagnostic: JsonDiagnostic) -> Self {
+        // Format the message like GHC's text output, so that diagnostics look the same either way.
+        let code = diagnostic.code.map(|code| format!("GHC-{code:05}"));
+        let flags = diagnostic
+            .reason
+            .as_ref()
+            .map(JsonReason::flags)
+            .unwrap_or_default();
+        let flag = flags.first().cloned();
+
+        let mut header = Vec::new();
+        if let Some(code) = &code {
+            header.push(format!("[{code}]"));
+        }
+        header.extend(flags.iter().map(|flag| format!("[{flag}]")));
+        let mut message = header.join(" ");
+        message.push('\n');
+
+        match diagnostic.message.as_slice() {
+            [single] => push_indented(&mut message, 4, None, single),
+            messages => {
+                for item in messages {
+                    push_indented(&mut message, 4, Some("• "), item);
+                }
+            }
+        }
+
+        match diagnostic.hints.as_slice() {
+            [] => {}
+            [single] => {
+                message.push_str("    Suggested fix:\n");
+                push_indented(&mut message, 6, None, single);
+            }
+            hints => {
+                message.push_str("    Suggested fixes:\n");
+                for hint in hints {
+                    push_indented(&mut message, 6, Some("• "), hint);
+                }
+            }
+        }
+
+        let (path, span) = match diagnostic.span {
+            Some(span) => {
+                // GHC's JSON spans have exclusive end columns, but its text output (and
+                // `PositionRange`) uses inclusive end columns.
+                let end_column = if span.start.line == span.end.line {
+                    span.start.column.max(span.end.column.saturating_sub(1))
+                } else {
+                    span.end.column.saturating_sub(1)
+                };
+                (
+                    Some(span.file),
+                    PositionRange::new(
+                        span.start.line,
+                        span.start.column,
+                        span.end.line,
+                        end_column,
+                    ),
+                )
+            }
+            None => (None, PositionRange::default()),
+        };
+
+        Self {
+            severity: match diagnostic.severity {
+                JsonSeverity::Warning => Severity::Warning,
+                JsonSeverity::Error => Severity::Error,
+            },
+            path,
+            span,
+            code,
+            flag,
+            message,
+            colored: None,
+        }
+    }
+}
+
+/// Push `text` to `message` with each line indented by `indent` spaces, with an optional
+/// `bullet` before the first line. Continuation lines are aligned with the text after the bullet.
+fn push_indented(message: &mut String, indent: usize, bullet: Option<&str>, text: &str) {
+    let bullet = bullet.unwrap_or("");
+    for (i, line) in text.lines().enumerate() {
+        if line.is_empty() {
+            message.push('\n');
+            continue;
+        }
+        message.push_str(&" ".repeat(indent));
+        if i == 0 {
+            message.push_str(bullet);
+        } else {
+            message.push_str(&" ".repeat(bullet.chars().count()));
+        }
+        message.push_str(line);
+        message.push('\n');
+    }
+}
+
+/// Parse a diagnostic printed as a line of JSON by GHC's `-fdiagnostics-as-json` option, like
+/// this:
+///
+/// ```text
+/// {"version":"1.1","ghcVersion":"ghc-9.12.1","span":{"file":"src/MyLib.hs","start":{"line":4,"column":11},"end":{"line":4,"column":14}},"severity":"Error","code":88464,"message":["Variable not in scope: foo"],"hints":[],"reason":null}
+/// ```
+pub fn json_diagnostic(input: &mut &str) -> PResult<GhcMessage> {
+    let _ = peek("{").parse_next(input)?;
+    until_newline
+        .verify_map(|line: &str| serde_json::from_str::<JsonDiagnostic>(line).ok())
+        .map(|diagnostic| GhcMessage::Diagnostic(diagnostic.into()))
+        .parse_next(input)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_json_diagnostic() {
+        assert_eq!(
+            json_diagnostic
+                .parse(concat!(
+                    r#"{"version":"1.1","ghcVersion":"ghc-9.12.1","#,
+                    r#""span":{"file":"src/MyLib.hs","start":{"line":4,"column":11},"end":{"line":4,"column":14}},"#,
+                    r#""severity":"Error","code":88464,"message":["Variable not in scope: foo"],"#,
+                    r#""hints":["Perhaps use ‘for’ (imported from Prelude)"],"reason":null}"#,
+                    "\n"
+                ))
+                .unwrap(),
+            GhcMessage::Diagnostic(GhcDiagnostic {
+                severity: Severity::Error,
+                path: Some("src/MyLib.hs".into()),
+                span: PositionRange::new(4, 11, 4, 13),
+                code: Some("GHC-88464".into()),
+                flag: None,
+                message: indoc!(
+                    "
+                    [GHC-88464]
+                        Variable not in scope: foo
+                        Suggested fix:
+                          Perhaps use ‘for’ (imported from Prelude)
+                    "
+                )
+                .trim_start()
+                .to_owned(),
+                colored: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_json_diagnostic_warning() {
+        // Schema 1.1 gives warning flags in an object.
+        assert_eq!(
+            json_diagnostic
+                .parse(concat!(
+                    r#"{"version":"1.1","ghcVersion":"ghc-9.12.1","#,
+                    r#""span":{"file":"src/MyLib.hs","start":{"line":3,"column":1},"end":{"line":3,"column":18}},"#,
+                    r#""severity":"Warning","code":66111,"#,
+                    r#""message":["The import of ‘Data.List’ is redundant\n  except perhaps to import instances from ‘Data.List’","To import instances alone, use: import Data.List()"],"#,
+                    r#""hints":[],"reason":{"flags":["unused-imports"]}}"#,
+                ))
+                .unwrap(),
+            GhcMessage::Diagnostic(GhcDiagnostic {
+                severity: Severity::Warning,
+                path: Some("src/MyLib.hs".into()),
+                span: PositionRange::new(3, 1, 3, 17),
+                code: Some("GHC-66111".into()),
+                flag: Some("-Wunused-imports".into()),
+                message: indoc!(
+                    "
+                    [GHC-66111] [-Wunused-imports]
+                        • The import of ‘Data.List’ is redundant
+                            except perhaps to import instances from ‘Data.List’
+                        • To import instances alone, use: import Data.List()
+                    "
+                )
+                .trim_start()
+                .to_owned(),
+                colored: None,
+            })
+        );
+
+        // Schema 1.0 gives the warning flag as a string.
+        let message = json_diagnostic
+            .parse(concat!(
+                r#"{"version":"1.0","ghcVersion":"ghc-9.10.1","#,
+                r#""span":{"file":"src/MyLib.hs","start":{"line":5,"column":1},"end":{"line":5,"column":2}},"#,
+                r#""severity":"Warning","code":40910,"message":["Defined but not used: ‘x’"],"#,
+                r#""hints":[],"reason":"Wunused-top-binds"}"#,
+            ))
+            .unwrap()
+            .into_diagnostic()
+            .unwrap();
+        assert_eq!(
+            message.to_string(),
+            indoc!(
+                "
+                src/MyLib.hs:5:1: warning: [GHC-40910] [-Wunused-top-binds]
+                    Defined but not used: ‘x’
+                "
+            )
+            .trim_start()
+        );
+    }
+
+    #[test]
+    fn test_parse_json_diagnostic_without_span() {
+        assert_eq!(
+            json_diagnostic
+                .parse(concat!(
+                    r#"{"version":"1.1","ghcVersion":"ghc-9.12.1","span":null,"#,
+                    r#""severity":"Error","code":null,"message":["can't find file: src/MyLib.hs"],"#,
+                    r#""hints":[],"reason":null}"#,
+                ))
+                .unwrap(),
+            GhcMessage::Diagnostic(GhcDiagnostic {
+                severity: Severity::Error,
+                path: None,
+                span: PositionRange::default(),
+                code: None,
+                flag: None,
+                message: "\n    can't find file: src/MyLib.hs\n".into(),
+                colored: None,
+            })
+        );
+    }
+
+    #[test]
+    fn test_parse_json_diagnostic_negative() {
+        // Not JSON.
+        assert!(json_diagnostic.parse("{-# LANGUAGE GADTs #-}\n").is_err());
+        // JSON, but not a diagnostic.
+        assert!(json_diagnostic.parse("{\"foo\": 1}\n").is_err());
+        // Multiple lines.
+        assert!(json_diagnostic
+            .parse(concat!(
+                r#"{"span":null,"severity":"Error","code":null,"message":["oops"]}"#,
+                "\n",
+                "Failed, no modules loaded.\n",
+            ))
+            .is_err());
+    }
+}
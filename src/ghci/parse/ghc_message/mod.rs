@@ -4,11 +4,14 @@ use std::fmt::Display;
 
 use camino::Utf8PathBuf;
 use miette::miette;
+use serde::Serialize;
 use winnow::combinator::alt;
 use winnow::combinator::repeat;
 use winnow::prelude::*;
 
 mod position;
+pub use position::parse_position_range;
+pub use position::Position;
 pub use position::PositionRange;
 
 mod severity;
@@ -17,7 +20,7 @@ pub use severity::Severity;
 mod single_quote;
 
 mod path_colon;
-use path_colon::path_colon;
+pub use path_colon::path_colon;
 
 mod compiling;
 use compiling::compiling;
@@ -33,6 +36,7 @@ use loaded_configuration::loaded_configuration;
 
 mod cant_find_file_diagnostic;
 use cant_find_file_diagnostic::cant_find_file_diagnostic;
+pub use cant_find_file_diagnostic::CANT_FIND_FILE_MESSAGE;
 
 mod generic_diagnostic;
 use generic_diagnostic::generic_diagnostic;
@@ -43,6 +47,13 @@ use module_import_cycle_diagnostic::module_import_cycle_diagnostic;
 mod no_location_info_diagnostic;
 use no_location_info_diagnostic::no_location_info_diagnostic;
 
+mod diagnostic_header;
+use diagnostic_header::DiagnosticHeader;
+
+mod json_diagnostic;
+use json_diagnostic::json_diagnostic;
+
+use super::ansi::StrippedText;
 use super::rest_of_line;
 use super::Module;
 
@@ -98,7 +109,8 @@ impl GhcMessage {
 }
 
 /// The result of compiling modules in `ghci`.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum CompilationResult {
     /// All the modules compiled successfully.
     Ok,
@@ -111,7 +123,7 @@ pub enum CompilationResult {
 /// ```text
 /// Foo.hs:81:1: Warning: Defined but not used: `bar'
 /// ```
-#[derive(Debug, Clone, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
 pub struct GhcDiagnostic {
     /// The diagnostic's severity.
     pub severity: Severity,
@@ -119,8 +131,44 @@ pub struct GhcDiagnostic {
     pub path: Option<Utf8PathBuf>,
     /// Span for the diagnostic.
     pub span: PositionRange,
+    /// The diagnostic's error code, like `GHC-83865`. GHC 9.6 and newer print these.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// The warning flag which enabled the diagnostic, like `-Wunused-imports`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub flag: Option<String>,
     /// The associated message.
+    ///
+    /// This includes the error code and warning flag, as GHC prints them.
     pub message: String,
+    /// The whole diagnostic as GHC printed it, including ANSI color codes, if GHC's output was
+    /// colored.
+    ///
+    /// Use this when showing diagnostics to the user, but not when writing files. See
+    /// [`GhcDiagnostic::colored`].
+    #[serde(skip)]
+    pub colored: Option<String>,
+}
+
+impl GhcDiagnostic {
+    /// Get the link to the [Haskell Error Index][index] page for this diagnostic's error code, if
+    /// any.
+    ///
+    /// [index]: https://errors.haskell.org/
+    pub fn error_index_url(&self) -> Option<String> {
+        self.code
+            .as_deref()
+            .filter(|code| code.starts_with("GHC-"))
+            .map(|code| format!("https://errors.haskell.org/messages/{code}"))
+    }
+
+    /// Display the diagnostic with GHC's colors, if it had any.
+    pub fn colored(&self) -> String {
+        match &self.colored {
+            Some(colored) => colored.clone(),
+            None => self.to_string(),
+        }
+    }
 }
 
 impl Display for GhcDiagnostic {
@@ -148,16 +196,29 @@ impl Display for GhcDiagnostic {
 }
 
 /// Parse [`GhcMessage`]s from lines of compiler output.
+///
+/// Colors are stripped before parsing, and diagnostics keep the original colored text in
+/// [`GhcDiagnostic::colored`].
 pub fn parse_ghc_messages(lines: &str) -> miette::Result<Vec<GhcMessage>> {
-    // TODO: Preserve ANSI colors somehow.
-    let uncolored_lines = strip_ansi_escapes::strip_str(lines);
+    let stripped = StrippedText::new(lines);
+
+    let messages = parse_messages_inner
+        .parse(stripped.as_str())
+        .map_err(|err| miette!("{err}"))?;
 
-    parse_messages_inner
-        .parse(&uncolored_lines)
-        .map_err(|err| miette!("{err}"))
+    Ok(messages
+        .into_iter()
+        .map(|(mut message, recognized)| {
+            if let GhcMessage::Diagnostic(diagnostic) = &mut message {
+                diagnostic.colored = stripped.original(stripped.range_of(recognized));
+            }
+            message
+        })
+        .collect())
 }
 
-fn parse_messages_inner(input: &mut &str) -> PResult<Vec<GhcMessage>> {
+/// Parse messages, along with the text each message was parsed from.
+fn parse_messages_inner<'i>(input: &mut &'i str) -> PResult<Vec<(GhcMessage, &'i str)>> {
     enum Item {
         One(GhcMessage),
         Many(Vec<GhcMessage>),
@@ -168,6 +229,9 @@ fn parse_messages_inner(input: &mut &str) -> PResult<Vec<GhcMessage>> {
         0..,
         alt((
             compiling.map(Item::One),
+            // Diagnostics from `-fdiagnostics-as-json`; the text parsers below handle older
+            // compilers.
+            json_diagnostic.map(Item::One),
             generic_diagnostic.map(Item::One),
             cant_find_file_diagnostic.map(Item::One),
             no_location_info_diagnostic.map(Item::One),
@@ -178,12 +242,13 @@ fn parse_messages_inner(input: &mut &str) -> PResult<Vec<GhcMessage>> {
                 tracing::debug!(line, "Ignoring GHC output line");
                 Item::Ignore
             }),
-        )),
+        ))
+        .with_recognized(),
     )
-    .fold(Vec::new, |mut messages, item| {
+    .fold(Vec::new, |mut messages, (item, recognized)| {
         match item {
-            Item::One(item) => messages.push(item),
-            Item::Many(items) => messages.extend(items),
+            Item::One(item) => messages.push((item, recognized)),
+            Item::Many(items) => messages.extend(items.into_iter().map(|item| (item, recognized))),
             Item::Ignore => {}
         }
         messages
@@ -191,6 +256,22 @@ fn parse_messages_inner(input: &mut &str) -> PResult<Vec<GhcMessage>> {
     .parse_next(input)
 }
 
+#[cfg(test)]
+impl GhcDiagnostic {
+    /// Construct a diagnostic without a span, code, flag, or colors.
+    pub fn new(severity: Severity, path: Option<&str>, message: &str) -> Self {
+        Self {
+            severity,
+            path: path.map(Utf8PathBuf::from),
+            span: Default::default(),
+            code: None,
+            flag: None,
+            message: message.to_owned(),
+            colored: None,
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -245,6 +326,8 @@ mod tests {
                     severity: Severity::Error,
                     path: Some("src/MyModule.hs".into()),
                     span: PositionRange::new(4, 11, 4, 11),
+                    code: None,
+                    flag: None,
                     message: [
                         "",
                         "    • Couldn't match type ‘[Char]’ with ‘()’",
@@ -257,7 +340,8 @@ mod tests {
                         "  |           ^^^^^^^^^",
                         "",
                     ]
-                    .join("\n")
+                    .join("\n"),
+                    colored: None,
                 }),
                 GhcMessage::Summary(CompilationSummary {
                     result: CompilationResult::Err,
@@ -266,4 +350,86 @@ mod tests {
             ]
         );
     }
+
+    #[test]
+    fn test_parse_messages_json() {
+        assert_eq!(
+            parse_ghc_messages(concat!(
+                "[1 of 2] Compiling MyLib            ( src/MyLib.hs, interpreted )\n",
+                r#"{"version":"1.1","ghcVersion":"ghc-9.12.1","span":{"file":"src/MyLib.hs","start":{"line":4,"column":11},"end":{"line":4,"column":20}},"severity":"Error","code":83865,"message":["Couldn't match type ‘[Char]’ with ‘()’"],"hints":[],"reason":null}"#,
+                "\n",
+                "Failed, no modules loaded.\n",
+            ))
+            .unwrap(),
+            vec![
+                GhcMessage::Compiling(Module {
+                    name: "MyLib".into(),
+                    path: "src/MyLib.hs".into(),
+                }),
+                GhcMessage::Diagnostic(GhcDiagnostic {
+                    severity: Severity::Error,
+                    path: Some("src/MyLib.hs".into()),
+                    span: PositionRange::new(4, 11, 4, 19),
+                    code: Some("GHC-83865".into()),
+                    flag: None,
+                    message: "[GHC-83865]\n    Couldn't match type ‘[Char]’ with ‘()’\n".into(),
+                    colored: None,
+                }),
+                GhcMessage::Summary(CompilationSummary {
+                    result: CompilationResult::Err,
+                    modules_loaded: 0,
+                }),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_messages_colored() {
+        let bold = |text: &str| format!("\x1b[;1m{text}\x1b[0m\x1b[0m");
+        let error = "\x1b[;1m\x1b[31merror\x1b[0m\x1b[0m\x1b[;1m:";
+        let diagnostic = format!(
+            "{}{error} [GHC-88464]\n    Variable not in scope: foo\n",
+            bold("src/MyLib.hs:4:11: "),
+        );
+        let messages = parse_ghc_messages(&format!(
+            "[1 of 1] Compiling MyLib            ( src/MyLib.hs, interpreted )\n\
+             {diagnostic}\
+             Failed, no modules loaded.\n"
+        ))
+        .unwrap();
+
+        assert_eq!(
+            messages,
+            vec![
+                GhcMessage::Compiling(Module {
+                    name: "MyLib".into(),
+                    path: "src/MyLib.hs".into(),
+                }),
+                GhcMessage::Diagnostic(GhcDiagnostic {
+                    severity: Severity::Error,
+                    path: Some("src/MyLib.hs".into()),
+                    span: PositionRange::new(4, 11, 4, 11),
+                    code: Some("GHC-88464".into()),
+                    flag: None,
+                    message: "[GHC-88464]\n    Variable not in scope: foo\n".into(),
+                    colored: Some(format!(
+                        "{}\x1b[0m\n",
+                        diagnostic.strip_suffix('\n').unwrap()
+                    )),
+                }),
+                GhcMessage::Summary(CompilationSummary {
+                    result: CompilationResult::Err,
+                    modules_loaded: 0,
+                }),
+            ]
+        );
+
+        // The plain rendering doesn't include colors.
+        let diagnostic = messages[1].clone().into_diagnostic().unwrap();
+        assert_eq!(
+            diagnostic.to_string(),
+            "src/MyLib.hs:4:11: error: [GHC-88464]\n    Variable not in scope: foo\n"
+        );
+        assert_eq!(diagnostic.colored(), diagnostic.colored.unwrap());
+    }
 }
@@ -71,7 +71,10 @@ pub fn module_import_cycle_diagnostic(input: &mut &str) -> PResult<Vec<GhcMessag
                 severity: Severity::Error,
                 path: Some(path),
                 span: Default::default(),
+                code: None,
+                flag: None,
                 message: message.to_owned(),
+                colored: None,
             })
         })
         .collect())
@@ -103,19 +106,28 @@ mod tests {
                     severity: Severity::Error,
                     path: Some("./C.hs".into()),
                     span: Default::default(),
-                    message: message.to_owned()
+                    code: None,
+                    flag: None,
+                    message: message.to_owned(),
+                    colored: None,
                 }),
                 GhcMessage::Diagnostic(GhcDiagnostic {
                     severity: Severity::Error,
                     path: Some("A.hs".into()),
                     span: Default::default(),
-                    message: message.to_owned()
+                    code: None,
+                    flag: None,
+                    message: message.to_owned(),
+                    colored: None,
                 }),
                 GhcMessage::Diagnostic(GhcDiagnostic {
                     severity: Severity::Error,
                     path: Some("./B.hs".into()),
                     span: Default::default(),
-                    message: message.to_owned()
+                    code: None,
+                    flag: None,
+                    message: message.to_owned(),
+                    colored: None,
                 }),
             ]
         );
@@ -132,19 +144,28 @@ mod tests {
                     severity: Severity::Error,
                     path: Some("./C.hs".into()),
                     span: Default::default(),
-                    message: message.clone()
+                    code: None,
+                    flag: None,
+                    message: message.clone(),
+                    colored: None,
                 }),
                 GhcMessage::Diagnostic(GhcDiagnostic {
                     severity: Severity::Error,
                     path: Some("A.hs".into()),
                     span: Default::default(),
-                    message: message.clone()
+                    code: None,
+                    flag: None,
+                    message: message.clone(),
+                    colored: None,
                 }),
                 GhcMessage::Diagnostic(GhcDiagnostic {
                     severity: Severity::Error,
                     path: Some("./B.hs".into()),
                     span: Default::default(),
-                    message: message.clone()
+                    code: None,
+                    flag: None,
+                    message: message.clone(),
+                    colored: None,
                 }),
             ]
         );
@@ -161,7 +182,10 @@ mod tests {
                 severity: Severity::Error,
                 path: Some("A.hs".into()),
                 span: Default::default(),
+                code: None,
+                flag: None,
                 message: message.into(),
+                colored: None,
             })]
         );
 
@@ -8,6 +8,7 @@ use crate::ghci::parse::ghc_message::position;
 use crate::ghci::parse::ghc_message::severity;
 use crate::ghci::parse::ghc_message::GhcMessage;
 
+use super::DiagnosticHeader;
 use super::GhcDiagnostic;
 
 /// Parse a message like this:
@@ -23,12 +24,16 @@ pub fn no_location_info_diagnostic(input: &mut &str) -> PResult<GhcMessage> {
     let severity = severity::parse_severity_colon.parse_next(input)?;
     let _ = space0.parse_next(input)?;
     let message = parse_message_body.parse_next(input)?;
+    let header = DiagnosticHeader::parse(message);
 
     Ok(GhcMessage::Diagnostic(GhcDiagnostic {
         severity,
         path: None,
         span: Default::default(),
+        code: header.code,
+        flag: header.flag,
         message: message.to_owned(),
+        colored: None,
     }))
 }
 
@@ -56,10 +61,13 @@ mod tests {
                 severity: Severity::Error,
                 path: None,
                 span: Default::default(),
+                code: None,
+                flag: None,
                 message: "\n    Could not find module ‘Example’\
                     \n    It is not a module in the current program, or in any known package.\
                     \n"
-                .into()
+                .into(),
+                colored: None,
             })
         );
 
@@ -77,6 +85,8 @@ mod tests {
                 severity: Severity::Error,
                 path: None,
                 span: Default::default(),
+                code: Some("GHC-29235".into()),
+                flag: None,
                 message: indoc!(
                     "
                     [GHC-29235]
@@ -84,7 +94,8 @@ mod tests {
                                                                                           src/Foo.hs
                     "
                 )
-                .into()
+                .into(),
+                colored: None,
             })
         );
 
@@ -0,0 +1,126 @@
+use miette::miette;
+use winnow::ascii::multispace1;
+use winnow::combinator::alt;
+use winnow::combinator::repeat;
+use winnow::combinator::terminated;
+use winnow::token::take_till;
+use winnow::token::take_until;
+use winnow::PResult;
+use winnow::Parser;
+
+use super::lines::rest_of_line;
+use super::module_name;
+
+/// Parse the names of the modules imported by a Haskell source file.
+///
+/// This only looks at `import` declarations at the start of a line, which is where they are in
+/// any module using layout. Imports in block comments are skipped.
+pub fn parse_imports(contents: &str) -> miette::Result<Vec<String>> {
+    parse_imports_inner
+        .parse(contents)
+        .map_err(|err| miette!("{err}"))
+}
+
+fn parse_imports_inner(input: &mut &str) -> PResult<Vec<String>> {
+    repeat(
+        0..,
+        alt((
+            import.map(Some),
+            block_comment.map(|()| None),
+            rest_of_line.map(|_| None),
+        )),
+    )
+    .fold(Vec::new, |mut imports, item| {
+        if let Some(item) = item {
+            imports.push(item);
+        }
+        imports
+    })
+    .parse_next(input)
+}
+
+/// Parse an import declaration, like this:
+///
+/// ```text
+/// import {-# SOURCE #-} safe qualified "base" Data.List qualified as List (sortOn)
+/// ```
+fn import(input: &mut &str) -> PResult<String> {
+    let _ = ("import", multispace1).parse_next(input)?;
+    let _: () = repeat(
+        0..,
+        terminated(
+            alt((
+                "{-# SOURCE #-}",
+                "safe",
+                "qualified",
+                // A package import, like `"base"`.
+                ('"', take_till(0.., '"'), '"').recognize(),
+            )),
+            multispace1,
+        ),
+    )
+    .parse_next(input)?;
+    let name = module_name.parse_next(input)?;
+    let _ = rest_of_line.parse_next(input)?;
+    Ok(name.to_owned())
+}
+
+/// Parse a block comment starting at the beginning of a line, like `{- ... -}`.
+///
+/// Nested block comments aren't supported.
+fn block_comment(input: &mut &str) -> PResult<()> {
+    let _ = ("{-", take_until(0.., "-}"), "-}").parse_next(input)?;
+    let _ = rest_of_line.parse_next(input)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_imports() {
+        assert_eq!(
+            parse_imports(indoc!(
+                r#"
+                {-# LANGUAGE OverloadedStrings #-}
+                module MyModule (example) where
+
+                import Data.Text (Text)
+                import qualified Data.Map as Map
+                import Data.Set qualified as Set
+                import {-# SOURCE #-} MyLib.Boot
+                import safe "base" Data.List
+                import
+                  MyLib.Types
+                {-
+                import Commented.Out
+                -}
+                -- import Also.Commented.Out
+                importantValue = "import Not.An.Import"
+                "#
+            ))
+            .unwrap(),
+            vec![
+                "Data.Text",
+                "Data.Map",
+                "Data.Set",
+                "MyLib.Boot",
+                "Data.List",
+                "MyLib.Types",
+            ]
+        );
+    }
+
+    #[test]
+    fn test_parse_imports_empty() {
+        assert_eq!(parse_imports("").unwrap(), Vec::<String>::new());
+        assert_eq!(
+            parse_imports("module Main where\nmain = pure ()").unwrap(),
+            Vec::<String>::new()
+        );
+    }
+}
@@ -1,14 +1,18 @@
 //! Parsers for `ghci` output and Haskell code.
 
+mod ansi;
 mod eval;
 mod ghc_message;
 mod haskell_grammar;
+mod imports;
 mod lines;
 mod module_and_files;
 mod module_set;
+mod show_modules;
 mod show_paths;
 mod show_targets;
 mod target_kind;
+mod test_output;
 
 use haskell_grammar::module_name;
 use lines::rest_of_line;
@@ -21,10 +25,18 @@ pub use ghc_message::CompilationResult;
 pub use ghc_message::CompilationSummary;
 pub use ghc_message::GhcDiagnostic;
 pub use ghc_message::GhcMessage;
+pub use ghc_message::Position;
+pub use ghc_message::PositionRange;
 pub use ghc_message::Severity;
+pub use ghc_message::CANT_FIND_FILE_MESSAGE;
+pub use imports::parse_imports;
 pub use module_and_files::Module;
 pub use module_set::ModuleSet;
+pub use show_modules::parse_show_modules;
 pub use show_paths::parse_show_paths;
 pub use show_paths::ShowPaths;
 pub use show_targets::parse_show_targets;
 pub use target_kind::TargetKind;
+pub use test_output::parse_test_output;
+pub use test_output::TestMessage;
+pub use test_output::TestSummary;
@@ -61,6 +61,17 @@ impl ModuleSet {
         }
     }
 
+    /// Remove a source path from this module set.
+    ///
+    /// Returns whether the value was present.
+    pub fn remove_source_path<P>(&mut self, path: &P) -> bool
+    where
+        NormalPath: Borrow<P>,
+        P: Hash + Eq + ?Sized,
+    {
+        self.modules.remove(path).is_some()
+    }
+
     /// Get the name used to refer to the given module path when importing it.
     ///
     /// If the module isn't imported, a path will be returned.
@@ -103,6 +114,11 @@ impl ModuleSet {
     pub fn iter(&self) -> Keys<'_, NormalPath, TargetKind> {
         self.modules.keys()
     }
+
+    /// Iterate over the source paths in this module set, with how they were imported.
+    pub fn iter_with_kinds(&self) -> impl Iterator<Item = (&NormalPath, TargetKind)> {
+        self.modules.iter().map(|(path, kind)| (path, *kind))
+    }
 }
 
 /// Information about a module to be imported into a `ghci` session.
@@ -117,3 +133,35 @@ pub struct ImportInfo {
     /// Whether the module is already loaded in the `ghci` session.
     pub loaded: bool,
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_remove_source_path() {
+        let mut modules = ModuleSet::from_paths(
+            [
+                ("src/MyLib.hs", TargetKind::Module),
+                ("src/MyModule.hs", TargetKind::Path),
+            ],
+            "/project",
+        )
+        .unwrap();
+
+        let present = NormalPath::new("src/MyModule.hs", "/project").unwrap();
+        assert!(modules.remove_source_path(&present));
+        assert!(!modules.contains_source_path(&present));
+        assert_eq!(modules.len(), 1);
+
+        let missing = NormalPath::new("src/Missing.hs", "/project").unwrap();
+        assert!(!modules.remove_source_path(&missing));
+        assert_eq!(modules.len(), 1);
+
+        // Removing a path twice only removes it once.
+        assert!(!modules.remove_source_path(&present));
+        assert!(modules.contains_source_path(&NormalPath::new("src/MyLib.hs", "/project").unwrap()));
+    }
+}
@@ -0,0 +1,52 @@
+use miette::miette;
+use winnow::combinator::repeat;
+use winnow::combinator::terminated;
+use winnow::Parser;
+
+use super::lines::rest_of_line;
+use super::module_and_files;
+use super::Module;
+
+/// Parse `:show modules` output into a list of loaded modules.
+pub fn parse_show_modules(input: &str) -> miette::Result<Vec<Module>> {
+    repeat(0.., terminated(module_and_files, rest_of_line))
+        .parse(input)
+        .map_err(|err| miette!("{err}"))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_show_modules() {
+        assert_eq!(
+            parse_show_modules(indoc!(
+                "
+                MyLib            ( src/MyLib.hs, interpreted )
+                MyModule         ( src/MyModule.hs, dist-newstyle/build/MyModule.o )
+                TestMain         ( test/TestMain.hs, interpreted )
+                "
+            ))
+            .unwrap(),
+            vec![
+                Module {
+                    name: "MyLib".into(),
+                    path: "src/MyLib.hs".into(),
+                },
+                Module {
+                    name: "MyModule".into(),
+                    path: "src/MyModule.hs".into(),
+                },
+                Module {
+                    name: "TestMain".into(),
+                    path: "test/TestMain.hs".into(),
+                },
+            ]
+        );
+
+        assert_eq!(parse_show_modules("").unwrap(), vec![]);
+    }
+}
@@ -0,0 +1,165 @@
+use winnow::ascii::digit1;
+use winnow::ascii::line_ending;
+use winnow::ascii::space0;
+use winnow::combinator::opt;
+use winnow::PResult;
+use winnow::Parser;
+
+use crate::ghci::parse::lines::until_newline;
+
+use super::dedent;
+use super::indented_lines;
+use super::source_location;
+use super::TestFailure;
+use super::TestMessage;
+use super::TestSummary;
+
+/// Parse an `hspec` failure, like this:
+///
+/// ```text
+///   test/MyLibSpec.hs:9:5:
+///   1) MyLib greets the world
+///        expected: "Hello, world!"
+///         but got: "Hello!"
+/// ```
+///
+/// The location line is only printed if `hspec` knows the location of the failing expectation.
+pub fn hspec_failure(input: &mut &str) -> PResult<TestMessage> {
+    let location = opt(until_newline.verify_map(source_location)).parse_next(input)?;
+    let _ = (space0, digit1, ") ").parse_next(input)?;
+    let description = until_newline.parse_next(input)?;
+    let message = indented_lines(3).parse_next(input)?;
+
+    let (path, span) = match location {
+        Some((path, span)) => (Some(path), span),
+        None => (None, Default::default()),
+    };
+
+    Ok(TestMessage::Failure(TestFailure {
+        path,
+        span,
+        description: description.trim_end().to_owned(),
+        message: dedent(&message),
+    }))
+}
+
+/// Parse an `hspec` summary, like `12 examples, 1 failure, 2 pending`.
+pub fn hspec_summary(input: &mut &str) -> PResult<TestMessage> {
+    let total = digit1.parse_to().parse_next(input)?;
+    let _ = (" example", opt("s"), ", ").parse_next(input)?;
+    let failed = digit1.parse_to().parse_next(input)?;
+    let _ = (" failure", opt("s")).parse_next(input)?;
+    let pending = opt((", ", digit1.parse_to(), " pending"))
+        .parse_next(input)?
+        .map(|(_, pending, _)| pending)
+        .unwrap_or(0);
+    let _ = (space0, line_ending).parse_next(input)?;
+
+    Ok(TestMessage::Summary(TestSummary {
+        total,
+        failed,
+        pending,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use crate::ghci::parse::ghc_message::PositionRange;
+
+    #[test]
+    fn test_parse_hspec_failure() {
+        assert_eq!(
+            hspec_failure
+                .parse(indoc!(
+                    r#"
+                      test/MyLibSpec.hs:9:5:
+                      1) MyLib greets the world
+                           expected: "Hello, world!"
+                            but got: "Hello!"
+                    "#
+                ))
+                .unwrap(),
+            TestMessage::Failure(TestFailure {
+                path: Some("test/MyLibSpec.hs".into()),
+                span: PositionRange::new(9, 5, 9, 5),
+                description: "MyLib greets the world".into(),
+                message: "expected: \"Hello, world!\"\n but got: \"Hello!\"".into(),
+            })
+        );
+
+        // Without a location.
+        assert_eq!(
+            hspec_failure
+                .parse(indoc!(
+                    "
+                      12) Parser parses a number
+                           uncaught exception: ErrorCall
+                           oops
+                    "
+                ))
+                .unwrap(),
+            TestMessage::Failure(TestFailure {
+                path: None,
+                span: Default::default(),
+                description: "Parser parses a number".into(),
+                message: "uncaught exception: ErrorCall\noops".into(),
+            })
+        );
+
+        // Negative cases.
+        // The rerun hint isn't part of the failure.
+        assert!(hspec_failure
+            .parse(indoc!(
+                r#"
+                  1) MyLib greets the world
+                       expected: 1
+                  To rerun use: --match "/MyLib/greets the world/"
+                "#
+            ))
+            .is_err());
+        // Not a failure.
+        assert!(hspec_failure.parse("  greets the world [✔]\n").is_err());
+    }
+
+    #[test]
+    fn test_parse_hspec_summary() {
+        assert_eq!(
+            hspec_summary.parse("12 examples, 1 failure\n").unwrap(),
+            TestMessage::Summary(TestSummary {
+                total: 12,
+                failed: 1,
+                pending: 0,
+            })
+        );
+        assert_eq!(
+            hspec_summary.parse("1 example, 0 failures\n").unwrap(),
+            TestMessage::Summary(TestSummary {
+                total: 1,
+                failed: 0,
+                pending: 0,
+            })
+        );
+        assert_eq!(
+            hspec_summary
+                .parse("5 examples, 2 failures, 1 pending\n")
+                .unwrap(),
+            TestMessage::Summary(TestSummary {
+                total: 5,
+                failed: 2,
+                pending: 1,
+            })
+        );
+
+        // Negative cases.
+        assert!(hspec_summary.parse("12 examples\n").is_err());
+        assert!(hspec_summary
+            .parse("0 tests executed, 0 failures\n")
+            .is_err());
+        assert!(hspec_summary.parse("12 examples, 1 failure").is_err());
+    }
+}
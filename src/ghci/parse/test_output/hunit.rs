@@ -0,0 +1,152 @@
+use winnow::ascii::digit1;
+use winnow::ascii::line_ending;
+use winnow::ascii::space0;
+use winnow::ascii::space1;
+use winnow::combinator::alt;
+use winnow::combinator::not;
+use winnow::combinator::preceded;
+use winnow::combinator::repeat;
+use winnow::PResult;
+use winnow::Parser;
+
+use crate::ghci::parse::lines::until_newline;
+
+use super::dedent;
+use super::source_location;
+use super::TestFailure;
+use super::TestMessage;
+use super::TestSummary;
+
+/// Parse an `HUnit` failure or error, like this:
+///
+/// ```text
+/// ### Failure in: 0:greets the world
+/// test/Main.hs:20
+/// expected: "Hello, world!"
+///  but got: "Hello!"
+/// ```
+///
+/// The message continues until a blank line, the next failure, or the summary.
+pub fn hunit_failure(input: &mut &str) -> PResult<TestMessage> {
+    let _ = alt(("### Failure in: ", "### Error in: ")).parse_next(input)?;
+    let description = until_newline.parse_next(input)?;
+    let mut message: Vec<&str> = repeat(
+        0..,
+        preceded(
+            not(alt(("###", "Cases: "))),
+            until_newline.verify(|line: &str| !line.trim().is_empty()),
+        ),
+    )
+    .parse_next(input)?;
+
+    let location = message.first().and_then(|line| source_location(line));
+    let (path, span) = match location {
+        Some((path, span)) => {
+            message.remove(0);
+            (Some(path), span)
+        }
+        None => (None, Default::default()),
+    };
+
+    Ok(TestMessage::Failure(TestFailure {
+        path,
+        span,
+        description: description.trim_end().to_owned(),
+        message: dedent(&message),
+    }))
+}
+
+/// Parse an `HUnit` summary, like `Cases: 12  Tried: 12  Errors: 0  Failures: 1`.
+pub fn hunit_summary(input: &mut &str) -> PResult<TestMessage> {
+    let _ = "Cases: ".parse_next(input)?;
+    let total = digit1.parse_to().parse_next(input)?;
+    let _ = (space1, "Tried: ", digit1, space1, "Errors: ").parse_next(input)?;
+    let errors: usize = digit1.parse_to().parse_next(input)?;
+    let _ = (space1, "Failures: ").parse_next(input)?;
+    let failures: usize = digit1.parse_to().parse_next(input)?;
+    let _ = (space0, line_ending).parse_next(input)?;
+
+    Ok(TestMessage::Summary(TestSummary {
+        total,
+        failed: errors + failures,
+        pending: 0,
+    }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use crate::ghci::parse::ghc_message::PositionRange;
+
+    #[test]
+    fn test_parse_hunit_failure() {
+        assert_eq!(
+            hunit_failure
+                .parse(indoc!(
+                    r#"
+                    ### Failure in: 0:greets the world
+                    test/Main.hs:20
+                    expected: "Hello, world!"
+                     but got: "Hello!"
+                    "#
+                ))
+                .unwrap(),
+            TestMessage::Failure(TestFailure {
+                path: Some("test/Main.hs".into()),
+                span: PositionRange::new(20, 1, 20, 1),
+                description: "0:greets the world".into(),
+                message: "expected: \"Hello, world!\"\n but got: \"Hello!\"".into(),
+            })
+        );
+
+        assert_eq!(
+            hunit_failure
+                .parse(indoc!(
+                    "
+                    ### Error in: 1
+                    Prelude.head: empty list
+                    "
+                ))
+                .unwrap(),
+            TestMessage::Failure(TestFailure {
+                path: None,
+                span: Default::default(),
+                description: "1".into(),
+                message: "Prelude.head: empty list".into(),
+            })
+        );
+
+        // Negative cases.
+        // The summary isn't part of the failure.
+        assert!(hunit_failure
+            .parse(indoc!(
+                "
+                ### Failure in: 0
+                oops
+                Cases: 1  Tried: 1  Errors: 0  Failures: 1
+                "
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_hunit_summary() {
+        assert_eq!(
+            hunit_summary
+                .parse("Cases: 12  Tried: 12  Errors: 1  Failures: 2\n")
+                .unwrap(),
+            TestMessage::Summary(TestSummary {
+                total: 12,
+                failed: 3,
+                pending: 0,
+            })
+        );
+
+        // Negative cases.
+        assert!(hunit_summary.parse("Cases: 12  Tried: 12\n").is_err());
+    }
+}
@@ -0,0 +1,295 @@
+//! Parser for test suite output.
+//!
+//! This recognizes failures and summaries printed by [hspec], [tasty], and [HUnit], so that test
+//! results can be reported alongside compiler diagnostics.
+//!
+//! [hspec]: https://hspec.github.io/
+//! [tasty]: https://hackage.haskell.org/package/tasty
+//! [HUnit]: https://hackage.haskell.org/package/HUnit
+
+use camino::Utf8PathBuf;
+use miette::miette;
+use serde::Serialize;
+use winnow::ascii::digit1;
+use winnow::ascii::space0;
+use winnow::combinator::alt;
+use winnow::combinator::eof;
+use winnow::combinator::opt;
+use winnow::combinator::peek;
+use winnow::combinator::preceded;
+use winnow::combinator::repeat;
+use winnow::error::ContextError;
+use winnow::prelude::*;
+use winnow::token::take_while;
+
+use crate::dedent::dedent;
+
+use super::ghc_message::path_colon;
+use super::ghc_message::GhcDiagnostic;
+use super::ghc_message::PositionRange;
+use super::ghc_message::Severity;
+use super::lines::until_newline;
+use super::rest_of_line;
+
+mod hspec;
+use hspec::hspec_failure;
+use hspec::hspec_summary;
+
+mod tasty;
+use tasty::tasty_failure;
+use tasty::tasty_summary;
+
+mod hunit;
+use hunit::hunit_failure;
+use hunit::hunit_summary;
+
+/// A message printed by a test suite.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TestMessage {
+    /// A test failed.
+    Failure(TestFailure),
+    /// The test suite finished.
+    Summary(TestSummary),
+}
+
+/// A failing test.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TestFailure {
+    /// Path to the file containing the failing assertion, if the test framework printed one.
+    pub path: Option<Utf8PathBuf>,
+    /// Span for the failing assertion, if the test framework printed one.
+    pub span: PositionRange,
+    /// The test's description or name, like `Foo.bar does the thing`.
+    pub description: String,
+    /// The failure message, with common indentation removed.
+    pub message: String,
+}
+
+impl From<TestFailure> for GhcDiagnostic {
+    fn from(failure: TestFailure) -> Self {
+        // Indent the message like GHC does, so that the diagnostic is displayed the same way.
+        let mut message = format!("\n    {}\n", failure.description);
+        for line in failure.message.lines() {
+            if !line.is_empty() {
+                message.push_str("      ");
+                message.push_str(line);
+            }
+            message.push('\n');
+        }
+
+        Self {
+            severity: Severity::Error,
+            path: failure.path,
+            span: failure.span,
+            code: None,
+            flag: None,
+            message,
+            colored: None,
+        }
+    }
+}
+
+/// Counts of tests run by a test suite.
+///
+/// ```text
+/// 12 examples, 1 failure, 2 pending
+/// ```
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct TestSummary {
+    /// The number of tests.
+    pub total: usize,
+    /// The number of tests that failed.
+    pub failed: usize,
+    /// The number of tests that were skipped or pending.
+    pub pending: usize,
+}
+
+impl TestSummary {
+    /// The number of tests that passed.
+    pub fn passed(&self) -> usize {
+        self.total.saturating_sub(self.failed + self.pending)
+    }
+
+    /// Combine the counts from two test suites.
+    pub fn combine(self, other: Self) -> Self {
+        Self {
+            total: self.total + other.total,
+            failed: self.failed + other.failed,
+            pending: self.pending + other.pending,
+        }
+    }
+}
+
+/// Parse [`TestMessage`]s from lines of test suite output.
+pub fn parse_test_output(lines: &str) -> miette::Result<Vec<TestMessage>> {
+    let uncolored_lines = strip_ansi_escapes::strip_str(lines);
+
+    parse_test_output_inner
+        .parse(&uncolored_lines)
+        .map_err(|err| miette!("{err}"))
+}
+
+fn parse_test_output_inner(input: &mut &str) -> PResult<Vec<TestMessage>> {
+    repeat(
+        0..,
+        alt((
+            hspec_failure.map(Some),
+            hspec_summary.map(Some),
+            tasty_failure.map(Some),
+            tasty_summary.map(Some),
+            hunit_failure.map(Some),
+            hunit_summary.map(Some),
+            rest_of_line.map(|_| None),
+        )),
+    )
+    .fold(Vec::new, |mut messages, item| {
+        if let Some(item) = item {
+            messages.push(item);
+        }
+        messages
+    })
+    .parse_next(input)
+}
+
+/// Parse lines indented by at least `min_indent` spaces, without their trailing newlines.
+fn indented_lines<'i>(min_indent: usize) -> impl Parser<&'i str, Vec<&'i str>, ContextError> {
+    repeat(
+        0..,
+        preceded(peek(take_while(min_indent.., ' ')), until_newline),
+    )
+}
+
+/// Parse a source location printed by a test framework, like `test/Main.hs:12:5:` or
+/// `test/Main.hs:12`.
+///
+/// Locations without a column are given column 1.
+fn source_location(line: &str) -> Option<(Utf8PathBuf, PositionRange)> {
+    fn inner(input: &mut &str) -> PResult<(Utf8PathBuf, PositionRange)> {
+        let _ = space0.parse_next(input)?;
+        let path = path_colon.parse_next(input)?;
+        let span = alt((
+            super::ghc_message::parse_position_range,
+            (digit1.parse_to(), opt(":")).map(|(line, _)| PositionRange::new(line, 1, line, 1)),
+        ))
+        .parse_next(input)?;
+        let _ = (space0, eof).parse_next(input)?;
+        Ok((path.to_owned(), span))
+    }
+
+    inner.parse(line).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_parse_test_output() {
+        assert_eq!(
+            parse_test_output(indoc!(
+                "
+                Loaded GHCi configuration from .ghci
+                MyLib
+                  greets the world [✘]
+
+                Failures:
+
+                  test/MyLibSpec.hs:9:5:
+                  1) MyLib greets the world
+                       expected: \"Hello, world!\"
+                        but got: \"Hello!\"
+
+                  To rerun use: --match \"/MyLib/greets the world/\"
+
+                Randomized with seed 1234
+
+                Finished in 0.0010 seconds
+                1 example, 1 failure
+                "
+            ))
+            .unwrap(),
+            vec![
+                TestMessage::Failure(TestFailure {
+                    path: Some("test/MyLibSpec.hs".into()),
+                    span: PositionRange::new(9, 5, 9, 5),
+                    description: "MyLib greets the world".into(),
+                    message: "expected: \"Hello, world!\"\n but got: \"Hello!\"".into(),
+                }),
+                TestMessage::Summary(TestSummary {
+                    total: 1,
+                    failed: 1,
+                    pending: 0,
+                }),
+            ]
+        );
+
+        // Compiler output doesn't contain test results.
+        assert_eq!(
+            parse_test_output(indoc!(
+                "
+                [1 of 1] Compiling MyLib ( src/MyLib.hs, interpreted )
+                Ok, one module loaded.
+                "
+            ))
+            .unwrap(),
+            vec![]
+        );
+    }
+
+    #[test]
+    fn test_test_failure_into_diagnostic() {
+        let diagnostic = GhcDiagnostic::from(TestFailure {
+            path: Some("test/MyLibSpec.hs".into()),
+            span: PositionRange::new(9, 5, 9, 5),
+            description: "MyLib greets the world".into(),
+            message: "expected: 1\n but got: 2".into(),
+        });
+        assert_eq!(
+            diagnostic.to_string(),
+            indoc!(
+                "
+                test/MyLibSpec.hs:9:5: error:
+                    MyLib greets the world
+                      expected: 1
+                       but got: 2
+                "
+            )
+        );
+    }
+
+    #[test]
+    fn test_source_location() {
+        assert_eq!(
+            source_location("  test/Main.hs:12:5: "),
+            Some(("test/Main.hs".into(), PositionRange::new(12, 5, 12, 5)))
+        );
+        assert_eq!(
+            source_location("test/Main.hs:12:"),
+            Some(("test/Main.hs".into(), PositionRange::new(12, 1, 12, 1)))
+        );
+        assert_eq!(
+            source_location("test/Main.hs:12"),
+            Some(("test/Main.hs".into(), PositionRange::new(12, 1, 12, 1)))
+        );
+
+        // Negative cases.
+        assert_eq!(source_location("expected: 1"), None);
+        assert_eq!(source_location("test/Main.hs:12: oops"), None);
+    }
+
+    #[test]
+    fn test_test_summary_passed() {
+        assert_eq!(
+            TestSummary {
+                total: 10,
+                failed: 2,
+                pending: 1,
+            }
+            .passed(),
+            7
+        );
+    }
+}
@@ -0,0 +1,193 @@
+use winnow::ascii::digit1;
+use winnow::ascii::space0;
+use winnow::combinator::alt;
+use winnow::combinator::opt;
+use winnow::PResult;
+use winnow::Parser;
+
+use crate::ghci::parse::lines::rest_of_line;
+use crate::ghci::parse::lines::until_newline;
+
+use super::dedent;
+use super::indented_lines;
+use super::source_location;
+use super::TestFailure;
+use super::TestMessage;
+use super::TestSummary;
+
+/// Parse a `tasty` failure, like this:
+///
+/// ```text
+///     greets the world:  FAIL (0.01s)
+///       test/Main.hs:20:
+///       expected: "Hello, world!"
+///        but got: "Hello!"
+///       Use -p '/greets the world/' to rerun this test only.
+/// ```
+pub fn tasty_failure(input: &mut &str) -> PResult<TestMessage> {
+    let (indent, name) = (space0, until_newline)
+        .verify_map(|(indent, line): (&str, &str)| {
+            let (name, status) = line.rsplit_once(':')?;
+            let status = status.trim();
+            // `tasty-expected-failure` prints `FAIL (expected)` for tests which are expected to
+            // fail.
+            let is_failure = status == "FAIL"
+                || (status.starts_with("FAIL (") && !status.starts_with("FAIL (expected"));
+            is_failure.then(|| (indent.len(), name.trim_end()))
+        })
+        .parse_next(input)?;
+    let mut message = indented_lines(indent + 1).parse_next(input)?;
+
+    if message
+        .last()
+        .map(|line| line.trim_start().starts_with("Use -p "))
+        .unwrap_or(false)
+    {
+        message.pop();
+    }
+
+    let location = message.first().and_then(|line| source_location(line));
+    let (path, span) = match location {
+        Some((path, span)) => {
+            message.remove(0);
+            (Some(path), span)
+        }
+        None => (None, Default::default()),
+    };
+
+    Ok(TestMessage::Failure(TestFailure {
+        path,
+        span,
+        description: name.trim_start().to_owned(),
+        message: dedent(&message),
+    }))
+}
+
+/// Parse a `tasty` summary, like `All 12 tests passed (0.01s)` or `1 out of 12 tests failed
+/// (0.01s)`.
+pub fn tasty_summary(input: &mut &str) -> PResult<TestMessage> {
+    fn all_passed(input: &mut &str) -> PResult<TestSummary> {
+        let _ = "All ".parse_next(input)?;
+        let total = digit1.parse_to().parse_next(input)?;
+        let _ = (" test", opt("s"), " passed").parse_next(input)?;
+        Ok(TestSummary {
+            total,
+            failed: 0,
+            pending: 0,
+        })
+    }
+
+    fn some_failed(input: &mut &str) -> PResult<TestSummary> {
+        let failed = digit1.parse_to().parse_next(input)?;
+        let _ = " out of ".parse_next(input)?;
+        let total = digit1.parse_to().parse_next(input)?;
+        let _ = (" test", opt("s"), " failed").parse_next(input)?;
+        Ok(TestSummary {
+            total,
+            failed,
+            pending: 0,
+        })
+    }
+
+    let summary = alt((all_passed, some_failed)).parse_next(input)?;
+    // The time taken.
+    let _ = rest_of_line.parse_next(input)?;
+
+    Ok(TestMessage::Summary(summary))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use crate::ghci::parse::ghc_message::PositionRange;
+
+    #[test]
+    fn test_parse_tasty_failure() {
+        assert_eq!(
+            tasty_failure
+                .parse(indoc!(
+                    r#"
+                        greets the world:  FAIL (0.01s)
+                          test/Main.hs:20:
+                          expected: "Hello, world!"
+                           but got: "Hello!"
+                          Use -p '/greets the world/' to rerun this test only.
+                    "#
+                ))
+                .unwrap(),
+            TestMessage::Failure(TestFailure {
+                path: Some("test/Main.hs".into()),
+                span: PositionRange::new(20, 1, 20, 1),
+                description: "greets the world".into(),
+                message: "expected: \"Hello, world!\"\n but got: \"Hello!\"".into(),
+            })
+        );
+
+        // Without a location or timing.
+        assert_eq!(
+            tasty_failure
+                .parse(indoc!(
+                    "
+                    parses numbers: FAIL
+                      *** Failed! Falsified (after 3 tests):
+                      -1
+                    "
+                ))
+                .unwrap(),
+            TestMessage::Failure(TestFailure {
+                path: None,
+                span: Default::default(),
+                description: "parses numbers".into(),
+                message: "*** Failed! Falsified (after 3 tests):\n-1".into(),
+            })
+        );
+
+        // Negative cases.
+        assert!(tasty_failure.parse("    greets the world: OK\n").is_err());
+        assert!(tasty_failure
+            .parse("    greets the world: FAIL (expected)\n")
+            .is_err());
+        // The next test isn't part of the failure.
+        assert!(tasty_failure
+            .parse(indoc!(
+                "
+                  first: FAIL
+                    oops
+                  second: OK
+                "
+            ))
+            .is_err());
+    }
+
+    #[test]
+    fn test_parse_tasty_summary() {
+        assert_eq!(
+            tasty_summary
+                .parse("All 12 tests passed (0.01s)\n")
+                .unwrap(),
+            TestMessage::Summary(TestSummary {
+                total: 12,
+                failed: 0,
+                pending: 0,
+            })
+        );
+        assert_eq!(
+            tasty_summary
+                .parse("1 out of 12 tests failed (0.01s)\n")
+                .unwrap(),
+            TestMessage::Summary(TestSummary {
+                total: 12,
+                failed: 1,
+                pending: 0,
+            })
+        );
+
+        // Negative cases.
+        assert!(tasty_summary.parse("All good (3 modules)\n").is_err());
+        assert!(tasty_summary.parse("1 out of 2 modules failed\n").is_err());
+    }
+}
@@ -9,10 +9,14 @@ use nix::sys::signal;
 use nix::sys::signal::Signal;
 use nix::unistd::Pid;
 use tokio::sync::mpsc;
+use tokio::sync::watch;
 use tracing::instrument;
 
 use crate::shutdown::ShutdownHandle;
 
+use super::GhciState;
+use super::GhciStatus;
+
 pub struct GhciProcess {
     pub shutdown: ShutdownHandle,
     pub process_group_id: Pid,
@@ -20,6 +24,9 @@ pub struct GhciProcess {
     /// This is used for the graceful shutdown implementation and for routine `ghci` session
     /// restarts.
     pub restart_receiver: mpsc::Receiver<()>,
+    /// If `ghci` exits unexpectedly, the session's state is set to [`GhciState::Crashed`] so that
+    /// it can be restarted.
+    pub status: watch::Sender<GhciStatus>,
 }
 
 impl GhciProcess {
@@ -37,8 +44,11 @@ impl GhciProcess {
                 self.stop(wait).await?;
             }
             result = &mut wait => {
-                self.exited(result.into_diagnostic()?).await;
-                let _ = self.shutdown.request_shutdown();
+                let status = result.into_diagnostic()?;
+                self.exited(status).await;
+                tracing::error!("ghci exited unexpectedly: {status}");
+                self.status
+                    .send_modify(|status| status.state = GhciState::Crashed);
             }
         }
         Ok(())
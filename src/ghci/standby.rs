@@ -0,0 +1,134 @@
+//! A standby `ghci` session, loaded in the background so that restarts are nearly instant.
+
+use std::collections::BTreeSet;
+
+use tokio::sync::watch;
+use tokio::task::JoinHandle;
+use tracing::instrument;
+
+use crate::event_filter::FileEvent;
+use crate::shutdown::ShutdownHandle;
+
+use super::Ghci;
+use super::GhciOpts;
+use super::GhciState;
+use super::GhciStatus;
+use super::Mute;
+use super::ReloadActions;
+use super::TimingsFile;
+
+/// A `ghci` session started in the background, to switch to when the main session restarts.
+///
+/// The session's output is hidden until it's switched to. Dropping the standby stops the session.
+pub struct Standby {
+    /// The task starting the session.
+    task: JoinHandle<miette::Result<Ghci>>,
+    /// Where the session's [`super::GhciProcess`] reports crashes.
+    status: watch::Sender<GhciStatus>,
+    /// Files changed since the session started, which it needs to load once it's switched to.
+    events: BTreeSet<FileEvent>,
+}
+
+impl Standby {
+    /// Start a standby session in the background, with the main session's `opts`.
+    ///
+    /// Until it's switched to, the session doesn't write the module graph or timings files or
+    /// send lifecycle events, which would be mixed up with the main session's.
+    pub fn spawn(shutdown: ShutdownHandle, opts: &GhciOpts) -> Self {
+        let mute = Mute::default();
+        mute.set(true);
+        // Crashes are only reported to the main session once the standby is switched to.
+        let status = watch::Sender::new(Default::default());
+        let opts = GhciOpts {
+            module_graph_path: None,
+            timings_path: None,
+            stdout_writer: opts.stdout_writer.clone().with_mute(mute.clone()),
+            stderr_writer: opts.stderr_writer.clone().with_mute(mute.clone()),
+            mute,
+            status: status.clone(),
+            events: Default::default(),
+            ..opts.clone()
+        };
+        tracing::debug!("Starting standby ghci session");
+        let task = tokio::spawn(async move {
+            let mut ghci = Ghci::new(shutdown, opts).await?;
+            ghci.warm_up().await?;
+            tracing::debug!("Standby ghci session is ready");
+            Ok(ghci)
+        });
+        Self {
+            task,
+            status,
+            events: Default::default(),
+        }
+    }
+
+    /// Record file events handled by the main session, for the standby session to load.
+    pub fn record(&mut self, events: &BTreeSet<FileEvent>) {
+        self.events.extend(events.iter().cloned());
+    }
+
+    /// Wait for the standby session to finish starting, and prepare it to replace the main
+    /// session, which was started with `opts`.
+    ///
+    /// Returns the session and the actions needed to load the changes since it started, or
+    /// `None` if the session failed to start or the changes require a restart.
+    #[instrument(skip_all, level = "debug")]
+    pub async fn promote(
+        mut self,
+        opts: &GhciOpts,
+    ) -> miette::Result<Option<(Ghci, ReloadActions)>> {
+        let mut ghci = match (&mut self.task).await {
+            Ok(Ok(ghci)) => ghci,
+            Ok(Err(err)) => {
+                tracing::warn!("Standby ghci session failed to start: {err:?}");
+                return Ok(None);
+            }
+            Err(err) => {
+                tracing::debug!("Standby ghci session task failed: {err}");
+                return Ok(None);
+            }
+        };
+
+        let actions = ghci
+            .get_reload_actions(std::mem::take(&mut self.events))
+            .await?;
+        if actions.needs_restart() || !actions.needs_unadd.is_empty() {
+            tracing::debug!(?actions, "Standby ghci session is out of date");
+            return Ok(None);
+        }
+
+        // Keep the session's writers, which are silenced by its `mute`, and restore the outputs
+        // the session was started without.
+        ghci.opts = GhciOpts {
+            stdout_writer: ghci.opts.stdout_writer.clone(),
+            stderr_writer: ghci.opts.stderr_writer.clone(),
+            mute: ghci.opts.mute.clone(),
+            ..opts.clone()
+        };
+        ghci.stdout.set_events(opts.events.clone());
+        ghci.timings_file = TimingsFile::new(opts.timings_path.clone(), opts.timings_format);
+
+        // Forward crashes to the main session's status, so that the session is restarted.
+        let mut process_status = self.status.subscribe();
+        let status = opts.status.clone();
+        tokio::spawn(async move {
+            if process_status
+                .wait_for(|process_status| process_status.state == GhciState::Crashed)
+                .await
+                .is_ok()
+            {
+                status.send_modify(|status| status.state = GhciState::Crashed);
+            }
+        });
+
+        Ok(Some((ghci, actions)))
+    }
+}
+
+impl Drop for Standby {
+    fn drop(&mut self) {
+        // Dropping the session stops `ghci`, even if it's still starting up.
+        self.task.abort();
+    }
+}
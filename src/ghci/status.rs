@@ -0,0 +1,73 @@
+//! The externally-visible status of a [`Ghci`] session.
+//!
+//! [`Ghci`]: super::Ghci
+
+use serde::Serialize;
+
+use super::parse::CompilationResult;
+use super::parse::Severity;
+use super::parse::TestSummary;
+use super::CompilationLog;
+
+/// What a `ghci` session is currently doing.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum GhciState {
+    /// The session is starting up and loading modules for the first time.
+    #[default]
+    Starting,
+    /// Modules are being reloaded or added.
+    Reloading,
+    /// The session is being restarted.
+    Restarting,
+    /// Test hooks are running.
+    Testing,
+    /// The `--run` command is running.
+    Running,
+    /// The session is waiting for changes.
+    Idle,
+    /// `ghci` exited unexpectedly or failed to start, and is waiting to be restarted.
+    Crashed,
+}
+
+/// A snapshot of a `ghci` session's state and its last compilation result.
+///
+/// This is published by the [`Ghci`] session whenever it starts or finishes work, and read by the
+/// control socket to answer `status` requests.
+///
+/// [`Ghci`]: super::Ghci
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct GhciStatus {
+    /// What the session is currently doing.
+    pub state: GhciState,
+    /// The result of the last compilation, if any has finished.
+    pub result: Option<CompilationResult>,
+    /// The number of modules loaded by the last compilation.
+    pub modules_loaded: Option<usize>,
+    /// The number of errors from the last compilation.
+    pub errors: usize,
+    /// The number of warnings from the last compilation.
+    pub warnings: usize,
+    /// The results of the last test run, if the test suite's output was recognized.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tests: Option<TestSummary>,
+}
+
+impl GhciStatus {
+    /// Update the compilation result fields from a finished compilation.
+    pub fn set_compilation(&mut self, log: &CompilationLog) {
+        self.result = log.result();
+        self.modules_loaded = log.summary.map(|summary| summary.modules_loaded);
+        self.errors = log
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Error)
+            .count();
+        self.warnings = log
+            .diagnostics
+            .iter()
+            .filter(|diagnostic| diagnostic.severity == Severity::Warning)
+            .count();
+        self.tests = log.tests;
+    }
+}
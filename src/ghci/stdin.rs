@@ -1,4 +1,3 @@
-use camino::Utf8Path;
 use miette::Context;
 use miette::IntoDiagnostic;
 use tokio::io::AsyncWriteExt;
@@ -8,9 +7,11 @@ use tracing::instrument;
 
 use crate::incremental_reader::FindAt;
 
+use super::parse::Module;
 use super::parse::ModuleSet;
 use super::parse::ShowPaths;
 use super::stderr::StderrEvent;
+use super::stdout::ParseOutput;
 use super::CompilationLog;
 use super::GhciCommand;
 use super::PROMPT;
@@ -35,13 +36,14 @@ impl GhciStdin {
         stdout: &mut GhciStdout,
         line: &str,
         find: FindAt,
+        parse: ParseOutput,
         log: &mut CompilationLog,
     ) -> miette::Result<()> {
         self.stdin
             .write_all(line.as_bytes())
             .await
             .into_diagnostic()?;
-        stdout.prompt(find, log).await
+        stdout.prompt(find, parse, log).await
     }
 
     /// Write a line on `stdin` and wait for a prompt on stdout.
@@ -53,11 +55,11 @@ impl GhciStdin {
         line: &str,
         log: &mut CompilationLog,
     ) -> miette::Result<()> {
-        self.write_line_with_prompt_at(stdout, line, FindAt::LineStart, log)
+        self.write_line_with_prompt_at(stdout, line, FindAt::LineStart, ParseOutput::Compiler, log)
             .await
     }
 
-    /// Run a [`GhciCommand`].
+    /// Run a [`GhciCommand`], parsing its output as directed by `parse`.
     ///
     /// The command may be multiple lines.
     #[instrument(skip(self, stdout), level = "debug")]
@@ -65,10 +67,18 @@ impl GhciStdin {
         &mut self,
         stdout: &mut GhciStdout,
         command: &GhciCommand,
+        parse: ParseOutput,
         log: &mut CompilationLog,
     ) -> miette::Result<()> {
         for line in command.lines() {
-            self.write_line(stdout, &format!("{line}\n"), log).await?;
+            self.write_line_with_prompt_at(
+                stdout,
+                &format!("{line}\n"),
+                FindAt::LineStart,
+                parse,
+                log,
+            )
+            .await?;
         }
 
         Ok(())
@@ -86,6 +96,7 @@ impl GhciStdin {
             stdout,
             &format!(":set prompt {PROMPT}\n"),
             FindAt::Anywhere,
+            ParseOutput::Compiler,
             log,
         )
         .await?;
@@ -104,10 +115,10 @@ impl GhciStdin {
     }
 
     #[instrument(skip(self, stdout), level = "debug")]
-    pub async fn add_module(
+    pub async fn add_modules(
         &mut self,
         stdout: &mut GhciStdout,
-        path: &Utf8Path,
+        modules: &[String],
         log: &mut CompilationLog,
     ) -> miette::Result<()> {
         // We use `:add` because `:load` unloads all previously loaded modules:
@@ -117,7 +128,22 @@ impl GhciStdin {
         // > to unload all the currently loaded modules and bindings.
         //
         // https://downloads.haskell.org/ghc/latest/docs/users_guide/ghci.html#ghci-cmd-:load
-        self.write_line(stdout, &format!(":add {path}\n"), log)
+        //
+        // Like `:unadd`, `:add` takes multiple modules and compiles once after adding all of them.
+        self.write_line(stdout, &format!(":add {}\n", modules.join(" ")), log)
+            .await
+    }
+
+    #[instrument(skip(self, stdout), level = "debug")]
+    pub async fn unadd_modules(
+        &mut self,
+        stdout: &mut GhciStdout,
+        modules: &[String],
+        log: &mut CompilationLog,
+    ) -> miette::Result<()> {
+        // `:unadd` takes multiple modules and reloads once after removing all of them, which
+        // saves a lot of time in large projects.
+        self.write_line(stdout, &format!(":unadd {}\n", modules.join(" ")), log)
             .await
     }
 
@@ -145,12 +171,22 @@ impl GhciStdin {
         self.write_line(stdout, &format!(":module + *{module_name}\n"), log)
             .await?;
 
-        self.run_command(stdout, command, log).await?;
-
-        self.write_line(stdout, &format!(":module - *{module_name}\n"), log)
+        self.run_command(stdout, command, ParseOutput::Compiler, log)
             .await?;
 
-        Ok(())
+        self.remove_from_context(stdout, module_name, log).await
+    }
+
+    /// Remove a module's top-level scope from the context, after [`Self::eval`] adds it.
+    #[instrument(skip(self, stdout), level = "debug")]
+    pub async fn remove_from_context(
+        &mut self,
+        stdout: &mut GhciStdout,
+        module_name: &str,
+        log: &mut CompilationLog,
+    ) -> miette::Result<()> {
+        self.write_line(stdout, &format!(":module - *{module_name}\n"), log)
+            .await
     }
 
     #[instrument(skip(self, stdout), level = "debug")]
@@ -177,6 +213,16 @@ impl GhciStdin {
         stdout.show_targets(show_paths).await
     }
 
+    #[instrument(skip_all, level = "debug")]
+    pub async fn show_modules(&mut self, stdout: &mut GhciStdout) -> miette::Result<Vec<Module>> {
+        self.stdin
+            .write_all(b":show modules\n")
+            .await
+            .into_diagnostic()?;
+
+        stdout.show_modules().await
+    }
+
     #[instrument(skip(self, stdout), level = "debug")]
     pub async fn quit(&mut self, stdout: &mut GhciStdout) -> miette::Result<()> {
         self.stdin
@@ -1,31 +1,58 @@
 use aho_corasick::AhoCorasick;
 use miette::Context;
 use miette::IntoDiagnostic;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+use std::sync::Arc;
+use std::time::Instant;
 use tokio::process::ChildStdout;
 use tokio::sync::mpsc;
 use tokio::sync::oneshot;
 use tracing::instrument;
 
 use crate::aho_corasick::AhoCorasickExt;
+use crate::event_stream::EventSender;
+use crate::event_stream::StreamEvent;
 use crate::incremental_reader::FindAt;
 use crate::incremental_reader::IncrementalReader;
 use crate::incremental_reader::ReadOpts;
 use crate::incremental_reader::WriteBehavior;
 
 use super::parse::parse_ghc_messages;
+use super::parse::parse_show_modules;
 use super::parse::parse_show_paths;
 use super::parse::parse_show_targets;
+use super::parse::parse_test_output;
+use super::parse::GhcMessage;
+use super::parse::Module;
 use super::parse::ModuleSet;
 use super::parse::ShowPaths;
 use super::stderr::StderrEvent;
+use super::timings::module_timings;
 use super::writer::GhciWriter;
 use super::CompilationLog;
 
+/// What to parse from `ghci`'s output before a prompt.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ParseOutput {
+    /// Compiler messages, from loading modules or running commands.
+    Compiler,
+    /// Compiler messages and test suite results, from running the test hooks.
+    Tests,
+    /// Nothing, for output which is only shown, like from commands typed by the user.
+    Nothing,
+}
+
 pub struct GhciStdout {
     /// Reader for parsing and forwarding the underlying stdout stream.
     pub reader: IncrementalReader<ChildStdout, GhciWriter>,
     /// Channel for communicating with the stderr task.
     pub stderr_sender: mpsc::Sender<StderrEvent>,
+    /// Where to send lifecycle events for parsed compiler messages.
+    pub events: EventSender,
+    /// Whether the output being read is parsed for compiler messages. This is shared with the
+    /// callback which sends `Compiling` events as lines are read.
+    pub parsing: Arc<AtomicBool>,
     /// Prompt patterns to match. Constructing these `AhoCorasick` automatons is costly so we store
     /// them in the task state.
     pub prompt_patterns: AhoCorasick,
@@ -34,8 +61,35 @@ pub struct GhciStdout {
 }
 
 impl GhciStdout {
+    /// Set where lifecycle events for parsed compiler messages are sent.
+    ///
+    /// `Compiling` events are sent as soon as their lines are read, rather than once `ghci`
+    /// prints a prompt, so that consumers can follow the progress of long compilations.
+    pub fn set_events(&mut self, events: EventSender) {
+        let sender = events.clone();
+        let parsing = self.parsing.clone();
+        self.reader.on_timestamped_line(move |line| {
+            if !parsing.load(Ordering::SeqCst) {
+                return;
+            }
+            if let Some(event) = compiling_event(line) {
+                sender.send(event);
+            }
+        });
+        self.events = events;
+    }
+
     #[instrument(skip_all, level = "debug")]
-    async fn parse_into_log(&self, data: &str, log: &mut CompilationLog) -> miette::Result<()> {
+    async fn parse_into_log(
+        &self,
+        data: &str,
+        parse: ParseOutput,
+        log: &mut CompilationLog,
+    ) -> miette::Result<()> {
+        if parse == ParseOutput::Nothing {
+            return Ok(());
+        }
+
         // Parse GHCi output into compiler messages.
         //
         // These include diagnostics, which modules were compiled, and a compilation summary.
@@ -47,11 +101,47 @@ impl GhciStdout {
                 .await;
             receiver.await.into_diagnostic()?
         };
-        log.extend(parse_ghc_messages(data).wrap_err("Failed to parse compiler output")?);
-        log.extend(parse_ghc_messages(&stderr_data).wrap_err("Failed to parse compiler output")?);
+        let mut messages = parse_ghc_messages(data).wrap_err("Failed to parse compiler output")?;
+        messages
+            .extend(parse_ghc_messages(&stderr_data).wrap_err("Failed to parse compiler output")?);
+        // Diagnostics are printed to `stderr` and the summary is printed to `stdout`; put the
+        // summary last so event consumers see it after the diagnostics it summarizes.
+        messages.sort_by_key(|message| matches!(message, GhcMessage::Summary(_)));
+        for event in messages
+            .iter()
+            // `Compiling` events were sent as the lines were read.
+            .filter(|message| !matches!(message, GhcMessage::Compiling(_)))
+            .filter_map(StreamEvent::from_ghc_message)
+        {
+            self.events.send(event);
+        }
+        log.extend(messages);
+
+        // Test suites run with `--test-ghci` print their results here too. Other output, like
+        // from programs run in `ghci`, can look like test results, so we only check for them
+        // when running tests.
+        if parse != ParseOutput::Tests {
+            return Ok(());
+        }
+        let mut test_messages =
+            parse_test_output(data).wrap_err("Failed to parse test suite output")?;
+        test_messages
+            .extend(parse_test_output(&stderr_data).wrap_err("Failed to parse test suite output")?);
+        for message in &test_messages {
+            self.events.send(StreamEvent::from_test_message(message));
+        }
+        log.extend(test_messages);
         Ok(())
     }
 
+    /// Add timings for the modules compiled since the last time this was called to the `log`.
+    fn take_timings(&mut self, log: &mut CompilationLog) {
+        log.timings.extend(module_timings(
+            self.reader.take_timestamped_lines(),
+            Instant::now(),
+        ));
+    }
+
     #[instrument(skip_all, name = "stdout_initialize", level = "debug")]
     pub async fn initialize(&mut self, log: &mut CompilationLog) -> miette::Result<()> {
         // Wait for `ghci` to start up. This may involve compiling a bunch of stuff.
@@ -70,18 +160,27 @@ impl GhciStdout {
             })
             .await?;
         tracing::debug!(data, "ghci started, saw version marker");
+        self.take_timings(log);
 
-        self.parse_into_log(&data, log).await?;
+        self.parse_into_log(&data, ParseOutput::Compiler, log)
+            .await?;
 
         Ok(())
     }
 
     #[instrument(skip_all, level = "debug")]
-    pub async fn prompt(&mut self, find: FindAt, log: &mut CompilationLog) -> miette::Result<()> {
+    pub async fn prompt(
+        &mut self,
+        find: FindAt,
+        parse: ParseOutput,
+        log: &mut CompilationLog,
+    ) -> miette::Result<()> {
         self.stderr_sender
             .send(StderrEvent::ClearBuffer)
             .await
             .into_diagnostic()?;
+        self.parsing
+            .store(parse != ParseOutput::Nothing, Ordering::SeqCst);
 
         let data = self
             .reader
@@ -93,8 +192,9 @@ impl GhciStdout {
             })
             .await?;
         tracing::debug!(bytes = data.len(), "Got data from ghci");
+        self.take_timings(log);
 
-        self.parse_into_log(&data, log).await?;
+        self.parse_into_log(&data, parse, log).await?;
         Ok(())
     }
 
@@ -128,6 +228,20 @@ impl GhciStdout {
         ModuleSet::from_paths(paths, &search_paths.cwd)
     }
 
+    #[instrument(skip_all, level = "debug")]
+    pub async fn show_modules(&mut self) -> miette::Result<Vec<Module>> {
+        let lines = self
+            .reader
+            .read_until(&mut ReadOpts {
+                end_marker: &self.prompt_patterns,
+                find: FindAt::LineStart,
+                writing: WriteBehavior::Hide,
+                buffer: &mut self.buffer,
+            })
+            .await?;
+        parse_show_modules(&lines).wrap_err("Failed to parse `:show modules` output")
+    }
+
     #[instrument(skip_all, level = "debug")]
     pub async fn quit(&mut self) -> miette::Result<()> {
         let leaving_ghci = AhoCorasick::from_anchored_patterns(["Leaving GHCi."]);
@@ -144,3 +258,13 @@ impl GhciStdout {
         Ok(())
     }
 }
+
+/// Get the `compiling` event for a `[1 of 3] Compiling Foo ( ... )` line, if it is one.
+fn compiling_event(line: &str) -> Option<StreamEvent> {
+    // The reader strips the trailing newline, but the parser needs it.
+    let messages = parse_ghc_messages(&format!("{line}\n")).ok()?;
+    messages.iter().find_map(|message| match message {
+        GhcMessage::Compiling(_) => StreamEvent::from_ghc_message(message),
+        _ => None,
+    })
+}
@@ -0,0 +1,147 @@
+//! Deciding when to restart `ghci` after it crashes.
+
+use std::collections::VecDeque;
+use std::time::Duration;
+use std::time::Instant;
+
+use backoff::backoff::Backoff;
+use backoff::ExponentialBackoff;
+use backoff::ExponentialBackoffBuilder;
+
+/// If `ghci` crashes this many times within [`CRASH_LOOP_WINDOW`], it's in a crash loop.
+pub const CRASH_LOOP_CRASHES: usize = 5;
+
+/// See [`CRASH_LOOP_CRASHES`].
+pub const CRASH_LOOP_WINDOW: Duration = Duration::from_secs(60);
+
+/// Sessions which run for at least this long before crashing reset the backoff delay.
+const HEALTHY_UPTIME: Duration = Duration::from_secs(30);
+
+/// When to restart a crashed `ghci` session.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Restart {
+    /// Restart after a delay, or sooner if files change.
+    After(Duration),
+    /// `ghci` is crashing repeatedly; wait for files to change before restarting it.
+    OnChange,
+}
+
+/// Tracks crashes of the `ghci` session to decide when to restart it.
+///
+/// Crashes are followed by restarts with an exponential backoff, until `ghci` crashes
+/// [`CRASH_LOOP_CRASHES`] times within [`CRASH_LOOP_WINDOW`].
+#[derive(Debug)]
+pub struct Supervisor {
+    backoff: ExponentialBackoff,
+    /// When recent crashes happened, oldest first.
+    crashes: VecDeque<Instant>,
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self {
+            backoff: ExponentialBackoffBuilder::new()
+                .with_initial_interval(Duration::from_millis(500))
+                .with_randomization_factor(0.0)
+                .with_multiplier(2.0)
+                .with_max_interval(Duration::from_secs(30))
+                .with_max_elapsed_time(None)
+                .build(),
+            crashes: VecDeque::new(),
+        }
+    }
+}
+
+impl Supervisor {
+    /// Record that a session which started at `started` crashed at `now`, and decide when to
+    /// restart it.
+    pub fn crashed(&mut self, started: Instant, now: Instant) -> Restart {
+        if now.saturating_duration_since(started) >= HEALTHY_UPTIME {
+            self.backoff.reset();
+        }
+
+        self.crashes.push_back(now);
+        while let Some(crash) = self.crashes.front() {
+            if now.saturating_duration_since(*crash) > CRASH_LOOP_WINDOW {
+                self.crashes.pop_front();
+            } else {
+                break;
+            }
+        }
+
+        if self.crashes.len() >= CRASH_LOOP_CRASHES {
+            self.reset();
+            return Restart::OnChange;
+        }
+
+        match self.backoff.next_backoff() {
+            Some(delay) => Restart::After(delay),
+            None => Restart::OnChange,
+        }
+    }
+
+    /// Forget about previous crashes, like when files change and `ghci` is started fresh.
+    pub fn reset(&mut self) {
+        self.backoff.reset();
+        self.crashes.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_supervisor_backoff() {
+        let mut supervisor = Supervisor::default();
+        let start = Instant::now();
+        let second = Duration::from_secs(1);
+
+        assert_eq!(
+            supervisor.crashed(start, start + second),
+            Restart::After(Duration::from_millis(500))
+        );
+        assert_eq!(
+            supervisor.crashed(start, start + 2 * second),
+            Restart::After(Duration::from_secs(1))
+        );
+        // A session which stayed up for a while resets the delay.
+        assert_eq!(
+            supervisor.crashed(start, start + 40 * second),
+            Restart::After(Duration::from_millis(500))
+        );
+    }
+
+    #[test]
+    fn test_supervisor_crash_loop() {
+        let mut supervisor = Supervisor::default();
+        let start = Instant::now();
+        let second = Duration::from_secs(1);
+
+        for i in 1..CRASH_LOOP_CRASHES {
+            let now = start + 10 * second * i as u32;
+            assert!(matches!(supervisor.crashed(now, now), Restart::After(_)));
+        }
+        let now = start + 50 * second;
+        assert_eq!(supervisor.crashed(now, now), Restart::OnChange);
+
+        // After a crash loop, crashes are counted from scratch.
+        assert_eq!(
+            supervisor.crashed(now, now),
+            Restart::After(Duration::from_millis(500))
+        );
+    }
+
+    #[test]
+    fn test_supervisor_old_crashes_forgotten() {
+        let mut supervisor = Supervisor::default();
+        let start = Instant::now();
+
+        for i in 0..2 * CRASH_LOOP_CRASHES {
+            let now = start + Duration::from_secs(20) * i as u32;
+            assert!(matches!(supervisor.crashed(now, now), Restart::After(_)));
+        }
+    }
+}
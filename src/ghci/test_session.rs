@@ -0,0 +1,117 @@
+//! A separate `ghci` session for running tests, so that reloads never wait for tests to finish.
+
+use tokio::sync::mpsc;
+use tokio::sync::watch;
+use tracing::instrument;
+
+use crate::cli::Opts;
+use crate::hooks::LifecycleEvent;
+use crate::shutdown::ShutdownHandle;
+
+use super::manager::run_ghci;
+use super::manager::GhciEvent;
+use super::GhciOpts;
+use super::Mute;
+use super::TerminalDisplay;
+
+/// Options for [`run_test_session`]. This is like a lower-effort builder interface, mostly
+/// provided because Rust tragically lacks named arguments.
+pub struct TestSessionOpts {
+    /// Options for the test session's `ghci`.
+    pub ghci: GhciOpts,
+    /// Events from the main session: reloads after successful compilations, and tests requested
+    /// outside of reloads.
+    pub receiver: mpsc::Receiver<GhciEvent>,
+}
+
+impl TestSessionOpts {
+    /// Construct options for [`run_test_session`] from parsed command-line interface arguments as
+    /// [`Opts`], and connect the main session's `ghci_opts` to the test session.
+    ///
+    /// Test results are sent back to the main session on `main_session`, so that they're reported
+    /// with its compilation results.
+    ///
+    /// Returns `None` if tests don't run in a separate session.
+    pub fn from_cli(
+        opts: &Opts,
+        ghci_opts: &mut GhciOpts,
+        main_session: mpsc::Sender<GhciEvent>,
+    ) -> Option<Self> {
+        if !opts.test_session {
+            return None;
+        }
+        if ghci_opts
+            .hooks
+            .select(LifecycleEvent::Test)
+            .next()
+            .is_none()
+        {
+            tracing::warn!(
+                "`--test-session` has no effect without `--test-ghci` or `--test-shell`"
+            );
+            return None;
+        }
+
+        let (sender, receiver) = mpsc::channel(32);
+        ghci_opts.test_session = Some(sender);
+
+        // The main session shows compiler output and diagnostics, so the test session hides them
+        // and only shows the test output. Test results are published by the main session.
+        let mute = Mute::default();
+        let ghci = GhciOpts {
+            error_path: None,
+            module_graph_path: None,
+            timings_path: None,
+            slowest_modules: 0,
+            enable_eval: false,
+            hooks: ghci_opts.hooks.for_test_session(),
+            // Tests are always interrupted when newer changes compile.
+            no_interrupt_reloads: false,
+            max_memory: None,
+            restart_every: None,
+            run: None,
+            test_session: None,
+            main_session: Some(main_session),
+            quiet: true,
+            standby: false,
+            stdout_writer: ghci_opts.stdout_writer.clone().with_mute(mute.clone()),
+            stderr_writer: ghci_opts.stderr_writer.clone().with_mute(mute.clone()),
+            clear: false,
+            display: TerminalDisplay::default(),
+            mute,
+            status: watch::Sender::new(Default::default()),
+            compilation_log: watch::Sender::new(Default::default()),
+            events: Default::default(),
+            ..ghci_opts.clone()
+        };
+
+        Some(Self { ghci, receiver })
+    }
+}
+
+/// Run the test session.
+///
+/// The session is started once the main session first compiles successfully. After that, it's
+/// reloaded with the changes from each of the main session's successful compilations, which runs
+/// the tests. Like the main session, reloads (and the tests they run) are interrupted by newer
+/// changes, and the session is restarted if it crashes.
+#[instrument(level = "debug", skip_all)]
+pub async fn run_test_session(
+    mut handle: ShutdownHandle,
+    mut opts: TestSessionOpts,
+) -> miette::Result<()> {
+    // A new session loads every module, so it doesn't need the changes in the first event.
+    tokio::select! {
+        _ = handle.on_shutdown_requested() => {
+            return Ok(());
+        }
+        event = opts.receiver.recv() => {
+            if event.is_none() {
+                return Ok(());
+            }
+        }
+    }
+
+    tracing::info!("Starting ghci test session");
+    run_ghci(handle, opts.ghci, opts.receiver).await
+}
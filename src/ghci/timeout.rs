@@ -0,0 +1,118 @@
+//! Timeouts for `ghci` commands which never return to a prompt.
+
+use std::future::Future;
+use std::time::Duration;
+
+use camino::Utf8PathBuf;
+
+use crate::cli::TimeoutOpts;
+
+use super::parse::GhcDiagnostic;
+use super::parse::PositionRange;
+use super::parse::Severity;
+
+/// A phase of work in the `ghci` session which can time out.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Phase {
+    /// Compiling modules, with `:reload`, `:add`, or `:unadd`.
+    Compile,
+    /// Running an eval command from a comment.
+    Eval {
+        /// The file the eval command is in.
+        path: Utf8PathBuf,
+        /// The line the eval command is on.
+        line: usize,
+    },
+    /// Running a `ghci` test command.
+    Test,
+}
+
+impl Phase {
+    /// Get the timeout for this phase, if any.
+    pub fn timeout(&self, opts: &TimeoutOpts) -> Option<Duration> {
+        match self {
+            Phase::Compile => opts.compile_timeout,
+            Phase::Eval { .. } => opts.eval_timeout,
+            Phase::Test => opts.test_timeout,
+        }
+    }
+
+    /// Construct an error diagnostic reporting that this phase didn't finish within `timeout`.
+    pub fn diagnostic(&self, timeout: Duration) -> GhcDiagnostic {
+        let (what, option) = match self {
+            Phase::Compile => ("Compilation", "--compile-timeout"),
+            Phase::Eval { .. } => ("Eval command", "--eval-timeout"),
+            Phase::Test => ("Test command", "--test-timeout"),
+        };
+        let (path, span) = match self {
+            Phase::Eval { path, line } => {
+                (Some(path.clone()), PositionRange::new(*line, 1, *line, 1))
+            }
+            _ => (None, PositionRange::default()),
+        };
+        GhcDiagnostic {
+            severity: Severity::Error,
+            path,
+            span,
+            code: None,
+            flag: None,
+            message: format!(
+                "{what} didn't finish within {} (`{option}`)",
+                humantime::format_duration(timeout)
+            ),
+            colored: None,
+        }
+    }
+}
+
+/// Run a `future` to completion, or until the `timeout` elapses.
+///
+/// Returns `None` if the `future` timed out.
+pub async fn with_timeout<T>(
+    timeout: Option<Duration>,
+    future: impl Future<Output = T>,
+) -> Option<T> {
+    match timeout {
+        Some(timeout) => tokio::time::timeout(timeout, future).await.ok(),
+        None => Some(future.await),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_phase_diagnostic() {
+        assert_eq!(
+            Phase::Compile
+                .diagnostic(Duration::from_secs(90))
+                .to_string(),
+            "<no location info>: error: Compilation didn't finish within 1m 30s (`--compile-timeout`)"
+        );
+        assert_eq!(
+            Phase::Eval {
+                path: "src/MyLib.hs".into(),
+                line: 12,
+            }
+            .diagnostic(Duration::from_millis(500))
+            .to_string(),
+            "src/MyLib.hs:12:1: error: Eval command didn't finish within 500ms (`--eval-timeout`)"
+        );
+    }
+
+    #[tokio::test]
+    async fn test_with_timeout() {
+        assert_eq!(with_timeout(None, async { 1 }).await, Some(1));
+        assert_eq!(
+            with_timeout(Some(Duration::from_secs(10)), async { 1 }).await,
+            Some(1)
+        );
+        assert_eq!(
+            with_timeout(Some(Duration::from_millis(1)), std::future::pending::<()>()).await,
+            None
+        );
+    }
+}
@@ -0,0 +1,289 @@
+//! Per-module compilation timings.
+
+use std::cmp::Reverse;
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::time::Duration;
+use std::time::Instant;
+
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use miette::Context;
+use miette::IntoDiagnostic;
+use serde::Serialize;
+use tracing::instrument;
+
+use super::error_log::write_atomic;
+use super::parse::parse_ghc_messages;
+use super::parse::GhcMessage;
+
+/// The format to write the timings file in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, clap::ValueEnum)]
+pub enum TimingsFormat {
+    /// Comma-separated values with a header row.
+    #[default]
+    Csv,
+    /// A JSON object with a list of modules.
+    Json,
+}
+
+/// How long a module took to compile.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ModuleTiming {
+    /// The module's name.
+    pub name: String,
+    /// The module's source path.
+    pub path: Utf8PathBuf,
+    /// How long the module took to compile.
+    pub duration: Duration,
+}
+
+impl Display for ModuleTiming {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{} ({:.2?})", self.name, self.duration)
+    }
+}
+
+/// Compute module compilation timings from timestamped `[1 of 3] Compiling Foo ( ... )` lines.
+///
+/// `ghci` doesn't report how long modules take to compile, so each module is assumed to compile
+/// from when its `Compiling` line is printed until the next one is printed, or until `end` for
+/// the last module.
+pub fn module_timings(lines: Vec<(Instant, String)>, end: Instant) -> Vec<ModuleTiming> {
+    let modules = lines
+        .into_iter()
+        // The reader strips the trailing newline, but the parser needs it.
+        .filter_map(
+            |(instant, line)| match parse_ghc_messages(&format!("{line}\n")) {
+                Ok(messages) => messages.into_iter().find_map(|message| match message {
+                    GhcMessage::Compiling(module) => Some((instant, module)),
+                    _ => None,
+                }),
+                Err(err) => {
+                    tracing::debug!(line, "Failed to parse `Compiling` line: {err}");
+                    None
+                }
+            },
+        )
+        .collect::<Vec<_>>();
+
+    let ends = modules
+        .iter()
+        .skip(1)
+        .map(|(instant, _)| *instant)
+        .chain(std::iter::once(end));
+
+    modules
+        .iter()
+        .zip(ends)
+        .map(|((start, module), end)| ModuleTiming {
+            name: module.name.clone(),
+            path: module.path.clone(),
+            duration: end.saturating_duration_since(*start),
+        })
+        .collect()
+}
+
+/// Get the `n` slowest modules from `timings`, slowest first.
+pub fn slowest(timings: &[ModuleTiming], n: usize) -> Vec<&ModuleTiming> {
+    let mut timings = timings.iter().collect::<Vec<_>>();
+    timings.sort_by_key(|timing| Reverse(timing.duration));
+    timings.truncate(n);
+    timings
+}
+
+/// Timings file writer.
+///
+/// This keeps the most recent compilation time for each module in the session, so that the file
+/// lists every module even if a reload only recompiles a few of them.
+pub struct TimingsFile {
+    path: Option<Utf8PathBuf>,
+    format: TimingsFormat,
+    modules: BTreeMap<String, ModuleTiming>,
+}
+
+impl TimingsFile {
+    /// Construct a new timings file writer for the given path.
+    pub fn new(path: Option<Utf8PathBuf>, format: TimingsFormat) -> Self {
+        Self {
+            path,
+            format,
+            modules: Default::default(),
+        }
+    }
+
+    /// Record the given timings and write the timings file, if any.
+    #[instrument(skip_all, name = "timings_write", level = "debug")]
+    pub async fn write(&mut self, timings: &[ModuleTiming]) -> miette::Result<()> {
+        let path = match &self.path {
+            Some(path) => path,
+            None => {
+                return Ok(());
+            }
+        };
+
+        if timings.is_empty() {
+            tracing::debug!("No modules compiled, not writing timings");
+            return Ok(());
+        }
+
+        for timing in timings {
+            self.modules.insert(timing.name.clone(), timing.clone());
+        }
+
+        let mut modules = self.modules.values().collect::<Vec<_>>();
+        modules.sort_by_key(|timing| Reverse(timing.duration));
+
+        let contents = match self.format {
+            TimingsFormat::Csv => csv_contents(&modules),
+            TimingsFormat::Json => json_contents(&modules)?,
+        };
+
+        write_atomic(path, contents.as_bytes())
+            .await
+            .wrap_err_with(|| format!("Failed to write timings to {path}"))
+    }
+}
+
+fn csv_contents(modules: &[&ModuleTiming]) -> String {
+    let mut contents = String::from("module,path,duration_ms\n");
+    for timing in modules {
+        contents.push_str(&format!(
+            "{},{},{}\n",
+            csv_field(&timing.name),
+            csv_field(timing.path.as_str()),
+            timing.duration.as_millis()
+        ));
+    }
+    contents
+}
+
+/// Quote a CSV field if needed.
+fn csv_field(field: &str) -> String {
+    if field.contains([',', '"', '\n']) {
+        format!("\"{}\"", field.replace('"', "\"\""))
+    } else {
+        field.to_owned()
+    }
+}
+
+fn json_contents(modules: &[&ModuleTiming]) -> miette::Result<String> {
+    #[derive(Serialize)]
+    struct JsonTimings<'a> {
+        modules: Vec<JsonModuleTiming<'a>>,
+    }
+
+    #[derive(Serialize)]
+    struct JsonModuleTiming<'a> {
+        module: &'a str,
+        path: &'a Utf8Path,
+        duration_ms: u128,
+    }
+
+    let mut contents = serde_json::to_string_pretty(&JsonTimings {
+        modules: modules
+            .iter()
+            .map(|timing| JsonModuleTiming {
+                module: &timing.name,
+                path: &timing.path,
+                duration_ms: timing.duration.as_millis(),
+            })
+            .collect(),
+    })
+    .into_diagnostic()?;
+    contents.push('\n');
+    Ok(contents)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    fn timing(name: &str, millis: u64) -> ModuleTiming {
+        ModuleTiming {
+            name: name.into(),
+            path: format!("src/{name}.hs").into(),
+            duration: Duration::from_millis(millis),
+        }
+    }
+
+    #[test]
+    fn test_module_timings() {
+        let start = Instant::now();
+        assert_eq!(
+            module_timings(
+                vec![
+                    (
+                        start,
+                        "[1 of 2] Compiling A ( src/A.hs, interpreted )".into()
+                    ),
+                    (
+                        start + Duration::from_millis(300),
+                        "[2 of 2] Compiling B ( src/B.hs, interpreted ) [A changed]".into()
+                    ),
+                    (start + Duration::from_millis(400), "Not a module".into()),
+                ],
+                start + Duration::from_millis(1000)
+            ),
+            vec![
+                ModuleTiming {
+                    name: "A".into(),
+                    path: "src/A.hs".into(),
+                    duration: Duration::from_millis(300),
+                },
+                ModuleTiming {
+                    name: "B".into(),
+                    path: "src/B.hs".into(),
+                    duration: Duration::from_millis(700),
+                },
+            ]
+        );
+
+        assert_eq!(module_timings(vec![], start), vec![]);
+    }
+
+    #[test]
+    fn test_slowest() {
+        let timings = [timing("A", 10), timing("B", 30), timing("C", 20)];
+        assert_eq!(slowest(&timings, 2), vec![&timings[1], &timings[2]]);
+        assert_eq!(slowest(&timings, 5).len(), 3);
+    }
+
+    #[test]
+    fn test_csv_contents() {
+        assert_eq!(
+            csv_contents(&[
+                &timing("B", 30),
+                &timing("A", 10),
+                &timing("Weird,\"Name", 5)
+            ]),
+            indoc!(
+                r#"
+                module,path,duration_ms
+                B,src/B.hs,30
+                A,src/A.hs,10
+                "Weird,""Name","src/Weird,""Name.hs",5
+                "#
+            )
+        );
+    }
+
+    #[test]
+    fn test_json_contents() {
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(
+                &json_contents(&[&timing("B", 30), &timing("A", 10)]).unwrap()
+            )
+            .unwrap(),
+            serde_json::json!({
+                "modules": [
+                    {"module": "B", "path": "src/B.hs", "duration_ms": 30},
+                    {"module": "A", "path": "src/A.hs", "duration_ms": 10},
+                ]
+            })
+        );
+    }
+}
@@ -3,6 +3,8 @@ use async_dup::Mutex;
 use std::fmt::Debug;
 use std::io;
 use std::pin::Pin;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
 use std::task::Context;
 use std::task::Poll;
 use tokio::io::AsyncWrite;
@@ -17,7 +19,10 @@ use tokio_util::compat::TokioAsyncWriteCompatExt;
 /// A dynamically reconfigurable sink for `ghci` process output. Built for use in `GhciOpts`, but
 /// usable as a general purpose clonable [`AsyncWrite`]r.
 #[derive(Debug)]
-pub struct GhciWriter(Kind);
+pub struct GhciWriter {
+    kind: Kind,
+    mute: Option<Mute>,
+}
 
 #[derive(Debug)]
 enum Kind {
@@ -27,27 +32,56 @@ enum Kind {
     Sink(Sink),
 }
 
+/// A switch shared between [`GhciWriter`]s to temporarily discard their output.
+#[derive(Debug, Clone, Default)]
+pub struct Mute(std::sync::Arc<AtomicBool>);
+
+impl Mute {
+    /// Start or stop discarding output.
+    pub fn set(&self, muted: bool) {
+        self.0.store(muted, Ordering::SeqCst);
+    }
+
+    fn is_muted(&self) -> bool {
+        self.0.load(Ordering::SeqCst)
+    }
+}
+
 impl GhciWriter {
+    fn new(kind: Kind) -> Self {
+        Self { kind, mute: None }
+    }
+
     /// Write to `stdout`.
     pub fn stdout() -> Self {
-        Self(Kind::Stdout(tokio::io::stdout()))
+        Self::new(Kind::Stdout(tokio::io::stdout()))
     }
 
     /// Write to `stderr`.
     pub fn stderr() -> Self {
-        Self(Kind::Stderr(tokio::io::stderr()))
+        Self::new(Kind::Stderr(tokio::io::stderr()))
     }
 
     /// Write to an in-memory buffer.
     pub fn duplex_stream(duplex_stream: DuplexStream) -> Self {
-        Self(Kind::DuplexStream(
+        Self::new(Kind::DuplexStream(
             Arc::new(Mutex::new(duplex_stream.compat_write())).compat_write(),
         ))
     }
 
     /// Write to the void.
     pub fn sink() -> Self {
-        Self(Kind::Sink(tokio::io::sink()))
+        Self::new(Kind::Sink(tokio::io::sink()))
+    }
+
+    /// Discard output while the given [`Mute`] is set.
+    pub fn with_mute(mut self, mute: Mute) -> Self {
+        self.mute = Some(mute);
+        self
+    }
+
+    fn is_muted(&self) -> bool {
+        self.mute.as_ref().map(Mute::is_muted).unwrap_or(false)
     }
 }
 
@@ -57,7 +91,11 @@ impl AsyncWrite for GhciWriter {
         cx: &mut Context<'_>,
         buf: &[u8],
     ) -> Poll<Result<usize, io::Error>> {
-        match Pin::into_inner(self).0 {
+        let this = Pin::into_inner(self);
+        if this.is_muted() {
+            return Poll::Ready(Ok(buf.len()));
+        }
+        match this.kind {
             Kind::Stdout(ref mut x) => Pin::new(x).poll_write(cx, buf),
             Kind::Stderr(ref mut x) => Pin::new(x).poll_write(cx, buf),
             Kind::DuplexStream(ref mut x) => Pin::new(x).poll_write(cx, buf),
@@ -66,7 +104,7 @@ impl AsyncWrite for GhciWriter {
     }
 
     fn poll_flush(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
-        match Pin::into_inner(self).0 {
+        match Pin::into_inner(self).kind {
             Kind::Stdout(ref mut x) => Pin::new(x).poll_flush(cx),
             Kind::Stderr(ref mut x) => Pin::new(x).poll_flush(cx),
             Kind::DuplexStream(ref mut x) => Pin::new(x).poll_flush(cx),
@@ -75,7 +113,7 @@ impl AsyncWrite for GhciWriter {
     }
 
     fn poll_shutdown(self: Pin<&mut Self>, cx: &mut Context<'_>) -> Poll<Result<(), io::Error>> {
-        match Pin::into_inner(self).0 {
+        match Pin::into_inner(self).kind {
             Kind::Stdout(ref mut x) => Pin::new(x).poll_shutdown(cx),
             Kind::Stderr(ref mut x) => Pin::new(x).poll_shutdown(cx),
             Kind::DuplexStream(ref mut x) => Pin::new(x).poll_shutdown(cx),
@@ -86,11 +124,15 @@ impl AsyncWrite for GhciWriter {
 
 impl Clone for GhciWriter {
     fn clone(&self) -> Self {
-        match &self.0 {
-            Kind::Stdout(_) => Self::stdout(),
-            Kind::Stderr(_) => Self::stderr(),
-            Kind::DuplexStream(x) => Self(Kind::DuplexStream(x.clone())),
-            Kind::Sink(_) => Self::sink(),
+        let kind = match &self.kind {
+            Kind::Stdout(_) => Kind::Stdout(tokio::io::stdout()),
+            Kind::Stderr(_) => Kind::Stderr(tokio::io::stderr()),
+            Kind::DuplexStream(x) => Kind::DuplexStream(x.clone()),
+            Kind::Sink(_) => Kind::Sink(tokio::io::sink()),
+        };
+        Self {
+            kind,
+            mute: self.mute.clone(),
         }
     }
 }
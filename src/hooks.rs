@@ -14,6 +14,8 @@ use enum_iterator::Sequence;
 use indoc::indoc;
 use tokio::task::JoinHandle;
 
+use crate::event_stream::EventSender;
+use crate::event_stream::StreamEvent;
 use crate::ghci::GhciCommand;
 use crate::maybe_async_command::MaybeAsyncCommand;
 
@@ -183,7 +185,8 @@ pub enum Command {
 }
 
 impl Command {
-    fn kind(&self) -> CommandKind {
+    /// Get the kind of this command.
+    pub fn kind(&self) -> CommandKind {
         match self {
             Command::Ghci(_) => CommandKind::Ghci,
             Command::Shell(_) => CommandKind::Shell,
@@ -307,19 +310,50 @@ pub struct HookOpts {
 }
 
 impl HookOpts {
+    /// Add a hook which runs before the hooks given on the command line.
+    pub fn prepend(&mut self, hook: Hook<Command>) {
+        self.hooks.insert(0, hook);
+    }
+
     pub fn select(&self, event: LifecycleEvent) -> impl Iterator<Item = &Hook<Command>> {
         self.hooks.iter().filter(move |hook| hook.event == event)
     }
 
+    /// Get the hooks for a separate test session: the test commands, and the `ghci` commands run
+    /// after startup and restarts, which may set the session up for testing.
+    pub fn for_test_session(&self) -> Self {
+        Self {
+            hooks: self
+                .hooks
+                .iter()
+                .filter(|hook| {
+                    matches!(
+                        (hook.event, &hook.command),
+                        (LifecycleEvent::Test, _)
+                            | (
+                                LifecycleEvent::Startup(When::After)
+                                    | LifecycleEvent::Restart(When::After),
+                                Command::Ghci(_),
+                            )
+                    )
+                })
+                .cloned()
+                .collect(),
+        }
+    }
+
     pub async fn run_shell_hooks(
         &self,
         event: LifecycleEvent,
         handles: &mut Vec<JoinHandle<miette::Result<ExitStatus>>>,
+        events: &EventSender,
     ) -> miette::Result<()> {
         for hook in self.select(event) {
             if let Command::Shell(command) = &hook.command {
                 tracing::info!(%command, "Running {hook} command");
-                command.run_on(handles).await?;
+                events.send(StreamEvent::hook_started(hook));
+                let status = command.run_on(handles).await?;
+                events.send(StreamEvent::hook_finished(hook, status));
             }
         }
         Ok(())
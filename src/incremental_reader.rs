@@ -2,9 +2,11 @@
 
 use std::borrow::Cow;
 use std::pin::Pin;
+use std::time::Instant;
 
 use aho_corasick::AhoCorasick;
 use line_span::LineSpans;
+use miette::miette;
 use miette::IntoDiagnostic;
 use tokio::io::AsyncRead;
 use tokio::io::AsyncReadExt;
@@ -16,6 +18,9 @@ use crate::buffers::LINE_BUFFER_CAPACITY;
 use crate::buffers::SPLIT_UTF8_CODEPOINT_CAPACITY;
 use crate::buffers::VEC_BUFFER_CAPACITY;
 
+/// A callback for lines read by an [`IncrementalReader`].
+type LineCallback = Box<dyn Fn(&str) + Send + Sync>;
+
 /// A tool for incrementally reading from a stream like stdout (and forwarding that stream to a
 /// writer).
 ///
@@ -41,6 +46,13 @@ pub struct IncrementalReader<R, W> {
     /// We're not guaranteed that the data we read at one time is aligned on a UTF-8 boundary. If
     /// that's the case, we store the data here until we get more data.
     non_utf8: Vec<u8>,
+    /// Lines matching these patterns are recorded in `timestamped_lines`.
+    timestamp_patterns: Option<AhoCorasick>,
+    /// Lines matching `timestamp_patterns` which haven't been taken yet, with the time they were
+    /// read.
+    timestamped_lines: Vec<(Instant, String)>,
+    /// Called with each line matching `timestamp_patterns` as soon as it's read.
+    on_timestamped_line: Option<LineCallback>,
 }
 
 impl<R, W> IncrementalReader<R, W>
@@ -56,6 +68,9 @@ where
             lines: String::with_capacity(VEC_BUFFER_CAPACITY * LINE_BUFFER_CAPACITY),
             line: String::with_capacity(LINE_BUFFER_CAPACITY),
             non_utf8: Vec::with_capacity(SPLIT_UTF8_CODEPOINT_CAPACITY),
+            timestamp_patterns: None,
+            timestamped_lines: Vec::new(),
+            on_timestamped_line: None,
         }
     }
 
@@ -68,10 +83,34 @@ where
         }
     }
 
+    /// Record the time that lines containing any of the given `patterns` are read. Lines are
+    /// timestamped as soon as their newline is read, rather than when they're returned from
+    /// [`Self::read_until`].
+    ///
+    /// See [`Self::take_timestamped_lines`].
+    pub fn with_timestamped_lines(self, patterns: AhoCorasick) -> Self {
+        Self {
+            timestamp_patterns: Some(patterns),
+            ..self
+        }
+    }
+
+    /// Call `callback` with each line matching the patterns given to
+    /// [`Self::with_timestamped_lines`] as soon as it's read, replacing any previous callback.
+    pub fn on_timestamped_line(&mut self, callback: impl Fn(&str) + Send + Sync + 'static) {
+        self.on_timestamped_line = Some(Box::new(callback));
+    }
+
+    /// Take the timestamped lines read so far. See [`Self::with_timestamped_lines`].
+    pub fn take_timestamped_lines(&mut self) -> Vec<(Instant, String)> {
+        std::mem::take(&mut self.timestamped_lines)
+    }
+
     /// Read from the contained reader until a line beginning with one of the `end_marker` patterns
     /// is seen, returning the lines until the marker is found.
     ///
-    /// This calls [`Self::try_read_until`] in a loop.
+    /// This calls [`Self::try_read_until`] in a loop. Fails if the reader reaches EOF first, like
+    /// when the process writing to it exits.
     ///
     /// TODO: Should this even use `aho_corasick`? Might be overkill, and with the automaton
     /// construction cost it might not even be more efficient.
@@ -85,7 +124,7 @@ where
 
     /// Examines the internal buffer and reads at most once from the underlying reader. If a line
     /// beginning with one of the `end_marker` patterns is seen, the lines before the marker are
-    /// returned. Otherwise, nothing is returned.
+    /// returned. Otherwise, nothing is returned. Fails if the reader reaches EOF.
     pub async fn try_read_until(
         &mut self,
         opts: &mut ReadOpts<'_>,
@@ -98,7 +137,7 @@ where
         match self.reader.read(opts.buffer).await {
             Ok(0) => {
                 // EOF
-                Ok(None)
+                Err(miette!("Reached end of output while waiting for a prompt"))
             }
             Ok(n) => {
                 let decoded = self.decode(&opts.buffer[..n]);
@@ -282,6 +321,14 @@ where
 
         let line = std::mem::replace(&mut self.line, String::with_capacity(LINE_BUFFER_CAPACITY));
         tracing::debug!(line, "Read line");
+        if let Some(patterns) = &self.timestamp_patterns {
+            if patterns.find_anywhere(&line).is_some() {
+                self.timestamped_lines.push((Instant::now(), line.clone()));
+                if let Some(callback) = &self.on_timestamped_line {
+                    callback(&line);
+                }
+            }
+        }
         self.lines.push_str(&line);
         self.lines.push('\n');
 
@@ -448,6 +495,26 @@ mod tests {
         );
     }
 
+    /// The reader reaches EOF before the end marker is found.
+    #[tokio::test]
+    async fn test_read_until_eof() {
+        let fake_reader = FakeReader::with_byte_chunks([b"[1 of 1] Compiling MyLib\n".as_slice()]);
+
+        let mut reader = IncrementalReader::new(fake_reader).with_writer(tokio::io::sink());
+        let end_marker = AhoCorasick::from_anchored_patterns(["ghci> "]);
+        let mut buffer = vec![0; LINE_BUFFER_CAPACITY];
+
+        assert!(reader
+            .read_until(&mut ReadOpts {
+                end_marker: &end_marker,
+                find: FindAt::LineStart,
+                writing: WriteBehavior::Hide,
+                buffer: &mut buffer,
+            })
+            .await
+            .is_err());
+    }
+
     /// Same as `test_read_until` but with `FindAt::Anywhere`.
     #[tokio::test]
     async fn test_read_until_find_anywhere() {
@@ -487,6 +554,89 @@ mod tests {
         );
     }
 
+    /// Test that lines matching the timestamp patterns are recorded in the order they're read.
+    #[tokio::test]
+    async fn test_read_until_timestamped_lines() {
+        let fake_reader = FakeReader::with_byte_chunks([
+            b"[1 of 2] Compiling A ( A.hs, interpreted )\n".as_slice(),
+            b"[2 of 2] Compiling B ( B.hs, interpreted )\nOk, two modules loaded.\n",
+            b"ghci> ",
+        ]);
+
+        let mut reader = IncrementalReader::new(fake_reader)
+            .with_writer(tokio::io::sink())
+            .with_timestamped_lines(AhoCorasick::new(["] Compiling "]).unwrap());
+        let end_marker = AhoCorasick::from_anchored_patterns(["ghci> "]);
+        let mut buffer = vec![0; LINE_BUFFER_CAPACITY];
+
+        reader
+            .read_until(&mut ReadOpts {
+                end_marker: &end_marker,
+                find: FindAt::LineStart,
+                writing: WriteBehavior::Hide,
+                buffer: &mut buffer,
+            })
+            .await
+            .unwrap();
+
+        let lines = reader.take_timestamped_lines();
+        assert_eq!(
+            lines
+                .iter()
+                .map(|(_, line)| line.as_str())
+                .collect::<Vec<_>>(),
+            vec![
+                "[1 of 2] Compiling A ( A.hs, interpreted )",
+                "[2 of 2] Compiling B ( B.hs, interpreted )",
+            ]
+        );
+        assert!(lines[0].0 <= lines[1].0);
+        assert!(reader.take_timestamped_lines().is_empty());
+    }
+
+    /// Test that an `IncrementalReader` calls its callback with timestamped lines as soon as
+    /// they're read, before the `end_marker` is seen.
+    #[tokio::test]
+    async fn test_on_timestamped_line() {
+        let fake_reader = FakeReader::with_byte_chunks([
+            b"[1 of 2] Compiling A ( A.hs, interpreted )\n".as_slice(),
+            b"[2 of 2] Compiling B ( B.hs, interpreted )\nOk, two modules loaded.\n",
+            b"ghci> ",
+        ]);
+
+        let mut reader = IncrementalReader::new(fake_reader)
+            .with_writer(tokio::io::sink())
+            .with_timestamped_lines(AhoCorasick::new(["] Compiling "]).unwrap());
+        let seen = std::sync::Arc::new(std::sync::Mutex::new(Vec::new()));
+        reader.on_timestamped_line({
+            let seen = seen.clone();
+            move |line| seen.lock().unwrap().push(line.to_owned())
+        });
+        let end_marker = AhoCorasick::from_anchored_patterns(["ghci> "]);
+        let mut buffer = vec![0; LINE_BUFFER_CAPACITY];
+        let mut opts = ReadOpts {
+            end_marker: &end_marker,
+            find: FindAt::LineStart,
+            writing: WriteBehavior::Hide,
+            buffer: &mut buffer,
+        };
+
+        assert_eq!(reader.try_read_until(&mut opts).await.unwrap(), None);
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec!["[1 of 2] Compiling A ( A.hs, interpreted )"]
+        );
+
+        reader.read_until(&mut opts).await.unwrap();
+        assert_eq!(
+            *seen.lock().unwrap(),
+            vec![
+                "[1 of 2] Compiling A ( A.hs, interpreted )",
+                "[2 of 2] Compiling B ( B.hs, interpreted )",
+            ]
+        );
+    }
+
     /// Test that an `IncrementalReader` can read until an `end_marker` while only operating on
     /// data remaining in its internal buffer.
     #[tokio::test]
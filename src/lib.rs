@@ -19,16 +19,22 @@ pub mod clap_markdown;
 pub mod cli;
 mod clonable_command;
 mod command_ext;
+mod control_socket;
 mod cwd;
+mod dedent;
 mod event_filter;
+mod event_stream;
+mod file_uri;
 mod format_bulleted_list;
 mod ghci;
 mod haskell_source_file;
 mod hooks;
 mod ignore;
 mod incremental_reader;
+mod lsp;
 mod maybe_async_command;
 mod normal_path;
+mod repl;
 mod shutdown;
 mod string_case;
 mod tracing;
@@ -40,10 +46,19 @@ pub(crate) use cwd::current_dir_utf8;
 pub(crate) use format_bulleted_list::format_bulleted_list;
 pub(crate) use string_case::StringCase;
 
+pub use control_socket::run_control_socket;
+pub use control_socket::ControlSocketOpts;
+pub use event_stream::run_events_file;
+pub use event_stream::EventsFileOpts;
 pub use ghci::manager::run_ghci;
+pub use ghci::run_test_session;
 pub use ghci::Ghci;
 pub use ghci::GhciOpts;
 pub use ghci::GhciWriter;
+pub use ghci::TestSessionOpts;
+pub use lsp::run_lsp;
+pub use lsp::LspOpts;
+pub use repl::run_repl;
 pub use shutdown::ShutdownError;
 pub use shutdown::ShutdownHandle;
 pub use shutdown::ShutdownManager;
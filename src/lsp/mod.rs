@@ -0,0 +1,381 @@
+//! A language server which publishes `ghci` diagnostics to editors.
+//!
+//! The server speaks the [Language Server Protocol][lsp] over `stdin` and `stdout`. It doesn't
+//! handle any document synchronization; files are still reloaded by the file watcher, and the
+//! diagnostics from each compilation are published with `textDocument/publishDiagnostics`.
+//!
+//! [lsp]: https://microsoft.github.io/language-server-protocol/
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::io::BufReader;
+
+use camino::Utf8Path;
+use camino::Utf8PathBuf;
+use miette::IntoDiagnostic;
+use serde_json::json;
+use serde_json::Value;
+use tokio::io::AsyncWriteExt;
+use tokio::io::Stdout;
+use tokio::sync::mpsc;
+use tokio::sync::watch;
+use tracing::instrument;
+
+use crate::cli::Opts;
+use crate::file_uri::file_uri;
+use crate::ghci::CompilationLog;
+use crate::ghci::GhciOpts;
+use crate::shutdown::ShutdownHandle;
+
+mod protocol;
+use protocol::Diagnostic;
+use protocol::IncomingMessage;
+use protocol::PublishDiagnosticsParams;
+
+mod transport;
+use transport::encode_message;
+use transport::read_message;
+
+/// Options for [`run_lsp`]. This is like a lower-effort builder interface, mostly provided
+/// because Rust tragically lacks named arguments.
+pub struct LspOpts {
+    /// Receiver for the [`CompilationLog`] of each finished compilation.
+    pub compilation_log: watch::Receiver<CompilationLog>,
+}
+
+impl LspOpts {
+    /// Construct options for [`run_lsp`] from parsed command-line interface arguments as
+    /// [`Opts`].
+    ///
+    /// Returns `None` if the language server isn't enabled.
+    pub fn from_cli(opts: &Opts, ghci_opts: &GhciOpts) -> Option<Self> {
+        opts.lsp.then(|| Self {
+            compilation_log: ghci_opts.compilation_log.subscribe(),
+        })
+    }
+}
+
+/// Run a language server on `stdin` and `stdout`, publishing diagnostics from the `ghci` session.
+#[instrument(level = "debug", skip_all)]
+pub async fn run_lsp(mut handle: ShutdownHandle, mut opts: LspOpts) -> miette::Result<()> {
+    let mut server = LspServer {
+        stdout: tokio::io::stdout(),
+        publisher: DiagnosticsPublisher::new(crate::current_dir_utf8()?),
+        initialized: false,
+        shutdown_requested: false,
+    };
+
+    // Reads from `stdin` can't be canceled, so we read on a separate thread to avoid blocking
+    // shutdown.
+    let (message_sender, mut message_receiver) = mpsc::channel(8);
+    std::thread::spawn(move || {
+        let mut stdin = BufReader::new(std::io::stdin().lock());
+        loop {
+            let message = match read_message(&mut stdin) {
+                Ok(Some(message)) => Ok(message),
+                Ok(None) => break,
+                Err(err) => Err(err),
+            };
+            let failed = message.is_err();
+            if message_sender.blocking_send(message).is_err() || failed {
+                break;
+            }
+        }
+    });
+
+    loop {
+        tokio::select! {
+            _ = handle.on_shutdown_requested() => {
+                break;
+            }
+            message = message_receiver.recv() => {
+                let message = match message {
+                    Some(message) => message?,
+                    None => {
+                        tracing::debug!("Language client closed stdin");
+                        break;
+                    }
+                };
+                let was_initialized = server.initialized;
+                if !server.handle_message(&message).await? {
+                    break;
+                }
+                if server.initialized && !was_initialized {
+                    // Publish diagnostics from any compilations that finished before the client
+                    // was ready.
+                    let log = opts.compilation_log.borrow_and_update().clone();
+                    server.publish(&log).await?;
+                }
+            }
+            changed = opts.compilation_log.changed() => {
+                if changed.is_err() {
+                    // The `ghci` session is gone.
+                    break;
+                }
+                if server.initialized {
+                    let log = opts.compilation_log.borrow_and_update().clone();
+                    server.publish(&log).await?;
+                }
+            }
+        }
+    }
+
+    let _ = handle.request_shutdown();
+    Ok(())
+}
+
+/// The language server's state.
+struct LspServer {
+    /// Where to write messages to the language client.
+    stdout: Stdout,
+    /// Tracks which files have published diagnostics.
+    publisher: DiagnosticsPublisher,
+    /// Has the client sent the `initialized` notification?
+    initialized: bool,
+    /// Has the client sent the `shutdown` request?
+    shutdown_requested: bool,
+}
+
+impl LspServer {
+    /// Handle a message from the language client.
+    ///
+    /// Returns `false` if the server should exit.
+    #[instrument(level = "trace", skip(self))]
+    async fn handle_message(&mut self, message: &str) -> miette::Result<bool> {
+        let message = match serde_json::from_str::<IncomingMessage>(message) {
+            Ok(message) => message,
+            Err(err) => {
+                tracing::debug!("Failed to parse message from language client: {err}");
+                self.respond_error(Value::Null, protocol::PARSE_ERROR, err.to_string())
+                    .await?;
+                return Ok(true);
+            }
+        };
+
+        let method = message.method.as_deref().unwrap_or_default();
+        tracing::debug!(method, "Received message from language client");
+
+        match (message.id, method) {
+            (_, "exit") => {
+                return Ok(false);
+            }
+            (Some(id), _) if self.shutdown_requested => {
+                self.respond_error(id, protocol::INVALID_REQUEST, "Server is shutting down")
+                    .await?;
+            }
+            (Some(id), "initialize") => {
+                self.respond(
+                    id,
+                    json!({
+                        "capabilities": {},
+                        "serverInfo": {
+                            "name": "ghciwatch",
+                            "version": env!("CARGO_PKG_VERSION"),
+                        },
+                    }),
+                )
+                .await?;
+            }
+            (None, "initialized") => {
+                self.initialized = true;
+            }
+            (Some(id), "shutdown") => {
+                self.shutdown_requested = true;
+                self.respond(id, Value::Null).await?;
+            }
+            (Some(id), method) => {
+                self.respond_error(
+                    id,
+                    protocol::METHOD_NOT_FOUND,
+                    format!("Unsupported method {method:?}"),
+                )
+                .await?;
+            }
+            (None, _) => {
+                // Notifications we don't care about, like `textDocument/didOpen`.
+            }
+        }
+
+        Ok(true)
+    }
+
+    /// Publish the diagnostics from a compilation, clearing diagnostics for files which no longer
+    /// have any.
+    #[instrument(level = "debug", skip_all)]
+    async fn publish(&mut self, log: &CompilationLog) -> miette::Result<()> {
+        for params in self.publisher.update(log) {
+            tracing::debug!(
+                uri = params.uri,
+                diagnostics = params.diagnostics.len(),
+                "Publishing diagnostics"
+            );
+            self.write(&json!({
+                "jsonrpc": "2.0",
+                "method": "textDocument/publishDiagnostics",
+                "params": params,
+            }))
+            .await?;
+        }
+        Ok(())
+    }
+
+    async fn respond(&mut self, id: Value, result: Value) -> miette::Result<()> {
+        self.write(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "result": result,
+        }))
+        .await
+    }
+
+    async fn respond_error(
+        &mut self,
+        id: Value,
+        code: i64,
+        message: impl Into<String>,
+    ) -> miette::Result<()> {
+        self.write(&json!({
+            "jsonrpc": "2.0",
+            "id": id,
+            "error": {
+                "code": code,
+                "message": message.into(),
+            },
+        }))
+        .await
+    }
+
+    async fn write(&mut self, message: &Value) -> miette::Result<()> {
+        self.stdout
+            .write_all(&encode_message(message)?)
+            .await
+            .into_diagnostic()?;
+        self.stdout.flush().await.into_diagnostic()
+    }
+}
+
+/// Converts [`CompilationLog`]s into `textDocument/publishDiagnostics` parameters, keeping track
+/// of which files have diagnostics so that they can be cleared when they're fixed.
+struct DiagnosticsPublisher {
+    /// The directory diagnostic paths are relative to.
+    cwd: Utf8PathBuf,
+    /// Files we've published diagnostics for.
+    published: BTreeSet<Utf8PathBuf>,
+}
+
+impl DiagnosticsPublisher {
+    fn new(cwd: Utf8PathBuf) -> Self {
+        Self {
+            cwd,
+            published: Default::default(),
+        }
+    }
+
+    /// Get the parameters for the notifications to publish for a compilation.
+    fn update(&mut self, log: &CompilationLog) -> Vec<PublishDiagnosticsParams> {
+        let mut files: BTreeMap<Utf8PathBuf, Vec<Diagnostic>> = BTreeMap::new();
+        for diagnostic in &log.diagnostics {
+            match &diagnostic.path {
+                Some(path) => {
+                    files
+                        .entry(self.absolute_path(path))
+                        .or_default()
+                        .push(Diagnostic::from(diagnostic));
+                }
+                None => {
+                    tracing::debug!(%diagnostic, "Not publishing diagnostic without a path");
+                }
+            }
+        }
+
+        let published = files.keys().cloned().collect::<BTreeSet<_>>();
+        let mut params = Vec::new();
+        for path in self.published.difference(&published) {
+            params.push(PublishDiagnosticsParams {
+                uri: file_uri(path),
+                diagnostics: Vec::new(),
+            });
+        }
+        for (path, diagnostics) in files {
+            params.push(PublishDiagnosticsParams {
+                uri: file_uri(&path),
+                diagnostics,
+            });
+        }
+
+        self.published = published;
+        params
+    }
+
+    fn absolute_path(&self, path: &Utf8Path) -> Utf8PathBuf {
+        if path.is_absolute() {
+            path.to_owned()
+        } else {
+            self.cwd.join(path)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    use crate::ghci::parse::GhcDiagnostic;
+    use crate::ghci::parse::Severity;
+
+    #[test]
+    fn test_diagnostics_publisher() {
+        let mut publisher = DiagnosticsPublisher::new("/home/me/project".into());
+
+        let params = publisher.update(&CompilationLog {
+            summary: None,
+            diagnostics: vec![
+                GhcDiagnostic::new(Severity::Error, Some("src/A.hs"), "A1"),
+                GhcDiagnostic::new(Severity::Error, Some("/tmp/B.hs"), "B"),
+                GhcDiagnostic::new(Severity::Error, None, "No location"),
+                GhcDiagnostic::new(Severity::Error, Some("src/A.hs"), "A2"),
+            ],
+            ..Default::default()
+        });
+        assert_eq!(
+            params
+                .iter()
+                .map(|params| (params.uri.as_str(), params.diagnostics.len()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("file:///home/me/project/src/A.hs", 2),
+                ("file:///tmp/B.hs", 1),
+            ]
+        );
+
+        // Files that no longer have diagnostics are cleared.
+        let params = publisher.update(&CompilationLog {
+            summary: None,
+            diagnostics: vec![GhcDiagnostic::new(Severity::Error, Some("src/C.hs"), "C")],
+            ..Default::default()
+        });
+        assert_eq!(
+            params
+                .iter()
+                .map(|params| (params.uri.as_str(), params.diagnostics.len()))
+                .collect::<Vec<_>>(),
+            vec![
+                ("file:///home/me/project/src/A.hs", 0),
+                ("file:///tmp/B.hs", 0),
+                ("file:///home/me/project/src/C.hs", 1),
+            ]
+        );
+
+        let params = publisher.update(&CompilationLog::default());
+        assert_eq!(
+            params
+                .iter()
+                .map(|params| (params.uri.as_str(), params.diagnostics.len()))
+                .collect::<Vec<_>>(),
+            vec![("file:///home/me/project/src/C.hs", 0)]
+        );
+        assert!(publisher.update(&CompilationLog::default()).is_empty());
+    }
+}
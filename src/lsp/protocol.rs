@@ -0,0 +1,230 @@
+//! The subset of the Language Server Protocol types we use.
+//!
+//! See: <https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/>
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::dedent::dedent;
+use crate::ghci::parse;
+use crate::ghci::parse::GhcDiagnostic;
+use crate::ghci::parse::Severity;
+
+/// A JSON-RPC error code for unparseable messages.
+pub const PARSE_ERROR: i64 = -32700;
+/// A JSON-RPC error code for requests received after a `shutdown` request.
+pub const INVALID_REQUEST: i64 = -32600;
+/// A JSON-RPC error code for requests we don't support.
+pub const METHOD_NOT_FOUND: i64 = -32601;
+
+/// A request or notification from the language client.
+///
+/// Requests have an `id` and expect a response; notifications don't.
+#[derive(Debug, Clone, Deserialize)]
+pub struct IncomingMessage {
+    /// The request ID, if this is a request.
+    #[serde(default)]
+    pub id: Option<Value>,
+    /// The method name, like `initialize`. This is missing for responses to our own requests,
+    /// which we don't make.
+    #[serde(default)]
+    pub method: Option<String>,
+}
+
+/// The parameters for a `textDocument/publishDiagnostics` notification.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PublishDiagnosticsParams {
+    /// The `file://` URI of the file the diagnostics are for.
+    pub uri: String,
+    /// The file's diagnostics. An empty list clears the file's diagnostics.
+    pub diagnostics: Vec<Diagnostic>,
+}
+
+/// An LSP diagnostic.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Diagnostic {
+    /// The range the diagnostic applies to.
+    pub range: Range,
+    /// `1` for errors, `2` for warnings.
+    pub severity: u8,
+    /// The diagnostic's error code, or its warning flag if it has no code.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub code: Option<String>,
+    /// A link to the documentation for the error code.
+    #[serde(rename = "codeDescription", skip_serializing_if = "Option::is_none")]
+    pub code_description: Option<CodeDescription>,
+    /// Where the diagnostic came from.
+    pub source: &'static str,
+    /// The diagnostic's message.
+    pub message: String,
+}
+
+/// A link to the documentation for a diagnostic's error code.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CodeDescription {
+    /// The URL of the documentation.
+    pub href: String,
+}
+
+impl From<&GhcDiagnostic> for Diagnostic {
+    fn from(diagnostic: &GhcDiagnostic) -> Self {
+        let range = if diagnostic.span.is_zero() {
+            Range::default()
+        } else {
+            let start = diagnostic.span.start();
+            let end = diagnostic.span.end();
+            Range {
+                start: Position::from(start),
+                // GHC's end columns are inclusive and LSP's are exclusive, so we only need to
+                // convert the line to 0-based.
+                end: Position {
+                    line: end.line().saturating_sub(1),
+                    character: end.column(),
+                },
+            }
+        };
+
+        Self {
+            range,
+            severity: match diagnostic.severity {
+                Severity::Error => 1,
+                Severity::Warning => 2,
+            },
+            code: diagnostic.code.clone().or_else(|| diagnostic.flag.clone()),
+            code_description: diagnostic
+                .error_index_url()
+                .map(|href| CodeDescription { href }),
+            source: "ghciwatch",
+            message: dedent_message(&diagnostic.message),
+        }
+    }
+}
+
+/// Remove the indentation GHC puts before diagnostic messages.
+///
+/// The first line (which may contain an error code) is left alone, and the rest of the lines are
+/// dedented by their common indentation.
+fn dedent_message(message: &str) -> String {
+    let mut lines = message.lines();
+    let first = lines.next().unwrap_or_default().trim();
+    let rest = lines.collect::<Vec<_>>();
+    format!("{first}\n{}", dedent(&rest)).trim().to_owned()
+}
+
+/// A range in a text document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Range {
+    /// The start position.
+    pub start: Position,
+    /// The end position, exclusive.
+    pub end: Position,
+}
+
+/// A position in a text document.
+///
+/// LSP positions are 0-based and count columns in UTF-16 code units by default. GHC counts columns
+/// in characters, which is the same outside of the astral planes.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct Position {
+    /// 0-based line number.
+    pub line: usize,
+    /// 0-based column number.
+    pub character: usize,
+}
+
+impl From<parse::Position> for Position {
+    fn from(position: parse::Position) -> Self {
+        Self {
+            line: position.line().saturating_sub(1),
+            character: position.column().saturating_sub(1),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use indoc::indoc;
+    use pretty_assertions::assert_eq;
+
+    use crate::ghci::parse::parse_ghc_messages;
+
+    #[test]
+    fn test_diagnostic_from_ghc() {
+        let diagnostic = parse_ghc_messages(indoc!(
+            "
+            src/MyLib.hs:4:11-16: error: [GHC-83865]
+                • Couldn't match type ‘[Char]’ with ‘()’
+                  Expected: ()
+                    Actual: String
+            "
+        ))
+        .unwrap()
+        .pop()
+        .unwrap()
+        .into_diagnostic()
+        .unwrap();
+
+        assert_eq!(
+            Diagnostic::from(&diagnostic),
+            Diagnostic {
+                range: Range {
+                    start: Position {
+                        line: 3,
+                        character: 10
+                    },
+                    end: Position {
+                        line: 3,
+                        character: 16
+                    },
+                },
+                severity: 1,
+                code: Some("GHC-83865".into()),
+                code_description: Some(CodeDescription {
+                    href: "https://errors.haskell.org/messages/GHC-83865".into(),
+                }),
+                source: "ghciwatch",
+                message: indoc!(
+                    "
+                    [GHC-83865]
+                    • Couldn't match type ‘[Char]’ with ‘()’
+                      Expected: ()
+                        Actual: String"
+                )
+                .into(),
+            }
+        );
+
+        assert_eq!(
+            Diagnostic::from(&GhcDiagnostic {
+                severity: Severity::Warning,
+                path: Some("A.hs".into()),
+                span: Default::default(),
+                code: None,
+                flag: Some("-Wunused-matches".into()),
+                message: "Oh no".into(),
+                colored: None,
+            }),
+            Diagnostic {
+                range: Default::default(),
+                severity: 2,
+                code: Some("-Wunused-matches".into()),
+                code_description: None,
+                source: "ghciwatch",
+                message: "Oh no".into(),
+            }
+        );
+    }
+
+    #[test]
+    fn test_dedent_message() {
+        assert_eq!(dedent_message("Oh no"), "Oh no");
+        assert_eq!(dedent_message("\n    Oh\n      no\n"), "Oh\n  no");
+        assert_eq!(
+            dedent_message("[GHC-88464]\n    Variable not in scope: x\n\n    Suggested fix: y\n"),
+            "[GHC-88464]\nVariable not in scope: x\n\nSuggested fix: y"
+        );
+    }
+}
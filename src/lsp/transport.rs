@@ -0,0 +1,98 @@
+//! The LSP base protocol: JSON-RPC messages framed with a `Content-Length` header.
+//!
+//! See: <https://microsoft.github.io/language-server-protocol/specifications/lsp/3.17/specification/#baseProtocol>
+
+use std::io::BufRead;
+
+use miette::miette;
+use miette::Context;
+use miette::IntoDiagnostic;
+use serde::Serialize;
+
+/// Read a single message's content from the given reader.
+///
+/// Returns `None` if the reader is closed before a message starts.
+pub fn read_message(reader: &mut impl BufRead) -> miette::Result<Option<String>> {
+    let mut content_length = None;
+    let mut line = String::new();
+
+    loop {
+        line.clear();
+        if reader.read_line(&mut line).into_diagnostic()? == 0 {
+            return if content_length.is_none() {
+                Ok(None)
+            } else {
+                Err(miette!(
+                    "Language client closed the stream in a message header"
+                ))
+            };
+        }
+
+        let header = line.trim_end_matches(['\r', '\n']);
+        if header.is_empty() {
+            break;
+        }
+
+        // Header names are case-insensitive. We ignore `Content-Type`, which only has one
+        // meaningful value.
+        if let Some((name, value)) = header.split_once(':') {
+            if name.trim().eq_ignore_ascii_case("Content-Length") {
+                content_length = Some(
+                    value
+                        .trim()
+                        .parse::<usize>()
+                        .into_diagnostic()
+                        .wrap_err_with(|| format!("Invalid `Content-Length` header: {header:?}"))?,
+                );
+            }
+        }
+    }
+
+    let content_length =
+        content_length.ok_or_else(|| miette!("Message is missing a `Content-Length` header"))?;
+    let mut content = vec![0; content_length];
+    reader.read_exact(&mut content).into_diagnostic()?;
+    String::from_utf8(content)
+        .into_diagnostic()
+        .wrap_err("Message content isn't valid UTF-8")
+        .map(Some)
+}
+
+/// Serialize and frame a message for writing.
+pub fn encode_message(message: &impl Serialize) -> miette::Result<Vec<u8>> {
+    let content = serde_json::to_vec(message).into_diagnostic()?;
+    let mut encoded = format!("Content-Length: {}\r\n\r\n", content.len()).into_bytes();
+    encoded.extend(content);
+    Ok(encoded)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use pretty_assertions::assert_eq;
+
+    #[test]
+    fn test_read_message() {
+        let mut input = "Content-Length: 2\r\n\r\n{}\
+            content-length: 5\r\nContent-Type: application/vscode-jsonrpc; charset=utf-8\r\n\r\n[1,2]"
+            .as_bytes();
+        assert_eq!(read_message(&mut input).unwrap().unwrap(), "{}");
+        assert_eq!(read_message(&mut input).unwrap().unwrap(), "[1,2]");
+        assert_eq!(read_message(&mut input).unwrap(), None);
+
+        // Negative cases.
+        assert!(read_message(&mut "Content-Length: 2\r\n".as_bytes()).is_err());
+        assert!(read_message(&mut "Content-Length: two\r\n\r\n{}".as_bytes()).is_err());
+        assert!(read_message(&mut "Content-Type: text\r\n\r\n{}".as_bytes()).is_err());
+        assert!(read_message(&mut "Content-Length: 10\r\n\r\n{}".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn test_encode_message() {
+        assert_eq!(
+            String::from_utf8(encode_message(&[1, 2]).unwrap()).unwrap(),
+            "Content-Length: 5\r\n\r\n[1,2]"
+        );
+    }
+}
@@ -9,11 +9,20 @@ use std::time::Duration;
 use clap::CommandFactory;
 use clap::Parser;
 use ghciwatch::cli;
+use ghciwatch::run_control_socket;
+use ghciwatch::run_events_file;
 use ghciwatch::run_ghci;
+use ghciwatch::run_lsp;
+use ghciwatch::run_repl;
+use ghciwatch::run_test_session;
 use ghciwatch::run_tui;
 use ghciwatch::run_watcher;
+use ghciwatch::ControlSocketOpts;
+use ghciwatch::EventsFileOpts;
 use ghciwatch::GhciOpts;
+use ghciwatch::LspOpts;
 use ghciwatch::ShutdownManager;
+use ghciwatch::TestSessionOpts;
 use ghciwatch::TracingOpts;
 use ghciwatch::WatcherOpts;
 use tokio::sync::mpsc;
@@ -53,8 +62,12 @@ async fn main() -> miette::Result<()> {
 
     let (ghci_sender, ghci_receiver) = mpsc::channel(32);
 
-    let (ghci_opts, maybe_ghci_reader) = GhciOpts::from_cli(&opts)?;
+    let (mut ghci_opts, maybe_ghci_reader) = GhciOpts::from_cli(&opts)?;
+    let test_session_opts = TestSessionOpts::from_cli(&opts, &mut ghci_opts, ghci_sender.clone());
     let watcher_opts = WatcherOpts::from_cli(&opts);
+    let control_socket_opts = ControlSocketOpts::from_cli(&opts, &ghci_opts);
+    let lsp_opts = LspOpts::from_cli(&opts, &ghci_opts);
+    let events_file_opts = EventsFileOpts::from_cli(&opts, &ghci_opts);
 
     let mut manager = ShutdownManager::with_timeout(Duration::from_secs(1));
 
@@ -70,6 +83,44 @@ async fn main() -> miette::Result<()> {
             .await;
     }
 
+    if let Some(control_socket_opts) = control_socket_opts {
+        let ghci_sender = ghci_sender.clone();
+        manager
+            .spawn("run_control_socket", |handle| {
+                run_control_socket(handle, ghci_sender, control_socket_opts)
+            })
+            .await;
+    }
+
+    if let Some(events_file_opts) = events_file_opts {
+        manager
+            .spawn("run_events_file", |handle| {
+                run_events_file(handle, events_file_opts)
+            })
+            .await;
+    }
+
+    if opts.repl {
+        let ghci_sender = ghci_sender.clone();
+        manager
+            .spawn("run_repl", |handle| run_repl(handle, ghci_sender))
+            .await;
+    }
+
+    if let Some(test_session_opts) = test_session_opts {
+        manager
+            .spawn("run_test_session", |handle| {
+                run_test_session(handle, test_session_opts)
+            })
+            .await;
+    }
+
+    if let Some(lsp_opts) = lsp_opts {
+        manager
+            .spawn("run_lsp", |handle| run_lsp(handle, lsp_opts))
+            .await;
+    }
+
     manager
         .spawn("run_ghci", |handle| {
             run_ghci(handle, ghci_opts, ghci_receiver)
@@ -118,25 +118,25 @@ impl MaybeAsyncCommand {
 
     /// Run this command.
     ///
-    /// If it's a synchronous command, report its status. Otherwise, add the [`JoinHandle`] for its
-    /// task to the given list of handles.
+    /// If it's a synchronous command, report and return its status. Otherwise, add the
+    /// [`JoinHandle`] for its task to the given list of handles and return `None`.
     pub async fn run_on(
         &self,
         handles: &mut Vec<JoinHandle<miette::Result<ExitStatus>>>,
-    ) -> miette::Result<()> {
+    ) -> miette::Result<Option<ExitStatus>> {
         match self.status().await {
             MaybeAsyncCommandStatus::Sync(result) => {
                 // If we failed to execute the program, that's an actual error, but if the
                 // program failed on its own, we'll log and move on.
-                result?;
+                result.map(Some)
             }
             MaybeAsyncCommandStatus::Async(join_handle) => {
                 // If the program is running asynchronously, we'll store the `JoinHandle`
                 // so we don't kill it and so we can log when it completes.
                 handles.push(join_handle);
+                Ok(None)
             }
         }
-        Ok(())
     }
 }
 
@@ -0,0 +1,100 @@
+//! Passing commands typed in the terminal through to the `ghci` session.
+
+use std::io::BufRead;
+
+use miette::miette;
+use tokio::sync::mpsc;
+use tracing::instrument;
+
+use crate::ghci::manager::GhciEvent;
+use crate::ghci::GhciCommand;
+use crate::shutdown::ShutdownHandle;
+
+/// Read lines from `stdin` and send them to the `ghci` session as commands.
+///
+/// Commands are queued and run between reloads. When `stdin` is closed, `ghciwatch` keeps running
+/// without reading commands.
+#[instrument(level = "debug", skip_all)]
+pub async fn run_repl(
+    mut handle: ShutdownHandle,
+    ghci_sender: mpsc::Sender<GhciEvent>,
+) -> miette::Result<()> {
+    // Reads from `tokio::io::stdin()` can't be canceled, which would keep the runtime from
+    // shutting down until the user presses enter, so we read from a separate thread instead.
+    let (line_sender, mut line_receiver) = mpsc::channel(32);
+    std::thread::Builder::new()
+        .name("repl_stdin".to_owned())
+        .spawn(move || {
+            for line in std::io::stdin().lock().lines() {
+                match line {
+                    Ok(line) => {
+                        if line_sender.blocking_send(line).is_err() {
+                            break;
+                        }
+                    }
+                    Err(err) => {
+                        tracing::debug!("Failed to read from stdin: {err}");
+                        break;
+                    }
+                }
+            }
+        })
+        .map_err(|err| miette!("Failed to start reading from stdin: {err}"))?;
+
+    loop {
+        tokio::select! {
+            _ = handle.on_shutdown_requested() => {
+                break;
+            }
+            line = line_receiver.recv() => {
+                let line = match line {
+                    Some(line) => line,
+                    None => {
+                        tracing::debug!("stdin closed; no longer reading commands");
+                        break;
+                    }
+                };
+                if line.trim().is_empty() {
+                    continue;
+                }
+                if is_quit_command(&line) {
+                    tracing::warn!("Press Ctrl-C to stop ghciwatch instead of quitting ghci");
+                    continue;
+                }
+                ghci_sender
+                    .send(GhciEvent::Command(GhciCommand::from(line)))
+                    .await
+                    .map_err(|err| miette!("Failed to send command to ghci: {err}"))?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Is `line` a `:quit` command, which `ghci` accepts abbreviated down to `:q`?
+fn is_quit_command(line: &str) -> bool {
+    match line.split_whitespace().next() {
+        Some(command) => command
+            .strip_prefix(':')
+            .map(|name| !name.is_empty() && "quit".starts_with(name))
+            .unwrap_or(false),
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_quit_command() {
+        assert!(is_quit_command(":q"));
+        assert!(is_quit_command("  :quit  "));
+        assert!(is_quit_command(":qui"));
+        assert!(!is_quit_command(":"));
+        assert!(!is_quit_command(":type quit"));
+        assert!(!is_quit_command(":quitter"));
+        assert!(!is_quit_command("quit"));
+    }
+}
@@ -151,7 +151,10 @@ impl EventHandler {
         } else {
             tracing::trace!(?events, "Processed events");
             self.ghci_sender
-                .send(GhciEvent::Reload { events })
+                .send(GhciEvent::Reload {
+                    events,
+                    force: false,
+                })
                 .await
                 .into_diagnostic()?;
         }
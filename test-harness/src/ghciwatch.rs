@@ -573,6 +573,12 @@ impl GhciWatch {
         Ok(())
     }
 
+    /// Wait until `ghciwatch` removes deleted modules from the `ghci` session.
+    pub async fn wait_until_unadd(&mut self) -> miette::Result<()> {
+        self.wait_for_log(BaseMatcher::ghci_unadd()).await?;
+        Ok(())
+    }
+
     /// Wait until `ghciwatch` restarts the `ghci` session.
     pub async fn wait_until_restart(&mut self) -> miette::Result<()> {
         // TODO: It would be nice to verify which modules have been deleted/moved.
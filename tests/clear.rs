@@ -2,13 +2,17 @@ use indoc::indoc;
 
 use test_harness::test;
 use test_harness::BaseMatcher;
+use test_harness::Fs;
 use test_harness::GhciWatchBuilder;
 
 /// Test that `ghciwatch` clears the screen on reloads and restarts when `--clear` is used.
 #[test]
 async fn clears_on_reload_and_restart() {
     let mut session = GhciWatchBuilder::new("tests/data/simple")
-        .with_arg("--clear")
+        .before_start(
+            |project_root| async move { Fs::new().touch(project_root.join(".ghci")).await },
+        )
+        .with_args(["--clear", "--watch", ".ghci"])
         .with_log_filter("ghciwatch::ghci[clear]=trace")
         .start()
         .await
@@ -43,19 +47,7 @@ async fn clears_on_reload_and_restart() {
         .await
         .unwrap();
 
-    {
-        // Rename the module and fix the module name to match the new path.
-        let module_path = session.path("src/MyModule.hs");
-        let new_path = session.path("src/MyCoolModule.hs");
-        session.fs_mut().disable_load_bearing_sleep();
-        session.fs().rename(&module_path, &new_path).await.unwrap();
-        session
-            .fs()
-            .replace(&new_path, "module MyModule", "module MyCoolModule")
-            .await
-            .unwrap();
-        session.fs_mut().reset_load_bearing_sleep();
-    }
+    session.fs().touch(session.path(".ghci")).await.unwrap();
 
     session.wait_for_log("Clearing the screen").await.unwrap();
     session
@@ -138,3 +138,165 @@ async fn can_write_error_log_compilation_errors() {
     "#]]
     .assert_eq(&error_contents);
 }
+
+/// Test that `ghciwatch --errors ... --error-file-format json` can write a JSON error log.
+#[test]
+async fn can_write_json_error_log() {
+    let error_path = "ghcid.json";
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--errors", error_path, "--error-file-format", "json"])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    let error_path = session.path(error_path);
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+    let error_contents = session
+        .fs()
+        .read(&error_path)
+        .await
+        .expect("ghciwatch writes ghcid.json");
+    expect![[r#"
+        {
+          "summary": {
+            "result": "ok",
+            "modules_loaded": 3
+          },
+          "diagnostics": []
+        }
+    "#]]
+    .assert_eq(&error_contents);
+}
+
+/// Test that warnings from modules which weren't recompiled in a reload stay in the error log.
+#[test]
+async fn error_log_keeps_warnings_from_modules_not_recompiled() {
+    let error_path = "ghcid.txt";
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--errors", error_path])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    let error_path = session.path(error_path);
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+
+    session
+        .fs()
+        .write(
+            session.path("src/My/Warning.hs"),
+            indoc!(
+                "
+                module My.Warning (warning) where
+
+                warning :: Int
+                warning = 2
+                  where
+                    unused = ()
+                "
+            ),
+        )
+        .await
+        .unwrap();
+    session
+        .wait_until_add()
+        .await
+        .expect("ghciwatch loads new modules");
+    session
+        .wait_for_log(BaseMatcher::span_close().in_leaf_spans(["error_log_write"]))
+        .await
+        .expect("ghciwatch writes ghcid.txt");
+
+    // Only `MyModule` is recompiled.
+    session
+        .fs()
+        .append(session.path("src/MyModule.hs"), "\n-- A comment.\n")
+        .await
+        .unwrap();
+    session
+        .wait_until_reload()
+        .await
+        .expect("ghciwatch reloads on changes");
+    session
+        .wait_for_log(BaseMatcher::span_close().in_leaf_spans(["error_log_write"]))
+        .await
+        .expect("ghciwatch writes ghcid.txt");
+
+    let error_contents = session
+        .fs()
+        .read(&error_path)
+        .await
+        .expect("ghciwatch writes ghcid.txt");
+    assert!(
+        error_contents.starts_with("All good (4 modules)\nsrc/My/Warning.hs:6:5: warning:"),
+        "{error_contents}"
+    );
+    assert!(
+        error_contents.contains("Defined but not used: `unused'"),
+        "{error_contents}"
+    );
+}
+
+/// Test that `--hide-diagnostics` rules remove diagnostics from the error log.
+#[test]
+async fn error_log_hides_diagnostics() {
+    let error_path = "ghcid.txt";
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args([
+            "--errors",
+            error_path,
+            "--hide-diagnostics",
+            "src/My/Warning.hs",
+        ])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    let error_path = session.path(error_path);
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+
+    session
+        .fs()
+        .write(
+            session.path("src/My/Warning.hs"),
+            indoc!(
+                "
+                module My.Warning (warning) where
+
+                warning :: Int
+                warning = 2
+                  where
+                    unused = ()
+                "
+            ),
+        )
+        .await
+        .unwrap();
+    session
+        .wait_until_add()
+        .await
+        .expect("ghciwatch loads new modules");
+    session
+        .wait_for_log(BaseMatcher::message(
+            "Hid 1 diagnostic matching `--hide-diagnostics`",
+        ))
+        .await
+        .expect("ghciwatch hides the warning");
+    session
+        .wait_for_log(BaseMatcher::span_close().in_leaf_spans(["error_log_write"]))
+        .await
+        .expect("ghciwatch writes ghcid.txt");
+
+    let error_contents = session
+        .fs()
+        .read(&error_path)
+        .await
+        .expect("ghciwatch writes ghcid.txt");
+    expect!["All good (4 modules)\n"].assert_eq(&error_contents);
+}
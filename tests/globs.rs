@@ -163,12 +163,12 @@ async fn can_ignore_restart_paths() {
         .expect("ghciwatch doesn't restart when ignored globs are changed");
 }
 
-/// Test that `ghciwatch` restarts when a Haskell module is removed, even if a `--restart-glob`
-/// explicitly ignores the path.
+/// Test that `ghciwatch` removes a Haskell module from the session with `:unadd` when it's
+/// removed, even if a `--restart-glob` explicitly ignores the path.
 ///
 /// This is needed to work around a `ghci` bug: https://gitlab.haskell.org/ghc/ghc/-/issues/11596
 #[test]
-async fn can_restart_on_module_change_even_if_ignored() {
+async fn can_unadd_removed_module_even_if_ignored() {
     let mut session = GhciWatchBuilder::new("tests/data/simple")
         .with_args(["--restart-glob", "!**/*.hs"])
         .start()
@@ -187,7 +187,11 @@ async fn can_restart_on_module_change_even_if_ignored() {
         .unwrap();
 
     session
-        .wait_until_restart()
+        .wait_until_unadd()
+        .await
+        .expect("ghciwatch unadds Haskell files when they're removed");
+    session
+        .wait_for_log(BaseMatcher::reload_completes().but_not(BaseMatcher::restart()))
         .await
-        .expect("ghciwatch restarts when Haskell files are removed");
+        .expect("ghciwatch doesn't restart when Haskell files are removed");
 }
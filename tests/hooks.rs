@@ -16,6 +16,10 @@ use test_harness::SpanMatcher;
 async fn can_run_hooks() {
     let mut session = GhciWatchBuilder::new("tests/data/simple")
         .with_args([
+            // Removing a module only restarts the session if it matches a restart glob.
+            "--restart-glob",
+            "**/MyModule.hs",
+            // ---
             "--before-startup-shell",
             "async:touch before-startup-1",
             "--before-startup-shell",
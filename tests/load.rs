@@ -1,6 +1,7 @@
 use indoc::indoc;
 
 use test_harness::test;
+use test_harness::BaseMatcher;
 use test_harness::GhciWatch;
 
 /// Test that `ghciwatch` can start up `ghci` and load a session.
@@ -43,3 +44,73 @@ async fn can_load_new_module() {
         .await
         .expect("ghciwatch loads new modules");
 }
+
+/// Test that when `ghciwatch` adds several new modules and one of them fails to compile, only the
+/// modules which loaded are recorded as targets, and the failing module is added again once it's
+/// fixed.
+#[test]
+async fn can_load_new_modules_when_one_fails() {
+    let mut session = GhciWatch::new("tests/data/simple")
+        .await
+        .expect("ghciwatch starts");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+    session
+        .fs()
+        .write(
+            session.path("src/My/Good.hs"),
+            indoc!(
+                "module My.Good (good) where
+                good :: ()
+                good = ()
+                "
+            ),
+        )
+        .await
+        .unwrap();
+    let bad_path = session.path("src/My/Bad.hs");
+    session
+        .fs()
+        .write(
+            &bad_path,
+            indoc!(
+                "module My.Bad (bad) where
+                bad :: ()
+                bad = True
+                "
+            ),
+        )
+        .await
+        .unwrap();
+    session
+        .wait_until_add()
+        .await
+        .expect("ghciwatch loads new modules");
+    session
+        .wait_for_log(
+            BaseMatcher::message("Module failed to load; not adding it to targets")
+                .with_field("path", "src/My/Bad.hs"),
+        )
+        .await
+        .expect("ghciwatch doesn't record the bad module as a target");
+    session
+        .wait_for_log(BaseMatcher::compilation_failed())
+        .await
+        .expect("ghciwatch fails to compile the bad module");
+
+    session
+        .fs()
+        .replace(&bad_path, "bad = True", "bad = ()")
+        .await
+        .unwrap();
+    session
+        .wait_until_add()
+        .await
+        .expect("ghciwatch adds the fixed module again");
+    session
+        .wait_for_log(BaseMatcher::compilation_succeeded())
+        .await
+        .expect("ghciwatch compiles the fixed module");
+}
@@ -0,0 +1,117 @@
+use expect_test::expect;
+use indoc::indoc;
+
+use test_harness::test;
+use test_harness::BaseMatcher;
+use test_harness::GhciWatchBuilder;
+
+/// Test that `ghciwatch --module-graph ...` writes the module graph and updates it when modules
+/// are added.
+#[test]
+async fn can_write_module_graph() {
+    let graph_path = "modules.json";
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args([
+            "--module-graph",
+            graph_path,
+            "--module-graph-format",
+            "json",
+        ])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    let graph_path = session.path(graph_path);
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+    let graph_contents = session
+        .fs()
+        .read(&graph_path)
+        .await
+        .expect("ghciwatch writes modules.json");
+    expect![[r#"
+        {
+          "modules": [
+            {
+              "name": "MyLib",
+              "path": "src/MyLib.hs",
+              "imports": []
+            },
+            {
+              "name": "MyModule",
+              "path": "src/MyModule.hs",
+              "imports": []
+            },
+            {
+              "name": "TestMain",
+              "path": "test/TestMain.hs",
+              "imports": []
+            }
+          ]
+        }
+    "#]]
+    .assert_eq(&graph_contents);
+
+    session
+        .fs()
+        .write(
+            session.path("src/My/Module.hs"),
+            indoc!(
+                "
+                module My.Module (myIdent) where
+
+                import qualified MyLib
+                import Data.List (sort)
+
+                myIdent :: IO ()
+                myIdent = MyLib.someFunc
+                "
+            ),
+        )
+        .await
+        .unwrap();
+    session
+        .wait_until_add()
+        .await
+        .expect("ghciwatch loads new modules");
+    session
+        .wait_for_log(BaseMatcher::span_close().in_leaf_spans(["module_graph_write"]))
+        .await
+        .expect("ghciwatch writes modules.json");
+
+    let graph_contents = session
+        .fs()
+        .read(&graph_path)
+        .await
+        .expect("ghciwatch writes modules.json");
+    expect![[r#"
+        {
+          "modules": [
+            {
+              "name": "My.Module",
+              "path": "src/My/Module.hs",
+              "imports": [
+                "MyLib"
+              ]
+            },
+            {
+              "name": "MyLib",
+              "path": "src/MyLib.hs",
+              "imports": []
+            },
+            {
+              "name": "MyModule",
+              "path": "src/MyModule.hs",
+              "imports": []
+            },
+            {
+              "name": "TestMain",
+              "path": "test/TestMain.hs",
+              "imports": []
+            }
+          ]
+        }
+    "#]]
+    .assert_eq(&graph_contents);
+}
@@ -2,7 +2,7 @@ use test_harness::test;
 use test_harness::BaseMatcher;
 use test_harness::GhciWatch;
 
-/// Test that `ghciwatch` can restart correctly when modules are removed and added (i.e., renamed)
+/// Test that `ghciwatch` can reload correctly when modules are removed and added (i.e., renamed)
 /// at the same time.
 #[test]
 async fn can_compile_renamed_module() {
@@ -23,9 +23,13 @@ async fn can_compile_renamed_module() {
         .unwrap();
 
     session
-        .wait_until_restart()
+        .wait_until_unadd()
         .await
-        .expect("ghciwatch restarts on module move");
+        .expect("ghciwatch unadds the old module on module move");
+    session
+        .wait_until_add()
+        .await
+        .expect("ghciwatch adds the new module on module move");
 
     session
         .wait_for_log(BaseMatcher::compilation_failed())
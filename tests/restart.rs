@@ -3,10 +3,11 @@ use indoc::indoc;
 use test_harness::test;
 use test_harness::BaseMatcher;
 use test_harness::GhciWatch;
+use test_harness::GhciWatchBuilder;
 
-/// Test that `ghciwatch` can restart `ghci` after a module is moved.
+/// Test that `ghciwatch` can reload `ghci` after a module is moved.
 #[test]
-async fn can_restart_after_module_move() {
+async fn can_reload_after_module_move() {
     let mut session = GhciWatch::new("tests/data/simple")
         .await
         .expect("ghciwatch starts");
@@ -48,9 +49,9 @@ async fn can_restart_after_module_move() {
     }
 
     session
-        .wait_until_restart()
+        .wait_until_unadd()
         .await
-        .expect("ghciwatch restarts ghci");
+        .expect("ghciwatch unadds the old module");
 
     session
         .wait_for_log(BaseMatcher::module_compiling("My.CoolModule"))
@@ -62,3 +63,86 @@ async fn can_restart_after_module_move() {
         .await
         .unwrap();
 }
+
+/// Test that `ghciwatch` restarts `ghci` after `--restart-every` reloads.
+#[test]
+async fn can_restart_every_n_reloads() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--restart-every", "2"])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+
+    let module_path = session.path("src/MyModule.hs");
+    for _ in 0..2 {
+        session
+            .fs()
+            .append(&module_path, "\n-- A change\n")
+            .await
+            .unwrap();
+        session
+            .wait_until_reload()
+            .await
+            .expect("ghciwatch reloads on changes");
+    }
+
+    session
+        .wait_for_log(BaseMatcher::message("^ghci has reloaded 2 times"))
+        .await
+        .unwrap();
+    session
+        .wait_for_log(BaseMatcher::message("^Restarting ghci$"))
+        .await
+        .expect("ghciwatch restarts ghci");
+    session
+        .wait_for_log(BaseMatcher::compilation_succeeded())
+        .await
+        .unwrap();
+}
+
+/// Test that `ghciwatch --standby` switches to the standby session when restarting.
+#[test]
+async fn can_restart_with_standby_session() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--standby", "--restart-every", "1"])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+    session
+        .wait_for_log(BaseMatcher::message("^Standby ghci session is ready$"))
+        .await
+        .expect("ghciwatch starts a standby session");
+
+    session
+        .fs()
+        .append(session.path("src/MyModule.hs"), "\n-- A change\n")
+        .await
+        .unwrap();
+    session
+        .wait_until_reload()
+        .await
+        .expect("ghciwatch reloads on changes");
+
+    session
+        .wait_for_log(BaseMatcher::message(
+            "^Switching to the standby ghci session$",
+        ))
+        .await
+        .expect("ghciwatch switches to the standby session");
+    session
+        .wait_for_log(BaseMatcher::compilation_succeeded())
+        .await
+        .unwrap();
+    session
+        .wait_for_log(BaseMatcher::message("^Standby ghci session is ready$"))
+        .await
+        .expect("ghciwatch starts another standby session");
+}
@@ -0,0 +1,48 @@
+use indoc::indoc;
+
+use test_harness::test;
+use test_harness::BaseMatcher;
+use test_harness::GhciWatchBuilder;
+
+/// Test that `ghciwatch --run ...` runs a command after compiling, and interrupts it to reload.
+#[test]
+async fn can_run_and_interrupt_command() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--run", "Control.Concurrent.threadDelay 600000000"])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+    let running = BaseMatcher::message("^Running `Control.Concurrent.threadDelay 600000000`$");
+    session
+        .assert_logged_or_wait(running.clone())
+        .await
+        .expect("ghciwatch runs the command");
+
+    session.checkpoint();
+    session
+        .fs()
+        .append(
+            session.path("src/MyModule.hs"),
+            indoc!(
+                "
+
+                hello = 1 :: Integer
+
+                "
+            ),
+        )
+        .await
+        .unwrap();
+    session
+        .wait_until_reload()
+        .await
+        .expect("ghciwatch interrupts the command to reload");
+    session
+        .wait_for_log(running)
+        .await
+        .expect("ghciwatch runs the command again");
+}
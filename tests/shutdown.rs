@@ -29,9 +29,10 @@ async fn can_shutdown_gracefully() {
     assert!(status.success(), "ghciwatch exits successfully");
 }
 
-/// Test that `ghciwatch` can gracefully shutdown when the `ghci` process is unexpectedly killed.
+/// Test that `ghciwatch` restarts `ghci` when the `ghci` process is unexpectedly killed, and can
+/// still gracefully shutdown afterwards.
 #[test]
-async fn can_shutdown_gracefully_when_ghci_killed() {
+async fn restarts_when_ghci_killed() {
     let mut session = GhciWatch::new("tests/data/simple")
         .await
         .expect("ghciwatch starts");
@@ -59,10 +60,21 @@ async fn can_shutdown_gracefully_when_ghci_killed() {
     signal::kill(Pid::from_raw(pid), Signal::SIGKILL).expect("Failed to kill ghci");
 
     session
-        .wait_for_log("^ghci exited:")
+        .wait_for_log("^ghci exited unexpectedly:")
         .await
         .expect("ghci exits");
-    session.wait_for_log("^Shutdown requested$").await.unwrap();
+    session
+        .wait_for_log("^Restarting ghci in ")
+        .await
+        .expect("ghciwatch restarts ghci");
+    session.checkpoint();
+    session
+        .wait_until_started()
+        .await
+        .expect("ghciwatch reloads ghci");
+
+    signal::kill(Pid::from_raw(session.pid() as i32), Signal::SIGINT)
+        .expect("Failed to send Ctrl-C to ghciwatch");
     session
         .wait_for_log("^All tasks completed successfully$")
         .await
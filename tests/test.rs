@@ -2,6 +2,10 @@ use expect_test::expect;
 use test_harness::test;
 use test_harness::BaseMatcher;
 use test_harness::GhciWatchBuilder;
+use tokio::io::AsyncBufReadExt;
+use tokio::io::AsyncWriteExt;
+use tokio::io::BufReader;
+use tokio::net::UnixStream;
 
 /// Test that `ghciwatch --test ...` can run a test suite.
 #[test]
@@ -43,3 +47,90 @@ async fn can_run_test_suite_on_reload() {
     "#]]
     .assert_eq(&error_contents);
 }
+
+/// Test that `ghciwatch --test-session` runs tests in a separate `ghci` session.
+#[test]
+async fn can_run_tests_in_test_session() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--test-ghci", "TestMain.testMain", "--test-session"])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+    session
+        .wait_for_log(BaseMatcher::message("^Starting ghci test session$"))
+        .await
+        .expect("ghciwatch starts the test session");
+    session
+        .wait_for_log("Finished running tests")
+        .await
+        .expect("ghciwatch runs the test suite in the test session");
+
+    session
+        .fs()
+        .touch(session.path("src/MyLib.hs"))
+        .await
+        .expect("Can touch file");
+    session
+        .wait_for_log("Finished running tests")
+        .await
+        .expect("ghciwatch runs the test suite again after reloading");
+}
+
+/// Test that files changed while tests requested over the control socket are running are reloaded
+/// once the tests finish.
+#[test]
+async fn can_reload_after_tests_from_control_socket() {
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args([
+            "--test-ghci",
+            "Control.Concurrent.threadDelay 2000000 >> TestMain.testMain",
+            "--control-socket",
+        ])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+    session
+        .wait_for_log("Finished running tests")
+        .await
+        .expect("ghciwatch runs the test suite");
+
+    let mut socket = UnixStream::connect(session.path(".ghciwatch.sock"))
+        .await
+        .expect("Can connect to control socket");
+    socket
+        .write_all(b"test\n")
+        .await
+        .expect("Can write to control socket");
+    let mut response = String::new();
+    BufReader::new(socket)
+        .read_line(&mut response)
+        .await
+        .expect("Can read from control socket");
+    assert_eq!(response, "{\"response\":\"ok\"}\n");
+    session
+        .wait_for_log(BaseMatcher::message("^Running test command$"))
+        .await
+        .expect("ghciwatch runs the test suite");
+
+    session
+        .fs()
+        .touch(session.path("src/MyLib.hs"))
+        .await
+        .expect("Can touch file");
+    session
+        .wait_for_log("Finished running tests")
+        .await
+        .expect("ghciwatch finishes running the test suite");
+    session
+        .wait_until_reload()
+        .await
+        .expect("ghciwatch reloads after the test suite finishes");
+}
@@ -0,0 +1,70 @@
+use test_harness::test;
+use test_harness::BaseMatcher;
+use test_harness::Fs;
+use test_harness::GhciWatchBuilder;
+
+/// Test that `ghciwatch` interrupts eval commands which don't finish within `--eval-timeout`,
+/// and reports the timeout in the error log.
+#[test]
+async fn can_time_out_eval_commands() {
+    let module_path = "src/MyModule.hs";
+    let error_path = "ghcid.txt";
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args([
+            "--enable-eval",
+            "--eval-timeout",
+            "1s",
+            "--errors",
+            error_path,
+        ])
+        .before_start(move |path| async move {
+            Fs::new()
+                .append(
+                    path.join(module_path),
+                    "\n-- $> Control.Concurrent.threadDelay 60000000\n",
+                )
+                .await
+        })
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    let module_path = session.path(module_path);
+    let error_path = session.path(error_path);
+
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+    session
+        .wait_for_log(BaseMatcher::message(
+            r"^Eval command didn't finish within 1s \(`--eval-timeout`\); interrupting ghci$",
+        ))
+        .await
+        .expect("ghciwatch times out eval commands");
+    let error_contents = session
+        .fs()
+        .read(&error_path)
+        .await
+        .expect("ghciwatch writes ghcid.txt");
+    assert!(
+        error_contents.contains(
+            "src/MyModule.hs:6:1: error: Eval command didn't finish within 1s (`--eval-timeout`)"
+        ),
+        "{error_contents}"
+    );
+
+    // `ghci` can still reload after being interrupted.
+    session
+        .fs()
+        .append(&module_path, "\n-- A change\n")
+        .await
+        .unwrap();
+    session
+        .wait_until_reload()
+        .await
+        .expect("ghciwatch reloads after a timeout");
+    session
+        .wait_for_log(BaseMatcher::compilation_succeeded())
+        .await
+        .unwrap();
+}
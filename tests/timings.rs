@@ -0,0 +1,43 @@
+use test_harness::test;
+use test_harness::GhciWatchBuilder;
+
+/// Test that `ghciwatch --timings-file ...` writes how long each module took to compile.
+#[test]
+async fn can_write_timings_file() {
+    let timings_path = "timings.csv";
+    let mut session = GhciWatchBuilder::new("tests/data/simple")
+        .with_args(["--timings-file", timings_path])
+        .start()
+        .await
+        .expect("ghciwatch starts");
+    let timings_path = session.path(timings_path);
+    session
+        .wait_until_ready()
+        .await
+        .expect("ghciwatch loads ghci");
+    let timings_contents = session
+        .fs()
+        .read(&timings_path)
+        .await
+        .expect("ghciwatch writes timings.csv");
+
+    let mut lines = timings_contents.lines();
+    assert_eq!(lines.next(), Some("module,path,duration_ms"));
+    // Durations vary from run to run, so we only check the modules.
+    let mut modules = lines
+        .map(|line| {
+            let (module, rest) = line.split_once(',').expect("line has a module");
+            let (path, _duration) = rest.split_once(',').expect("line has a path");
+            (module, path)
+        })
+        .collect::<Vec<_>>();
+    modules.sort();
+    assert_eq!(
+        modules,
+        vec![
+            ("MyLib", "src/MyLib.hs"),
+            ("MyModule", "src/MyModule.hs"),
+            ("TestMain", "test/TestMain.hs"),
+        ]
+    );
+}